@@ -0,0 +1,308 @@
+use crate::evm_core::context::evm_context::EvmContext;
+use crate::evm_core::context::spec::SpecId;
+use crate::evm_core::gas::blob::max_blob_gas_per_block;
+use crate::evm_core::storage::EvmState;
+use crate::evm_core::transaction::transact::transact;
+use crate::evm_core::utils::error::RunnerError;
+
+use super::receipt::{logs_bloom, receipts_root, Bloom, Receipt};
+use super::system::{apply_beacon_root_call, apply_block_hash_call, apply_withdrawals};
+use super::types::{Block, BlockTransaction};
+
+//交易未声明chainId时默认主网
+const DEFAULT_CHAIN_ID: u64 = 1;
+
+/// Receipts and totals of an executed block.
+#[derive(Debug)]
+pub struct BlockResult {
+    pub receipts: Vec<Receipt>,
+    pub gas_used: u64,
+    pub blob_gas_used: u64,
+    /// Union of the receipt blooms.
+    pub logs_bloom: Bloom,
+    /// Root of `receipts`, `None` unless `gas_exact`: with intrinsic-only gas
+    /// the cumulative gas in the receipts, and so the root, is wrong.
+    pub receipts_root: Option<[u8; 32]>,
+    /// Whether every receipt has exact gas. Otherwise `gas_used` counts
+    /// intrinsic gas only and differs from a real chain.
+    pub gas_exact: bool,
+    /// `(header, executed)` gas used when the block header disagrees with
+    /// the execution. Only set by `execute_block*` for headers with `gasUsed`.
+    pub gas_mismatch: Option<(u64, u64)>,
+}
+
+/// Execute every transaction of `block` in order against `state`.
+///
+/// The block environment and fork come from the header. `chain_id` falls back
+/// to the one carried by the block's transactions.
+pub fn execute_block(
+    state: &mut EvmState,
+    block: &Block,
+    chain_id: Option<u64>,
+) -> Result<BlockResult, RunnerError> {
+    let chain_id = chain_id.or(block.chain_id()).unwrap_or(DEFAULT_CHAIN_ID);
+    let evm_context = block.header.evm_context(chain_id, block.header.spec_id());
+    execute_block_with_context(state, block, &evm_context)
+}
+
+/// Execute `block` with an explicit block environment, e.g. to force a fork.
+///
+/// System calls (EIP-4788, EIP-2935) run before the transactions and
+/// withdrawals (EIP-4895) after them. An invalid transaction aborts the
+/// block. `state` then keeps the effects of the transactions before it.
+pub fn execute_block_with_context(
+    state: &mut EvmState,
+    block: &Block,
+    evm_context: &EvmContext,
+) -> Result<BlockResult, RunnerError> {
+    let spec_id = evm_context.spec_id;
+    if let Some(root) = block.header.parent_beacon_block_root {
+        if spec_id.is_enabled_in(SpecId::Cancun) {
+            apply_beacon_root_call(state, evm_context, root)?;
+        }
+    }
+    if spec_id.is_enabled_in(SpecId::Prague) {
+        apply_block_hash_call(state, evm_context, block.header.parent_hash)?;
+    }
+
+    let mut builder = BlockBuilder::new(block.header.gas_limit);
+    for (index, block_tx) in block.transactions.iter().enumerate() {
+        builder
+            .execute_transaction(state, evm_context, block_tx)
+            .map_err(|e| match e {
+                RunnerError::BlockGasLimitReached(_) | RunnerError::BlockBlobGasLimitReached(_) => e,
+                e => RunnerError::InvalidBlockTransaction(index, Box::new(e)),
+            })?;
+    }
+
+    if spec_id.is_enabled_in(SpecId::Shanghai) {
+        apply_withdrawals(state, &block.withdrawals);
+        state.finalize_transaction(true, spec_id);
+    }
+
+    let mut result = builder.finish();
+    // 操作码不计量gas 执行了代码的区块通常对不上 明确报告而不是静默给出错误的根
+    if block.header.gas_used != 0 && block.header.gas_used != result.gas_used {
+        result.gas_mismatch = Some((block.header.gas_used, result.gas_used));
+    }
+    Ok(result)
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                BlockBuilder                                */
+/* -------------------------------------------------------------------------- */
+/// Runs transactions one at a time into a block, keeping the running totals.
+///
+/// An invalid transaction is rejected without touching `state` or the totals,
+/// so callers building their own blocks can skip it and carry on.
+#[derive(Debug)]
+pub struct BlockBuilder {
+    gas_limit: u64,
+    receipts: Vec<Receipt>,
+    cumulative_gas_used: u64,
+    blob_gas_used: u64,
+    log_index: u64,
+}
+
+impl BlockBuilder {
+    pub fn new(gas_limit: u64) -> Self {
+        Self {
+            gas_limit,
+            receipts: Vec::new(),
+            cumulative_gas_used: 0,
+            blob_gas_used: 0,
+            log_index: 0,
+        }
+    }
+
+    /// Execute `block_tx` as the next transaction of the block.
+    ///
+    /// Block limit errors carry the index the transaction would have had.
+    pub fn execute_transaction(
+        &mut self,
+        state: &mut EvmState,
+        evm_context: &EvmContext,
+        block_tx: &BlockTransaction,
+    ) -> Result<&Receipt, RunnerError> {
+        let tx = &block_tx.transaction;
+        let index = self.receipts.len();
+        // 交易gas上限不能超过区块剩余gas
+        if self.cumulative_gas_used + tx.gas_limit() > self.gas_limit {
+            return Err(RunnerError::BlockGasLimitReached(index));
+        }
+        if self.blob_gas_used + tx.blob_gas() > max_blob_gas_per_block(evm_context.spec_id) {
+            return Err(RunnerError::BlockBlobGasLimitReached(index));
+        }
+
+        // EIP-658之前收据记录交易后的状态根 需要完整的状态
+        let pre_byzantium = !evm_context.spec_id.is_enabled_in(SpecId::Byzantium);
+        if pre_byzantium {
+            state.load_all()?;
+        }
+
+        let mut result = transact(state, evm_context, tx.clone())?;
+        self.cumulative_gas_used += result.gas_used;
+        self.blob_gas_used += result.blob_gas_used;
+
+        // 日志序号在整个区块内递增
+        for log in result.logs.iter_mut() {
+            log.transaction_hash = block_tx.hash;
+            log.transaction_index = index as u64;
+            log.log_index = self.log_index;
+            self.log_index += 1;
+        }
+        self.receipts.push(Receipt {
+            tx_type: tx.tx_type(),
+            transaction_hash: block_tx.hash,
+            success: result.success,
+            state_root: pre_byzantium.then(|| state.cached_state_root()),
+            gas_used: result.gas_used,
+            cumulative_gas_used: self.cumulative_gas_used,
+            gas_exact: result.gas_exact,
+            logs_bloom: logs_bloom(&result.logs),
+            logs: result.logs,
+            contract_address: result.contract_address,
+            blob_gas_used: result.blob_gas_used,
+            blob_gas_price: result.blob_gas_price,
+        });
+        Ok(&self.receipts[index])
+    }
+
+    pub fn gas_used(&self) -> u64 {
+        self.cumulative_gas_used
+    }
+
+    pub fn finish(self) -> BlockResult {
+        let gas_exact = self.receipts.iter().all(|receipt| receipt.gas_exact);
+        BlockResult {
+            gas_used: self.cumulative_gas_used,
+            blob_gas_used: self.blob_gas_used,
+            logs_bloom: logs_bloom(self.receipts.iter().flat_map(|receipt| &receipt.logs)),
+            receipts_root: gas_exact.then(|| receipts_root(&self.receipts)),
+            gas_exact,
+            gas_mismatch: None,
+            receipts: self.receipts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::AccountState;
+    use crate::evm_core::utils::byte_operate::u64_to_u256_array;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const BOB: [u8; 20] = [0xb0; 20];
+    const CONTRACT: [u8; 20] = [0xcc; 20];
+    const MINER: [u8; 20] = [0xc0; 20];
+
+    // London区块 ALICE向BOB转账两次 每笔21000 gas
+    const BLOCK: &str = r#"{
+        "number": "0x10",
+        "hash": "0x1010101010101010101010101010101010101010101010101010101010101010",
+        "miner": "0xc0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0",
+        "timestamp": "0x64",
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0xa410",
+        "difficulty": "0x1",
+        "baseFeePerGas": "0x7",
+        "transactions": [
+            {
+                "type": "0x0",
+                "hash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+                "from": "0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1",
+                "to": "0xb0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0",
+                "nonce": "0x0",
+                "gas": "0x5208",
+                "gasPrice": "0xa",
+                "value": "0x3",
+                "input": "0x",
+                "chainId": "0x1"
+            },
+            {
+                "type": "0x0",
+                "hash": "0x2222222222222222222222222222222222222222222222222222222222222222",
+                "from": "0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1",
+                "to": "0xb0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0",
+                "nonce": "0x1",
+                "gas": "0x5208",
+                "gasPrice": "0xa",
+                "value": "0x4",
+                "input": "0x",
+                "chainId": "0x1"
+            }
+        ]
+    }"#;
+
+    fn state() -> EvmState {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: u64_to_u256_array(1_000_000),
+                ..Default::default()
+            },
+        );
+        // SSTORE(0, 1)
+        db.insert_account(CONTRACT, AccountState::default());
+        db.insert_code(CONTRACT, vec![0x60, 0x01, 0x60, 0x00, 0x55]);
+        EvmState::with_database(db)
+    }
+
+    #[test]
+    fn replays_block_fixture() {
+        let block = Block::from_json(BLOCK).unwrap();
+        assert_eq!(block.header.spec_id(), SpecId::London);
+
+        let mut state = state();
+        let result = execute_block(&mut state, &block, None).unwrap();
+
+        assert_eq!(result.receipts.len(), 2);
+        assert!(result.receipts.iter().all(|receipt| receipt.success));
+        assert_eq!(result.receipts[1].transaction_hash, [0x22; 32]);
+        assert_eq!(result.receipts[0].cumulative_gas_used, 21_000);
+        assert_eq!(result.receipts[1].cumulative_gas_used, 42_000);
+        assert_eq!(result.gas_used, 42_000);
+        assert!(result.gas_exact);
+        assert_eq!(result.gas_mismatch, None);
+        assert_eq!(result.logs_bloom, [0u8; 256]);
+        // 两个成功且没有日志的legacy收据 由独立实现算出
+        assert_eq!(
+            hex::encode(result.receipts_root.unwrap()),
+            "d95b673818fa493deec414e01e610d97ee287c9421c8eff4102b1647c1a184e4"
+        );
+
+        // 价格10 base fee 7 小费3
+        assert_eq!(state.accounts[&BOB].balance, u64_to_u256_array(7));
+        assert_eq!(state.accounts[&MINER].balance, u64_to_u256_array(42_000 * 3));
+        assert_eq!(
+            state.accounts[&ALICE].balance,
+            u64_to_u256_array(1_000_000 - 42_000 * 10 - 7)
+        );
+        assert_eq!(state.accounts[&ALICE].nonce, 2);
+    }
+
+    #[test]
+    fn reports_gas_mismatch() {
+        // 第二笔交易改为调用合约 执行了代码 gas只计intrinsic
+        let json = BLOCK
+            .replace("\"gasUsed\": \"0xa410\"", "\"gasUsed\": \"0xf9ac\"")
+            .replace(
+                "\"to\": \"0xb0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0\",\n                \"nonce\": \"0x1\",\n                \"gas\": \"0x5208\"",
+                "\"to\": \"0xcccccccccccccccccccccccccccccccccccccccc\",\n                \"nonce\": \"0x1\",\n                \"gas\": \"0xc350\"",
+            );
+        let block = Block::from_json(&json).unwrap();
+        assert_eq!(block.transactions[1].transaction.to(), Some(CONTRACT));
+
+        let mut state = state();
+        let result = execute_block(&mut state, &block, None).unwrap();
+
+        assert!(!result.gas_exact);
+        assert!(!result.receipts[1].gas_exact);
+        assert_eq!(result.receipts_root, None);
+        assert_eq!(result.gas_mismatch, Some((0xf9ac, 42_000)));
+        assert_eq!(state.sload(CONTRACT, [0u8; 32]).unwrap(), u64_to_u256_array(1));
+    }
+}
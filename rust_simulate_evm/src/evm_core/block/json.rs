@@ -0,0 +1,261 @@
+/*
+解析 eth_getBlockByNumber(number, true) 返回的区块JSON
+数值均为十六进制字符串 交易需为完整对象而非哈希
+*/
+use serde_json::Value;
+
+use crate::evm_core::transaction::types::{
+    AccessListItem, Authorization, Transaction, TxEip1559, TxEip2930, TxEip4844, TxEip7702,
+    TxLegacy,
+};
+use crate::evm_core::utils::byte_operate::{
+    hex_to_address, hex_to_bytes, hex_to_u128, hex_to_u64, hex_to_word,
+};
+use crate::evm_core::utils::error::RunnerError;
+
+use super::types::{Block, BlockHeader, BlockTransaction, Withdrawal};
+
+impl Block {
+    /// Parse a block in `eth_getBlockByNumber` format with full transaction objects.
+    ///
+    /// Both the bare block object and a JSON-RPC response wrapping it are accepted.
+    pub fn from_json(json: &str) -> Result<Self, RunnerError> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| RunnerError::InvalidBlock(e.to_string()))?;
+        Self::from_json_value(&value)
+    }
+
+    pub fn from_json_value(value: &Value) -> Result<Self, RunnerError> {
+        let block = value.get("result").unwrap_or(value);
+        let header = parse_header(block)?;
+
+        let mut transactions = Vec::new();
+        if let Some(txs) = block.get("transactions") {
+            let txs = txs
+                .as_array()
+                .ok_or_else(|| invalid("transactions is not an array"))?;
+            for tx in txs {
+                if !tx.is_object() {
+                    return Err(invalid("transactions must be full objects, not hashes"));
+                }
+                transactions.push(BlockTransaction {
+                    hash: optional(tx, "hash", hex_to_word)?.unwrap_or([0u8; 32]),
+                    transaction: parse_transaction(tx)?,
+                    raw: Vec::new(),
+                });
+            }
+        }
+
+        let mut withdrawals = Vec::new();
+        for withdrawal in array(block, "withdrawals")? {
+            withdrawals.push(Withdrawal {
+                index: required(withdrawal, "index", hex_to_u64)?,
+                validator_index: required(withdrawal, "validatorIndex", hex_to_u64)?,
+                address: required(withdrawal, "address", hex_to_address)?,
+                amount: required(withdrawal, "amount", hex_to_u64)?,
+            });
+        }
+
+        Ok(Block {
+            header,
+            transactions,
+            withdrawals,
+        })
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                   Header                                   */
+/* -------------------------------------------------------------------------- */
+fn parse_header(block: &Value) -> Result<BlockHeader, RunnerError> {
+    Ok(BlockHeader {
+        number: required(block, "number", hex_to_u64)?,
+        hash: optional(block, "hash", hex_to_word)?.unwrap_or_default(),
+        parent_hash: optional(block, "parentHash", hex_to_word)?.unwrap_or_default(),
+        coinbase: required(block, "miner", hex_to_address)?,
+        timestamp: required(block, "timestamp", hex_to_u64)?,
+        gas_limit: required(block, "gasLimit", hex_to_u64)?,
+        gas_used: optional(block, "gasUsed", hex_to_u64)?.unwrap_or_default(),
+        difficulty: optional(block, "difficulty", hex_to_word)?.unwrap_or_default(),
+        mix_hash: optional(block, "mixHash", hex_to_word)?.unwrap_or_default(),
+        state_root: optional(block, "stateRoot", hex_to_word)?.unwrap_or_default(),
+        transactions_root: optional(block, "transactionsRoot", hex_to_word)?.unwrap_or_default(),
+        receipts_root: optional(block, "receiptsRoot", hex_to_word)?.unwrap_or_default(),
+        base_fee_per_gas: optional(block, "baseFeePerGas", hex_to_u128)?,
+        withdrawals_root: optional(block, "withdrawalsRoot", hex_to_word)?,
+        blob_gas_used: optional(block, "blobGasUsed", hex_to_u64)?,
+        excess_blob_gas: optional(block, "excessBlobGas", hex_to_u64)?,
+        parent_beacon_block_root: optional(block, "parentBeaconBlockRoot", hex_to_word)?,
+        requests_hash: optional(block, "requestsHash", hex_to_word)?,
+    })
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                 Transaction                                */
+/* -------------------------------------------------------------------------- */
+/// Parse a transaction object as returned by `eth_getTransactionByHash`.
+pub fn parse_transaction(tx: &Value) -> Result<Transaction, RunnerError> {
+    let tx_type = optional(tx, "type", hex_to_u64)?.unwrap_or(0);
+    let from = required(tx, "from", hex_to_address)?;
+    let nonce = required(tx, "nonce", hex_to_u64)?;
+    let gas_limit = required(tx, "gas", hex_to_u64)?;
+    let to = optional(tx, "to", hex_to_address)?;
+    let value = optional(tx, "value", hex_to_word)?.unwrap_or_default();
+    let input = optional(tx, "input", hex_to_bytes)?.unwrap_or_default();
+
+    let transaction = match tx_type {
+        0x00 => {
+            // 未给出chainId时由 v = chain_id * 2 + 35 + y_parity 推出
+            let chain_id = match optional(tx, "chainId", hex_to_u64)? {
+                Some(chain_id) => Some(chain_id),
+                None => optional(tx, "v", hex_to_u64)?
+                    .filter(|v| *v >= 35)
+                    .map(|v| (v - 35) / 2),
+            };
+            Transaction::Legacy(TxLegacy {
+                from,
+                chain_id,
+                nonce,
+                gas_price: required(tx, "gasPrice", hex_to_u128)?,
+                gas_limit,
+                to,
+                value,
+                input,
+            })
+        }
+        0x01 => Transaction::Eip2930(TxEip2930 {
+            from,
+            chain_id: required(tx, "chainId", hex_to_u64)?,
+            nonce,
+            gas_price: required(tx, "gasPrice", hex_to_u128)?,
+            gas_limit,
+            to,
+            value,
+            input,
+            access_list: parse_access_list(tx)?,
+        }),
+        0x02 => Transaction::Eip1559(TxEip1559 {
+            from,
+            chain_id: required(tx, "chainId", hex_to_u64)?,
+            nonce,
+            max_priority_fee_per_gas: required(tx, "maxPriorityFeePerGas", hex_to_u128)?,
+            max_fee_per_gas: required(tx, "maxFeePerGas", hex_to_u128)?,
+            gas_limit,
+            to,
+            value,
+            input,
+            access_list: parse_access_list(tx)?,
+        }),
+        0x03 => Transaction::Eip4844(TxEip4844 {
+            from,
+            chain_id: required(tx, "chainId", hex_to_u64)?,
+            nonce,
+            max_priority_fee_per_gas: required(tx, "maxPriorityFeePerGas", hex_to_u128)?,
+            max_fee_per_gas: required(tx, "maxFeePerGas", hex_to_u128)?,
+            gas_limit,
+            to: to.ok_or_else(|| invalid("blob transaction without to"))?,
+            value,
+            input,
+            access_list: parse_access_list(tx)?,
+            max_fee_per_blob_gas: required(tx, "maxFeePerBlobGas", hex_to_u128)?,
+            blob_versioned_hashes: strings(tx, "blobVersionedHashes")?
+                .into_iter()
+                .map(hex_to_word)
+                .collect::<Result<_, _>>()?,
+        }),
+        0x04 => Transaction::Eip7702(TxEip7702 {
+            from,
+            chain_id: required(tx, "chainId", hex_to_u64)?,
+            nonce,
+            max_priority_fee_per_gas: required(tx, "maxPriorityFeePerGas", hex_to_u128)?,
+            max_fee_per_gas: required(tx, "maxFeePerGas", hex_to_u128)?,
+            gas_limit,
+            to: to.ok_or_else(|| invalid("set-code transaction without to"))?,
+            value,
+            input,
+            access_list: parse_access_list(tx)?,
+            authorization_list: parse_authorization_list(tx)?,
+        }),
+        other => return Err(RunnerError::TransactionTypeNotSupported(other as u8)),
+    };
+    Ok(transaction)
+}
+
+fn parse_access_list(tx: &Value) -> Result<Vec<AccessListItem>, RunnerError> {
+    let mut access_list = Vec::new();
+    for item in array(tx, "accessList")? {
+        access_list.push(AccessListItem {
+            address: required(item, "address", hex_to_address)?,
+            storage_keys: strings(item, "storageKeys")?
+                .into_iter()
+                .map(hex_to_word)
+                .collect::<Result<_, _>>()?,
+        });
+    }
+    Ok(access_list)
+}
+
+fn parse_authorization_list(tx: &Value) -> Result<Vec<Authorization>, RunnerError> {
+    let mut authorization_list = Vec::new();
+    for item in array(tx, "authorizationList")? {
+        // 部分节点用v代替yParity
+        let y_parity = match optional(item, "yParity", hex_to_u64)? {
+            Some(y_parity) => y_parity,
+            None => required(item, "v", hex_to_u64)?,
+        };
+        authorization_list.push(Authorization {
+            chain_id: required(item, "chainId", hex_to_u64)?,
+            address: required(item, "address", hex_to_address)?,
+            nonce: required(item, "nonce", hex_to_u64)?,
+            y_parity: y_parity as u8,
+            r: required(item, "r", hex_to_word)?,
+            s: required(item, "s", hex_to_word)?,
+        });
+    }
+    Ok(authorization_list)
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                   Helpers                                  */
+/* -------------------------------------------------------------------------- */
+fn optional<T>(
+    value: &Value,
+    key: &str,
+    parse: fn(&str) -> Result<T, RunnerError>,
+) -> Result<Option<T>, RunnerError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(hex)) => parse(hex).map(Some),
+        Some(_) => Err(invalid(&format!("{} is not a hex string", key))),
+    }
+}
+
+fn required<T>(
+    value: &Value,
+    key: &str,
+    parse: fn(&str) -> Result<T, RunnerError>,
+) -> Result<T, RunnerError> {
+    optional(value, key, parse)?.ok_or_else(|| invalid(&format!("missing field {}", key)))
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> Result<&'a [Value], RunnerError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(&[]),
+        Some(Value::Array(items)) => Ok(items),
+        Some(_) => Err(invalid(&format!("{} is not an array", key))),
+    }
+}
+
+fn strings<'a>(value: &'a Value, key: &str) -> Result<Vec<&'a str>, RunnerError> {
+    array(value, key)?
+        .iter()
+        .map(|item| {
+            item.as_str()
+                .ok_or_else(|| invalid(&format!("{} must contain hex strings", key)))
+        })
+        .collect()
+}
+
+fn invalid(message: &str) -> RunnerError {
+    RunnerError::InvalidBlock(message.to_string())
+}
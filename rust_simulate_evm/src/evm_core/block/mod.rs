@@ -0,0 +1,9 @@
+pub mod types;
+
+pub mod json;
+
+pub mod receipt;
+
+pub mod system;
+
+pub mod executor;
@@ -0,0 +1,191 @@
+use alloy_rlp::Encodable;
+use ethers::utils::keccak256;
+
+use crate::evm_core::log::Log;
+use crate::evm_core::trie::encoding::{rlp_bytes, rlp_list};
+use crate::evm_core::trie::root::ordered_trie_root;
+
+/// A 2048-bit bloom filter over log addresses and topics.
+pub type Bloom = [u8; 256];
+
+/* -------------------------------------------------------------------------- */
+/*                                   Receipt                                  */
+/* -------------------------------------------------------------------------- */
+/// Outcome of one transaction within a block.
+#[derive(Debug, Clone)]
+pub struct Receipt {
+    /// EIP-2718 type of the transaction.
+    pub tx_type: u8,
+    pub transaction_hash: [u8; 32],
+    pub success: bool,
+    /// State root after the transaction, encoded in place of the status
+    /// before Byzantium (EIP-658). `None` from Byzantium on.
+    pub state_root: Option<[u8; 32]>,
+    /// Gas charged, exact only if `gas_exact` (see `TransactionResult`).
+    pub gas_used: u64,
+    /// Gas used by this and all earlier transactions of the block.
+    pub cumulative_gas_used: u64,
+    pub gas_exact: bool,
+    pub logs: Vec<Log>,
+    pub logs_bloom: Bloom,
+    pub contract_address: Option<[u8; 20]>,
+    /// Blob gas and its price, zero for transactions without blobs.
+    pub blob_gas_used: u64,
+    pub blob_gas_price: u128,
+}
+
+impl Receipt {
+    /// Consensus encoding: `type || rlp([status, cumulativeGas, bloom, logs])`.
+    ///
+    /// Legacy receipts carry no type byte. Before Byzantium the post-state
+    /// root takes the place of the status.
+    pub fn encode(&self) -> Vec<u8> {
+        let status = match self.state_root {
+            Some(state_root) => rlp_bytes(&state_root),
+            None => {
+                let mut status = Vec::new();
+                (self.success as u8).encode(&mut status);
+                status
+            }
+        };
+        let mut cumulative_gas_used = Vec::new();
+        self.cumulative_gas_used.encode(&mut cumulative_gas_used);
+
+        let logs: Vec<Vec<u8>> = self.logs.iter().map(encode_log).collect();
+        let body = rlp_list(&[
+            status,
+            cumulative_gas_used,
+            rlp_bytes(&self.logs_bloom),
+            rlp_list(&logs),
+        ]);
+
+        if self.tx_type == 0x00 {
+            body
+        } else {
+            let mut out = vec![self.tx_type];
+            out.extend(body);
+            out
+        }
+    }
+}
+
+// [address, [topic, ...], data]
+fn encode_log(log: &Log) -> Vec<u8> {
+    let topics: Vec<Vec<u8>> = log.topics.iter().map(|topic| rlp_bytes(topic)).collect();
+    rlp_list(&[rlp_bytes(&log.address), rlp_list(&topics), rlp_bytes(&log.data)])
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Bloom                                   */
+/* -------------------------------------------------------------------------- */
+/// Bloom filter of the addresses and topics of `logs`.
+pub fn logs_bloom<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Bloom {
+    let mut bloom = [0u8; 256];
+    for log in logs {
+        accrue_bloom(&mut bloom, &log.address);
+        for topic in &log.topics {
+            accrue_bloom(&mut bloom, topic);
+        }
+    }
+    bloom
+}
+
+/// Returns `true` if `input` may be in the bloom. False positives are possible.
+pub fn bloom_contains(bloom: &Bloom, input: &[u8]) -> bool {
+    let mut single = [0u8; 256];
+    accrue_bloom(&mut single, input);
+    bloom.iter().zip(single).all(|(a, b)| a & b == b)
+}
+
+//keccak前6字节组成3个11位下标 每个下标置位一次
+fn accrue_bloom(bloom: &mut Bloom, input: &[u8]) {
+    let hash = keccak256(input);
+    for pair in hash[..6].chunks(2) {
+        let bit = (((pair[0] as usize) << 8) | pair[1] as usize) & 2047;
+        bloom[255 - bit / 8] |= 1 << (bit % 8);
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                Receipts root                               */
+/* -------------------------------------------------------------------------- */
+/// Root of the receipts trie, as committed to by `receiptsRoot` in the header.
+pub fn receipts_root(receipts: &[Receipt]) -> [u8; 32] {
+    ordered_trie_root(receipts.iter().map(Receipt::encode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::trie::encoding::EMPTY_ROOT;
+
+    fn receipt(tx_type: u8, cumulative_gas_used: u64) -> Receipt {
+        Receipt {
+            tx_type,
+            transaction_hash: [0u8; 32],
+            success: true,
+            state_root: None,
+            gas_used: cumulative_gas_used,
+            cumulative_gas_used,
+            gas_exact: true,
+            logs: Vec::new(),
+            logs_bloom: [0u8; 256],
+            contract_address: None,
+            blob_gas_used: 0,
+            blob_gas_price: 0,
+        }
+    }
+
+    #[test]
+    fn encode_status_receipt() {
+        // rlp([1, 0, bloom, []])
+        let mut expected = hex::decode("f901060180b90100").unwrap();
+        expected.extend([0u8; 256]);
+        expected.push(0xc0);
+        assert_eq!(receipt(0x00, 0).encode(), expected);
+
+        let mut typed = vec![0x02];
+        typed.extend(&expected);
+        assert_eq!(receipt(0x02, 0).encode(), typed);
+    }
+
+    #[test]
+    fn encode_pre_byzantium_receipt() {
+        let mut receipt = receipt(0x00, 0);
+        receipt.state_root = Some([0x11u8; 32]);
+        let encoded = receipt.encode();
+        // 状态根替代status 列表长度增加32字节
+        assert_eq!(encoded[..3], [0xf9, 0x01, 0x26]);
+        assert_eq!(encoded[3], 0xa0);
+        assert_eq!(encoded[4..36], [0x11u8; 32]);
+    }
+
+    #[test]
+    fn receipts_root_vectors() {
+        assert_eq!(receipts_root(&[]), EMPTY_ROOT);
+        // ethers-core中的区块3 一笔成功的转账
+        assert_eq!(
+            hex::encode(receipts_root(&[receipt(0x00, 21000)])),
+            "056b23fbba480696b65fe5a59b8f2148a1299103c4f57df839233af2cf4ca2d2"
+        );
+    }
+
+    #[test]
+    fn bloom_contains_log_address_and_topics() {
+        let log = Log {
+            address: [0xaau8; 20],
+            topics: vec![[0xbbu8; 32]],
+            data: vec![1, 2, 3],
+            block_number: 0,
+            transaction_hash: [0u8; 32],
+            transaction_index: 0,
+            log_index: 0,
+        };
+        let bloom = logs_bloom([&log]);
+        assert!(bloom.iter().map(|byte| byte.count_ones()).sum::<u32>() <= 6);
+        assert!(bloom_contains(&bloom, &[0xaau8; 20]));
+        assert!(bloom_contains(&bloom, &[0xbbu8; 32]));
+        assert!(!bloom_contains(&bloom, &[0xccu8; 20]));
+        assert_eq!(logs_bloom([]), [0u8; 256]);
+    }
+}
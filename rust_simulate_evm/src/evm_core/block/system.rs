@@ -0,0 +1,255 @@
+/*
+共识层驱动的状态变更
+系统调用由SYSTEM_ADDRESS发起 不收gas 不增加nonce 也不会创建系统地址账户
+提款在所有交易之后直接增加余额 单位为gwei
+*/
+use ethers::types::U256;
+
+use crate::evm_core::context::evm_context::EvmContext;
+use crate::evm_core::execute::Execute;
+use crate::evm_core::storage::EvmState;
+use crate::evm_core::utils::error::RunnerError;
+
+use super::types::Withdrawal;
+
+/// Caller of every system call: 0xfffffffffffffffffffffffffffffffffffffffe.
+pub const SYSTEM_ADDRESS: [u8; 20] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xfe,
+];
+
+/// EIP-4788 beacon roots contract: 0x000F3df6D732807Ef1319fB7B8bB8522d0Beac02.
+pub const BEACON_ROOTS_ADDRESS: [u8; 20] = [
+    0x00, 0x0f, 0x3d, 0xf6, 0xd7, 0x32, 0x80, 0x7e, 0xf1, 0x31, 0x9f, 0xb7, 0xb8, 0xbb, 0x85, 0x22,
+    0xd0, 0xbe, 0xac, 0x02,
+];
+
+/// EIP-2935 block hash history contract: 0x0000F90827F1C53a10cb7A02335B175320002935.
+pub const HISTORY_STORAGE_ADDRESS: [u8; 20] = [
+    0x00, 0x00, 0xf9, 0x08, 0x27, 0xf1, 0xc5, 0x3a, 0x10, 0xcb, 0x7a, 0x02, 0x33, 0x5b, 0x17, 0x53,
+    0x20, 0x00, 0x29, 0x35,
+];
+
+//系统调用的gas上限 不计入区块gas
+pub const SYSTEM_CALL_GAS_LIMIT: u64 = 30_000_000;
+
+/// Call `to` as the system address with `input`, outside of any transaction.
+///
+/// Nothing happens if `to` has no code, as before the contract is deployed.
+pub fn system_call(
+    state: &mut EvmState,
+    evm_context: &EvmContext,
+    to: [u8; 20],
+    input: Vec<u8>,
+) -> Result<Vec<u8>, RunnerError> {
+    state
+        .load_account(to)
+        .map_err(|e| RunnerError::SystemCallFailed(to, Box::new(e)))?;
+    if state.get_code_at(to).is_none() {
+        return Ok(Vec::new());
+    }
+
+    let mut execute = Execute::new(
+        SYSTEM_ADDRESS,
+        Some(SYSTEM_ADDRESS),
+        Some(SYSTEM_ADDRESS),
+        None,
+        None,
+        Some(std::mem::take(state)),
+        Some(evm_context.clone()),
+    );
+    execute.gas = SYSTEM_CALL_GAS_LIMIT;
+
+    let result = execute.call(to, [0u8; 32], input, SYSTEM_CALL_GAS_LIMIT, false);
+    let output = execute.returndata.heap.clone();
+    *state = execute.state;
+    state.finalize_transaction(result.is_ok(), evm_context.spec_id);
+
+    result
+        .map(|_| output)
+        .map_err(|e| RunnerError::SystemCallFailed(to, Box::new(e)))
+}
+
+/// EIP-4788: store the parent beacon block root in the beacon roots contract.
+pub fn apply_beacon_root_call(
+    state: &mut EvmState,
+    evm_context: &EvmContext,
+    parent_beacon_block_root: [u8; 32],
+) -> Result<(), RunnerError> {
+    system_call(state, evm_context, BEACON_ROOTS_ADDRESS, parent_beacon_block_root.to_vec())?;
+    Ok(())
+}
+
+/// EIP-2935: store the parent block hash in the history contract.
+pub fn apply_block_hash_call(
+    state: &mut EvmState,
+    evm_context: &EvmContext,
+    parent_hash: [u8; 32],
+) -> Result<(), RunnerError> {
+    system_call(state, evm_context, HISTORY_STORAGE_ADDRESS, parent_hash.to_vec())?;
+    Ok(())
+}
+
+/// EIP-4895: credit each withdrawal, converting gwei to wei.
+pub fn apply_withdrawals(state: &mut EvmState, withdrawals: &[Withdrawal]) {
+    for withdrawal in withdrawals {
+        // 金额为0的提款不会触及账户
+        if withdrawal.amount == 0 {
+            continue;
+        }
+        let mut wei = [0u8; 32];
+        (U256::from(withdrawal.amount) * U256::exp10(9)).to_big_endian(&mut wei);
+        state.add_balance(withdrawal.address, wei);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::context::spec::SpecId;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::AccountState;
+    use crate::evm_core::transaction::transact::transact;
+    use crate::evm_core::transaction::types::{Transaction, TxLegacy};
+    use crate::evm_core::utils::byte_operate::u64_to_u256_array;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+
+    // EIP-4788中给出的合约代码
+    const BEACON_ROOTS_CODE: &str = "3373fffffffffffffffffffffffffffffffffffffffe14604d57602036146024575f5ffd5b5f35801560495762001fff810690815414603c575f5ffd5b62001fff01545f5260205ff35b5f5ffd5b62001fff42064281555f359062001fff015500";
+    // EIP-2935中给出的合约代码
+    const HISTORY_STORAGE_CODE: &str = "3373fffffffffffffffffffffffffffffffffffffffe14604657602036036042575f35600143038111604257611fff81430311604257611fff9006545f5260205ff35b5f5ffd5b5f35611fff60014303065500";
+
+    fn state() -> EvmState {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: u64_to_u256_array(1_000_000),
+                ..Default::default()
+            },
+        );
+        db.insert_code(BEACON_ROOTS_ADDRESS, hex::decode(BEACON_ROOTS_CODE).unwrap());
+        db.insert_code(HISTORY_STORAGE_ADDRESS, hex::decode(HISTORY_STORAGE_CODE).unwrap());
+        EvmState::with_database(db)
+    }
+
+    fn context(spec_id: SpecId, number: u64, timestamp: u64) -> EvmContext {
+        EvmContext {
+            block_number: Some(u64_to_u256_array(number)),
+            timestamp: Some(u64_to_u256_array(timestamp)),
+            spec_id,
+            ..Default::default()
+        }
+    }
+
+    fn call(state: &mut EvmState, evm_context: &EvmContext, to: [u8; 20], input: Vec<u8>) -> Vec<u8> {
+        let nonce = state.accounts.get(&ALICE).map_or(0, |account| account.nonce);
+        let tx = Transaction::Legacy(TxLegacy {
+            from: ALICE,
+            nonce,
+            gas_limit: 100_000,
+            to: Some(to),
+            input,
+            ..Default::default()
+        });
+        let result = transact(state, evm_context, tx).unwrap();
+        assert!(result.success, "{:?}", result.error);
+        result.output
+    }
+
+    #[test]
+    fn beacon_root_ring_buffer() {
+        let mut state = state();
+        // 1_700_000_000 % 8191 = 7096
+        let evm_context = context(SpecId::Cancun, 1, 1_700_000_000);
+        let root = [0x42u8; 32];
+        apply_beacon_root_call(&mut state, &evm_context, root).unwrap();
+
+        let timestamp_slot = u64_to_u256_array(7096);
+        let root_slot = u64_to_u256_array(7096 + 8191);
+        assert_eq!(
+            state.sload(BEACON_ROOTS_ADDRESS, timestamp_slot).unwrap(),
+            u64_to_u256_array(1_700_000_000)
+        );
+        assert_eq!(state.sload(BEACON_ROOTS_ADDRESS, root_slot).unwrap(), root);
+        // 系统地址不被创建
+        assert!(!state.account_exists(SYSTEM_ADDRESS));
+
+        // 按时间戳查询
+        let output = call(
+            &mut state,
+            &evm_context,
+            BEACON_ROOTS_ADDRESS,
+            u64_to_u256_array(1_700_000_000).to_vec(),
+        );
+        assert_eq!(output, root.to_vec());
+    }
+
+    #[test]
+    fn block_hash_history_storage() {
+        let mut state = state();
+        // 父区块号 20_000 % 8191 = 3618
+        let evm_context = context(SpecId::Prague, 20_001, 0);
+        let parent_hash = [0x77u8; 32];
+        apply_block_hash_call(&mut state, &evm_context, parent_hash).unwrap();
+
+        assert_eq!(
+            state.sload(HISTORY_STORAGE_ADDRESS, u64_to_u256_array(3618)).unwrap(),
+            parent_hash
+        );
+        let output = call(
+            &mut state,
+            &evm_context,
+            HISTORY_STORAGE_ADDRESS,
+            u64_to_u256_array(20_000).to_vec(),
+        );
+        assert_eq!(output, parent_hash.to_vec());
+    }
+
+    #[test]
+    fn system_call_without_code_does_nothing() {
+        let mut state = EvmState::default();
+        let evm_context = context(SpecId::Cancun, 1, 12);
+        apply_beacon_root_call(&mut state, &evm_context, [0x42u8; 32]).unwrap();
+        assert!(state.accounts.is_empty());
+    }
+
+    #[test]
+    fn withdrawals_are_credited_in_wei() {
+        let mut state = EvmState::default();
+        let withdrawals = [
+            Withdrawal {
+                index: 0,
+                validator_index: 1,
+                address: [0xaa; 20],
+                amount: 1,
+            },
+            Withdrawal {
+                index: 1,
+                validator_index: 2,
+                address: [0xbb; 20],
+                amount: 32_000_000_000,
+            },
+            Withdrawal {
+                index: 2,
+                validator_index: 2,
+                address: [0xbb; 20],
+                amount: u64::MAX,
+            },
+            Withdrawal {
+                index: 3,
+                validator_index: 3,
+                address: [0xcc; 20],
+                amount: 0,
+            },
+        ];
+        apply_withdrawals(&mut state, &withdrawals);
+
+        assert_eq!(state.accounts[&[0xaa; 20]].balance, u64_to_u256_array(1_000_000_000));
+        // (32e9 + 2^64 - 1) gwei
+        let expected = (U256::from(32_000_000_000u64) + U256::from(u64::MAX)) * U256::exp10(9);
+        assert_eq!(U256::from_big_endian(&state.accounts[&[0xbb; 20]].balance), expected);
+        assert!(!state.account_exists([0xcc; 20]));
+    }
+}
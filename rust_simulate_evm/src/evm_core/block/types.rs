@@ -0,0 +1,117 @@
+use crate::evm_core::context::evm_context::EvmContext;
+use crate::evm_core::context::spec::SpecId;
+use crate::evm_core::transaction::types::Transaction;
+use crate::evm_core::utils::byte_operate::{pad_left, u64_to_u256_array};
+
+/* -------------------------------------------------------------------------- */
+/*                                Block header                                */
+/* -------------------------------------------------------------------------- */
+/// The header fields the executor needs, as returned by `eth_getBlockByNumber`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub number: u64,
+    pub hash: [u8; 32],
+    pub parent_hash: [u8; 32],
+    pub coinbase: [u8; 20],
+    pub timestamp: u64,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub difficulty: [u8; 32],
+    /// `prevrandao` after the merge.
+    pub mix_hash: [u8; 32],
+    pub state_root: [u8; 32],
+    pub transactions_root: [u8; 32],
+    pub receipts_root: [u8; 32],
+    /// London (EIP-1559) and later.
+    pub base_fee_per_gas: Option<u128>,
+    /// Shanghai (EIP-4895) and later.
+    pub withdrawals_root: Option<[u8; 32]>,
+    /// Cancun (EIP-4844) and later.
+    pub blob_gas_used: Option<u64>,
+    pub excess_blob_gas: Option<u64>,
+    /// Cancun (EIP-4788) and later.
+    pub parent_beacon_block_root: Option<[u8; 32]>,
+    /// Prague (EIP-7685) and later.
+    pub requests_hash: Option<[u8; 32]>,
+}
+
+impl BlockHeader {
+    /// Guess the fork from the fields the header carries.
+    ///
+    /// Pre-London headers look alike, so they all map to Berlin.
+    pub fn spec_id(&self) -> SpecId {
+        if self.requests_hash.is_some() {
+            SpecId::Prague
+        } else if self.blob_gas_used.is_some() || self.parent_beacon_block_root.is_some() {
+            SpecId::Cancun
+        } else if self.withdrawals_root.is_some() {
+            SpecId::Shanghai
+        } else if self.base_fee_per_gas.is_some() && self.difficulty == [0u8; 32] {
+            SpecId::Merge
+        } else if self.base_fee_per_gas.is_some() {
+            SpecId::London
+        } else {
+            SpecId::Berlin
+        }
+    }
+
+    /// Block environment for executing this block's transactions.
+    pub fn evm_context(&self, chain_id: u64, spec_id: SpecId) -> EvmContext {
+        let mut evm_context = EvmContext::new();
+        evm_context.chain_id = Some(u64_to_u256_array(chain_id));
+        evm_context.blockhash = Some(self.hash);
+        evm_context.block_number = Some(u64_to_u256_array(self.number));
+        evm_context.coinbase = Some(self.coinbase);
+        evm_context.timestamp = Some(u64_to_u256_array(self.timestamp));
+        evm_context.gas_limit = Some(u64_to_u256_array(self.gas_limit));
+        evm_context.basefee = self.base_fee_per_gas.map(|basefee| pad_left(&basefee.to_be_bytes()));
+        evm_context.difficulty = Some(self.difficulty);
+        if spec_id.is_enabled_in(SpecId::Merge) {
+            evm_context.prevrandao = Some(self.mix_hash.into());
+        }
+        evm_context.excess_blob_gas = self.excess_blob_gas;
+        evm_context.spec_id = spec_id;
+        evm_context
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Block                                   */
+/* -------------------------------------------------------------------------- */
+/// A transaction as included in a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTransaction {
+    /// Zero when unknown, e.g. for blocks built by hand.
+    pub hash: [u8; 32],
+    pub transaction: Transaction,
+    /// Encoding stored in the transactions trie. Empty when unknown.
+    pub raw: Vec<u8>,
+}
+
+/// A validator withdrawal pushed from the beacon chain (EIP-4895).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub index: u64,
+    pub validator_index: u64,
+    pub address: [u8; 20],
+    /// Amount in gwei.
+    pub amount: u64,
+}
+
+/// A block header and its transactions, in execution order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub transactions: Vec<BlockTransaction>,
+    /// Applied after the transactions, from Shanghai on.
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+impl Block {
+    /// Chain id carried by the first replay-protected transaction.
+    pub fn chain_id(&self) -> Option<u64> {
+        self.transactions
+            .iter()
+            .find_map(|tx| tx.transaction.chain_id())
+    }
+}
@@ -0,0 +1,721 @@
+/*
+本地链
+持有EvmState并按顺序出块 每个区块推进区块号和时间戳
+保存区块头 交易和收据 可按区块号 区块哈希或交易哈希查询
+时间是模拟的 只会被 increase_time / set_next_block_timestamp 推进
+*/
+use std::collections::{HashMap, HashSet};
+
+use alloy_rlp::Encodable;
+use ethers::utils::keccak256;
+
+use crate::evm_core::block::executor::BlockBuilder;
+use crate::evm_core::block::receipt::{receipts_root, Receipt};
+use crate::evm_core::block::system::apply_block_hash_call;
+use crate::evm_core::block::types::{Block, BlockHeader, BlockTransaction};
+use crate::evm_core::context::spec::SpecId;
+use crate::evm_core::gas::blob::calc_excess_blob_gas;
+use crate::evm_core::storage::EvmState;
+use crate::evm_core::transaction::decode::decode_raw_transaction;
+use crate::evm_core::transaction::types::{AccessListItem, Authorization, Transaction};
+use crate::evm_core::trie::encoding::{rlp_bytes, rlp_list, rlp_uint, EMPTY_ROOT};
+use crate::evm_core::trie::root::ordered_trie_root;
+use crate::evm_core::utils::byte_operate::u64_to_u256_array;
+use crate::evm_core::utils::error::RunnerError;
+
+use super::pool::TransactionPool;
+use super::types::{MinedBlock, MiningMode};
+
+const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+
+const DEFAULT_COINBASE: [u8; 20] = [0xc0u8; 20];
+
+//BLOCKHASH可见的历史区块数
+const BLOCKHASH_WINDOW: u64 = 256;
+
+/// A single-node chain that mines blocks on top of an [`EvmState`].
+///
+/// Starts from a genesis block at number 0 and timestamp 0 holding `state`.
+#[derive(Debug)]
+pub struct Chain {
+    pub state: EvmState,
+    pub chain_id: u64,
+    pub spec_id: SpecId,
+    pub mining_mode: MiningMode,
+    pub gas_limit: u64,
+    pub coinbase: [u8; 20],
+    /// Base fee of new blocks from London on. Not adjusted between blocks.
+    pub base_fee_per_gas: u128,
+    blocks: Vec<MinedBlock>,
+    block_numbers: HashMap<[u8; 32], u64>,
+    //交易哈希 => (区块号, 区块内序号)
+    transactions: HashMap<[u8; 32], (u64, usize)>,
+    pool: TransactionPool,
+    rejected: HashMap<[u8; 32], RunnerError>,
+    time: u64,
+    next_timestamp: Option<u64>,
+}
+
+impl Chain {
+    pub fn new(mut state: EvmState, chain_id: u64) -> Self {
+        // 分叉的数据库无法列出全部账户 状态根只覆盖已缓存的部分
+        let state_root = state
+            .state_root()
+            .unwrap_or_else(|_| state.cached_state_root());
+        let mut genesis = BlockHeader {
+            gas_limit: DEFAULT_GAS_LIMIT,
+            coinbase: DEFAULT_COINBASE,
+            state_root,
+            transactions_root: ordered_trie_root(Vec::new()),
+            receipts_root: receipts_root(&[]),
+            ..Default::default()
+        };
+        genesis.hash = header_hash(&genesis);
+
+        let mut chain = Self {
+            state,
+            chain_id,
+            spec_id: SpecId::default(),
+            mining_mode: MiningMode::default(),
+            gas_limit: DEFAULT_GAS_LIMIT,
+            coinbase: DEFAULT_COINBASE,
+            base_fee_per_gas: 0,
+            blocks: Vec::new(),
+            block_numbers: HashMap::new(),
+            transactions: HashMap::new(),
+            pool: TransactionPool::new(),
+            rejected: HashMap::new(),
+            time: 0,
+            next_timestamp: None,
+        };
+        chain.push_block(MinedBlock {
+            block: Block {
+                header: genesis,
+                ..Default::default()
+            },
+            receipts: Vec::new(),
+        });
+        chain
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                   Queries                                  */
+    /* -------------------------------------------------------------------------- */
+    pub fn block_number(&self) -> u64 {
+        self.latest_block().header().number
+    }
+
+    pub fn latest_block(&self) -> &MinedBlock {
+        self.blocks.last().expect("chain always has a genesis block")
+    }
+
+    pub fn block_by_number(&self, number: u64) -> Option<&MinedBlock> {
+        self.blocks.get(number as usize)
+    }
+
+    pub fn block_by_hash(&self, hash: [u8; 32]) -> Option<&MinedBlock> {
+        self.block_by_number(*self.block_numbers.get(&hash)?)
+    }
+
+    pub fn transaction_by_hash(&self, hash: [u8; 32]) -> Option<&BlockTransaction> {
+        let (number, index) = self.transactions.get(&hash)?;
+        self.blocks[*number as usize].transactions().get(*index)
+    }
+
+    pub fn receipt_by_hash(&self, hash: [u8; 32]) -> Option<&Receipt> {
+        let (number, index) = self.transactions.get(&hash)?;
+        self.blocks[*number as usize].receipts.get(*index)
+    }
+
+    /// Block number holding the transaction with `hash`.
+    pub fn transaction_block(&self, hash: [u8; 32]) -> Option<u64> {
+        self.transactions.get(&hash).map(|(number, _)| *number)
+    }
+
+    /// Why a pending transaction was dropped at mining time.
+    pub fn transaction_error(&self, hash: [u8; 32]) -> Option<&RunnerError> {
+        self.rejected.get(&hash)
+    }
+
+    /// Transactions waiting to be mined, pending and queued.
+    pub fn pool(&self) -> &TransactionPool {
+        &self.pool
+    }
+
+    pub fn pool_mut(&mut self) -> &mut TransactionPool {
+        &mut self.pool
+    }
+
+    /// Transactions the next block could include, in no particular order.
+    pub fn pending_transactions(&self) -> Vec<&BlockTransaction> {
+        self.pool.pending(&self.state)
+    }
+
+    /// Current simulated time in seconds.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                Transactions                                */
+    /* -------------------------------------------------------------------------- */
+    /// Submit an unsigned transaction. With no signature to hash, it is
+    /// hashed over its fields and sender, see [`unsigned_encoding`].
+    pub fn send_transaction(&mut self, tx: Transaction) -> Result<[u8; 32], RunnerError> {
+        let raw = unsigned_encoding(&tx);
+        self.submit(BlockTransaction {
+            hash: keccak256(&raw),
+            transaction: tx,
+            raw,
+        })
+    }
+
+    /// Submit a raw signed transaction, hashed like on a real node.
+    pub fn send_raw_transaction(&mut self, raw: &[u8]) -> Result<[u8; 32], RunnerError> {
+        self.submit(BlockTransaction {
+            hash: keccak256(raw),
+            transaction: decode_raw_transaction(raw)?,
+            raw: raw.to_vec(),
+        })
+    }
+
+    /// In [`MiningMode::Auto`] the transaction is mined at once and an invalid
+    /// one is returned as an error. Otherwise it goes to the pool, where it may
+    /// replace a transaction with the same sender and nonce.
+    fn submit(&mut self, block_tx: BlockTransaction) -> Result<[u8; 32], RunnerError> {
+        let hash = block_tx.hash;
+        if self.mining_mode != MiningMode::Auto {
+            // 交易池按缓存中的nonce排序 先从数据库载入发送者
+            self.state.load_account(block_tx.transaction.caller())?;
+            self.pool.add(block_tx, &self.state)?;
+            return Ok(hash);
+        }
+
+        // 交易被拒绝时区块不上链 回滚系统调用的修改
+        let checkpoint = self.state.snapshot();
+        let (block, mut rejected) = self.build_block(vec![block_tx])?;
+        if let Some((_, error)) = rejected.pop() {
+            self.state.revert_to(checkpoint);
+            return Err(error);
+        }
+        self.state.discard_snapshot(checkpoint);
+        self.push_block(block);
+        Ok(hash)
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                   Mining                                   */
+    /* -------------------------------------------------------------------------- */
+    /// Mine one block from the pool, highest tip first.
+    ///
+    /// Transactions that can never be valid leave the pool, see
+    /// [`Chain::transaction_error`]. Those rejected for the current state or
+    /// block, e.g. a nonce gap or low balance, stay for a later block.
+    pub fn mine(&mut self) -> Result<&MinedBlock, RunnerError> {
+        let basefee = if self.spec_id.is_enabled_in(SpecId::London) {
+            self.base_fee_per_gas
+        } else {
+            0
+        };
+        let transactions = self.pool.best_transactions(&self.state, basefee, self.gas_limit);
+        let (block, rejected) = self.build_block(transactions)?;
+        for (block_tx, error) in rejected {
+            if is_intrinsic(&error) {
+                self.pool.remove(block_tx.hash);
+                self.rejected.insert(block_tx.hash, error);
+            }
+        }
+        self.push_block(block);
+        self.pool.prune(&self.state);
+        Ok(self.latest_block())
+    }
+
+    pub fn mine_blocks(&mut self, count: u64) -> Result<(), RunnerError> {
+        for _ in 0..count {
+            self.mine()?;
+        }
+        Ok(())
+    }
+
+    /// Advance simulated time. In [`MiningMode::Interval`] the blocks due in
+    /// that time are mined, each `interval` seconds after its parent.
+    pub fn increase_time(&mut self, seconds: u64) -> Result<(), RunnerError> {
+        self.time += seconds;
+        if let MiningMode::Interval(interval) = self.mining_mode {
+            while interval > 0 && self.latest_block().header().timestamp + interval <= self.time {
+                self.next_timestamp = Some(self.latest_block().header().timestamp + interval);
+                self.mine()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fix the timestamp of the next block, which must be after the latest one.
+    pub fn set_next_block_timestamp(&mut self, timestamp: u64) -> Result<(), RunnerError> {
+        if timestamp <= self.latest_block().header().timestamp {
+            return Err(RunnerError::InvalidBlock(format!(
+                "timestamp {} is not after the latest block",
+                timestamp
+            )));
+        }
+        self.next_timestamp = Some(timestamp);
+        Ok(())
+    }
+
+    //执行交易生成新区块 返回区块和被拒绝的交易 尚未加入链
+    fn build_block(
+        &mut self,
+        transactions: Vec<BlockTransaction>,
+    ) -> Result<(MinedBlock, Vec<(BlockTransaction, RunnerError)>), RunnerError> {
+        let spec_id = self.spec_id;
+        let parent = self.latest_block().header().clone();
+        // 默认时间戳为当前时间 且至少比父区块晚1秒
+        let timestamp = self
+            .next_timestamp
+            .unwrap_or_else(|| self.time.max(parent.timestamp + 1));
+        let cancun = spec_id.is_enabled_in(SpecId::Cancun);
+
+        let mut header = BlockHeader {
+            number: parent.number + 1,
+            parent_hash: parent.hash,
+            coinbase: self.coinbase,
+            timestamp,
+            gas_limit: self.gas_limit,
+            // prevrandao由父区块哈希派生
+            mix_hash: keccak256(parent.hash),
+            base_fee_per_gas: spec_id
+                .is_enabled_in(SpecId::London)
+                .then_some(self.base_fee_per_gas),
+            withdrawals_root: spec_id.is_enabled_in(SpecId::Shanghai).then_some(EMPTY_ROOT),
+            excess_blob_gas: cancun.then(|| {
+                calc_excess_blob_gas(
+                    parent.excess_blob_gas.unwrap_or(0),
+                    parent.blob_gas_used.unwrap_or(0),
+                    spec_id,
+                )
+            }),
+            ..Default::default()
+        };
+
+        let mut evm_context = header.evm_context(self.chain_id, spec_id);
+        let first = header.number.saturating_sub(BLOCKHASH_WINDOW);
+        evm_context.block_hashes = self.blocks[first as usize..]
+            .iter()
+            .map(|block| (block.header().number, block.header().hash))
+            .collect();
+        // 系统调用先于交易修改状态 出块失败时回滚
+        let checkpoint = self.state.snapshot();
+        if spec_id.is_enabled_in(SpecId::Prague) {
+            if let Err(error) = apply_block_hash_call(&mut self.state, &evm_context, parent.hash) {
+                self.state.revert_to(checkpoint);
+                return Err(error);
+            }
+        }
+
+        let mut builder = BlockBuilder::new(self.gas_limit);
+        let mut included = Vec::new();
+        let mut rejected = Vec::new();
+        // 被拒绝交易的发送者 其后续nonce留在交易池
+        let mut skipped = HashSet::new();
+        for block_tx in transactions {
+            let sender = block_tx.transaction.caller();
+            if skipped.contains(&sender) {
+                continue;
+            }
+            match builder.execute_transaction(&mut self.state, &evm_context, &block_tx) {
+                Ok(_) => included.push(block_tx),
+                Err(error) => {
+                    skipped.insert(sender);
+                    rejected.push((block_tx, error));
+                }
+            }
+        }
+        let result = builder.finish();
+        let state_root = match self.state.state_root() {
+            Ok(root) => root,
+            Err(RunnerError::IncompleteState) => self.state.cached_state_root(),
+            Err(error) => {
+                self.state.revert_to(checkpoint);
+                return Err(error);
+            }
+        };
+        self.state.discard_snapshot(checkpoint);
+
+        // 操作码不计量gas 本地链按自己的规则计算gasUsed和收据根 与真实链可能不同
+        header.gas_used = result.gas_used;
+        header.blob_gas_used = cancun.then_some(result.blob_gas_used);
+        header.transactions_root =
+            ordered_trie_root(included.iter().map(|block_tx| block_tx.raw.clone()));
+        header.receipts_root = receipts_root(&result.receipts);
+        header.state_root = state_root;
+        header.hash = header_hash(&header);
+
+        let block = MinedBlock {
+            block: Block {
+                header,
+                transactions: included,
+                withdrawals: Vec::new(),
+            },
+            receipts: result.receipts,
+        };
+        Ok((block, rejected))
+    }
+
+    fn push_block(&mut self, block: MinedBlock) {
+        let number = block.header().number;
+        let hash = block.header().hash;
+        for (index, block_tx) in block.transactions().iter().enumerate() {
+            self.transactions.insert(block_tx.hash, (number, index));
+            self.rejected.remove(&block_tx.hash);
+        }
+        self.time = self.time.max(block.header().timestamp);
+        self.next_timestamp = None;
+        self.block_numbers.insert(hash, number);
+        self.blocks.push(block);
+    }
+}
+
+//交易本身无效 与状态和区块无关 重试也不会成功
+fn is_intrinsic(error: &RunnerError) -> bool {
+    matches!(
+        error,
+        RunnerError::InvalidChainId
+            | RunnerError::TxGasLimitExceedsBlock
+            | RunnerError::IntrinsicGasTooLow
+            | RunnerError::TipAboveFeeCap
+            | RunnerError::TransactionTypeNotSupported(_)
+            | RunnerError::InvalidSignature
+            | RunnerError::EmptyBlobs
+            | RunnerError::InvalidBlobVersionedHash
+            | RunnerError::TooManyBlobs
+            | RunnerError::EmptyAuthorizationList
+    )
+}
+
+/// Hash of the header fields kept by [`BlockHeader`].
+///
+/// Logs bloom, extra data and ommers are not tracked, so this is not the
+/// canonical Ethereum block hash. It is only unique within the local chain.
+fn header_hash(header: &BlockHeader) -> [u8; 32] {
+    let mut items = vec![
+        rlp_bytes(&header.parent_hash),
+        rlp_bytes(&header.coinbase),
+        rlp_bytes(&header.state_root),
+        rlp_bytes(&header.transactions_root),
+        rlp_bytes(&header.receipts_root),
+        rlp_uint(&header.difficulty),
+        rlp_uint(&u64_to_u256_array(header.number)),
+        rlp_uint(&u64_to_u256_array(header.gas_limit)),
+        rlp_uint(&u64_to_u256_array(header.gas_used)),
+        rlp_uint(&u64_to_u256_array(header.timestamp)),
+        rlp_bytes(&header.mix_hash),
+    ];
+    if let Some(base_fee) = header.base_fee_per_gas {
+        let mut word = [0u8; 32];
+        word[16..].copy_from_slice(&base_fee.to_be_bytes());
+        items.push(rlp_uint(&word));
+    }
+    if let Some(withdrawals_root) = header.withdrawals_root {
+        items.push(rlp_bytes(&withdrawals_root));
+    }
+    if let (Some(blob_gas_used), Some(excess_blob_gas)) =
+        (header.blob_gas_used, header.excess_blob_gas)
+    {
+        items.push(rlp_uint(&u64_to_u256_array(blob_gas_used)));
+        items.push(rlp_uint(&u64_to_u256_array(excess_blob_gas)));
+    }
+    keccak256(rlp_list(&items))
+}
+
+/// Encoding of an unsigned transaction: `type || rlp([fields..., sender])`.
+///
+/// The fields are those of the signing payload, followed by the sender in
+/// place of the signature. Legacy transactions carry no type byte.
+fn unsigned_encoding(tx: &Transaction) -> Vec<u8> {
+    let mut items = Vec::new();
+    if let Some(chain_id) = tx.chain_id() {
+        items.push(rlp(&chain_id));
+    }
+    items.push(rlp(&tx.nonce()));
+    if let Transaction::Legacy(legacy) = tx {
+        items.push(rlp(&legacy.gas_price));
+    } else if let Transaction::Eip2930(eip2930) = tx {
+        items.push(rlp(&eip2930.gas_price));
+    } else {
+        items.push(rlp(&tx.max_priority_fee_per_gas()));
+        items.push(rlp(&tx.max_fee_per_gas()));
+    }
+    items.push(rlp(&tx.gas_limit()));
+    items.push(tx.to().map_or_else(|| rlp_bytes(&[]), |to| rlp_bytes(&to)));
+    items.push(rlp_uint(&tx.value()));
+    items.push(rlp_bytes(tx.input()));
+    if tx.tx_type() != 0x00 {
+        items.push(encode_access_list(tx.access_list()));
+    }
+    if let Transaction::Eip4844(eip4844) = tx {
+        items.push(rlp(&eip4844.max_fee_per_blob_gas));
+        let hashes: Vec<Vec<u8>> = eip4844
+            .blob_versioned_hashes
+            .iter()
+            .map(|hash| rlp_bytes(hash))
+            .collect();
+        items.push(rlp_list(&hashes));
+    }
+    if let Transaction::Eip7702(eip7702) = tx {
+        items.push(encode_authorization_list(&eip7702.authorization_list));
+    }
+    items.push(rlp_bytes(&tx.caller()));
+
+    let body = rlp_list(&items);
+    if tx.tx_type() == 0x00 {
+        body
+    } else {
+        let mut out = vec![tx.tx_type()];
+        out.extend(body);
+        out
+    }
+}
+
+// [[address, [key, ...]], ...]
+fn encode_access_list(access_list: &[AccessListItem]) -> Vec<u8> {
+    let items: Vec<Vec<u8>> = access_list
+        .iter()
+        .map(|item| {
+            let keys: Vec<Vec<u8>> =
+                item.storage_keys.iter().map(|key| rlp_bytes(key)).collect();
+            rlp_list(&[rlp_bytes(&item.address), rlp_list(&keys)])
+        })
+        .collect();
+    rlp_list(&items)
+}
+
+// [[chain_id, address, nonce, y_parity, r, s], ...]
+fn encode_authorization_list(authorization_list: &[Authorization]) -> Vec<u8> {
+    let items: Vec<Vec<u8>> = authorization_list
+        .iter()
+        .map(|authorization| {
+            rlp_list(&[
+                rlp(&authorization.chain_id),
+                rlp_bytes(&authorization.address),
+                rlp(&authorization.nonce),
+                rlp(&authorization.y_parity),
+                rlp_uint(&authorization.r),
+                rlp_uint(&authorization.s),
+            ])
+        })
+        .collect();
+    rlp_list(&items)
+}
+
+fn rlp(value: &impl Encodable) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::AccountState;
+    use crate::evm_core::transaction::types::TxLegacy;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const BOB: [u8; 20] = [0xb0; 20];
+
+    // ethers-core中的区块3 唯一一笔交易 chain id为1 nonce为2
+    const RAW_TX: &str = "\
+        f865028504a817c80083015f9094dca8ce283150ab773bcbeb8d38289bdb5661de1e808025\
+        a019f2694eb9113656dbea0b925e2e7ceb43df83e601c4116aee9c0dd99130be88\
+        a073e5764b324a4f7679d890a198ba658ba1c8cd36983ff9797e10b1b89dbb448e";
+
+    fn funded(accounts: &[([u8; 20], u64)]) -> EvmState {
+        let mut db = MemoryDb::new();
+        for (address, nonce) in accounts {
+            db.insert_account(
+                *address,
+                AccountState {
+                    nonce: *nonce,
+                    balance: u64_to_u256_array(u64::MAX),
+                    ..Default::default()
+                },
+            );
+        }
+        EvmState::with_database(db)
+    }
+
+    fn legacy(nonce: u64, gas_price: u128) -> TxLegacy {
+        TxLegacy {
+            from: ALICE,
+            chain_id: Some(1),
+            nonce,
+            gas_price,
+            gas_limit: 21_000,
+            to: Some(BOB),
+            value: u64_to_u256_array(5),
+            ..Default::default()
+        }
+    }
+
+    fn transfer(nonce: u64, gas_price: u128) -> Transaction {
+        Transaction::Legacy(legacy(nonce, gas_price))
+    }
+
+    #[test]
+    fn genesis_has_empty_roots() {
+        let chain = Chain::new(funded(&[(ALICE, 0)]), 1);
+        let genesis = chain.latest_block().header();
+        assert_eq!(chain.block_number(), 0);
+        assert_eq!(genesis.transactions_root, EMPTY_ROOT);
+        assert_eq!(genesis.receipts_root, EMPTY_ROOT);
+        assert_eq!(chain.block_by_hash(genesis.hash).unwrap().header(), genesis);
+    }
+
+    #[test]
+    fn auto_mines_each_transaction() {
+        let mut chain = Chain::new(funded(&[(ALICE, 0)]), 1);
+        let hash = chain.send_transaction(transfer(0, 1)).unwrap();
+
+        assert_eq!(chain.block_number(), 1);
+        assert_eq!(chain.transaction_block(hash), Some(1));
+        assert!(chain.receipt_by_hash(hash).unwrap().success);
+        assert_eq!(chain.state.accounts[&BOB].balance, u64_to_u256_array(5));
+        let block = chain.latest_block();
+        assert_eq!(block.header().parent_hash, chain.block_by_number(0).unwrap().header().hash);
+        assert_eq!(block.header().gas_used, 21_000);
+    }
+
+    #[test]
+    fn rejected_transaction_mines_no_block() {
+        let mut chain = Chain::new(funded(&[(ALICE, 0)]), 1);
+        assert_eq!(
+            chain.send_transaction(transfer(1, 1)),
+            Err(RunnerError::NonceMismatch {
+                expected: 0,
+                got: 1
+            })
+        );
+        assert_eq!(chain.block_number(), 0);
+    }
+
+    #[test]
+    fn unsigned_hash_covers_fees_and_payload() {
+        // 手续费 数据和发送者不同的交易哈希都不同
+        let hashes: Vec<[u8; 32]> = [
+            legacy(0, 1),
+            legacy(0, 2),
+            TxLegacy {
+                input: vec![0x01],
+                ..legacy(0, 1)
+            },
+            TxLegacy {
+                from: BOB,
+                ..legacy(0, 1)
+            },
+        ]
+        .into_iter()
+        .map(Transaction::Legacy)
+        .collect::<Vec<_>>()
+        .iter()
+        .map(|tx| keccak256(unsigned_encoding(tx)))
+        .collect();
+        for (index, hash) in hashes.iter().enumerate() {
+            assert!(!hashes[index + 1..].contains(hash));
+        }
+    }
+
+    #[test]
+    fn transactions_root_of_unsigned_transactions() {
+        let mut chain = Chain::new(funded(&[(ALICE, 0)]), 1);
+        chain.mining_mode = MiningMode::Manual;
+        chain.send_transaction(transfer(0, 1)).unwrap();
+        chain.send_transaction(transfer(1, 1)).unwrap();
+        chain.mine().unwrap();
+
+        let block = chain.latest_block();
+        assert_eq!(block.transactions().len(), 2);
+        assert_eq!(
+            block.header().transactions_root,
+            ordered_trie_root([
+                unsigned_encoding(&transfer(0, 1)),
+                unsigned_encoding(&transfer(1, 1)),
+            ])
+        );
+    }
+
+    #[test]
+    fn transactions_root_of_raw_transaction() {
+        let raw = hex::decode(RAW_TX).unwrap();
+        let sender = decode_raw_transaction(&raw).unwrap().caller();
+        let mut chain = Chain::new(funded(&[(sender, 2)]), 1);
+        let hash = chain.send_raw_transaction(&raw).unwrap();
+
+        // 与真实区块3的交易根相同
+        let header = chain.latest_block().header();
+        assert_eq!(
+            hex::encode(header.transactions_root),
+            "7270c1c4440180f2bd5215809ee3d545df042b67329499e1ab97eb759d31610d"
+        );
+        assert_eq!(chain.transaction_by_hash(hash).unwrap().raw, raw);
+    }
+
+    #[test]
+    fn manual_mode_waits_for_mine() {
+        let mut chain = Chain::new(funded(&[(ALICE, 0)]), 1);
+        chain.mining_mode = MiningMode::Manual;
+        let hash = chain.send_transaction(transfer(0, 1)).unwrap();
+        assert_eq!(chain.block_number(), 0);
+        assert_eq!(chain.pending_transactions().len(), 1);
+
+        chain.mine().unwrap();
+        assert_eq!(chain.transaction_block(hash), Some(1));
+        assert!(chain.pool().is_empty());
+    }
+
+    #[test]
+    fn mine_keeps_transactions_rejected_by_state() {
+        let mut chain = Chain::new(funded(&[(ALICE, 0), (BOB, 0)]), 1);
+        chain.mining_mode = MiningMode::Manual;
+        // 余额不足以支付gas 后续nonce也留在交易池
+        let poor = chain.send_transaction(transfer(0, 1 << 60)).unwrap();
+        let next = chain.send_transaction(transfer(1, 1)).unwrap();
+        // gas上限低于固有gas 永远无效
+        let invalid = chain
+            .send_transaction(Transaction::Legacy(TxLegacy {
+                from: BOB,
+                gas_limit: 20_000,
+                ..legacy(0, 1)
+            }))
+            .unwrap();
+
+        chain.mine().unwrap();
+        assert!(chain.latest_block().transactions().is_empty());
+        assert_eq!(chain.pool().len(), 2);
+        assert_eq!(chain.transaction_error(poor), None);
+        assert_eq!(chain.transaction_error(next), None);
+        assert_eq!(
+            chain.transaction_error(invalid),
+            Some(&RunnerError::IntrinsicGasTooLow)
+        );
+
+        chain.state.accounts.get_mut(&ALICE).unwrap().balance = [0xff; 32];
+        chain.mine().unwrap();
+        assert_eq!(chain.transaction_block(poor), Some(2));
+        assert_eq!(chain.transaction_block(next), Some(2));
+        assert!(chain.pool().is_empty());
+    }
+
+    #[test]
+    fn interval_mode_follows_time() {
+        let mut chain = Chain::new(funded(&[(ALICE, 0)]), 1);
+        chain.mining_mode = MiningMode::Interval(12);
+        chain.increase_time(30).unwrap();
+        assert_eq!(chain.block_number(), 2);
+        assert_eq!(chain.latest_block().header().timestamp, 24);
+
+        chain.set_next_block_timestamp(100).unwrap();
+        chain.mine().unwrap();
+        assert_eq!(chain.latest_block().header().timestamp, 100);
+        assert!(chain.set_next_block_timestamp(100).is_err());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod types;
+
+pub mod pool;
+
+pub mod local;
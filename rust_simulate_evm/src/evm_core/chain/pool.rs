@@ -0,0 +1,395 @@
+/*
+交易池
+按发送者保存交易 nonce连续可执行的为pending 有空缺的为queued
+同一发送者同一nonce的交易需提高足够费用才能替换
+出块时按有效小费从高到低贪心选取 同一发送者保持nonce顺序
+*/
+use std::collections::{BTreeMap, BinaryHeap};
+use std::cmp::Reverse;
+
+use crate::evm_core::block::types::BlockTransaction;
+use crate::evm_core::storage::EvmState;
+use crate::evm_core::utils::error::RunnerError;
+
+//替换交易的最低加价百分比 与geth默认值相同
+const DEFAULT_PRICE_BUMP: u128 = 10;
+
+#[derive(Debug, Clone)]
+struct PooledTransaction {
+    block_tx: BlockTransaction,
+    //进入交易池的顺序 小费相同时先到先得
+    sequence: u64,
+}
+
+/// Pending and queued transactions waiting to be mined.
+#[derive(Debug, Clone)]
+pub struct TransactionPool {
+    /// Percent by which both fee caps must rise to replace a transaction.
+    pub price_bump: u128,
+    senders: BTreeMap<[u8; 20], BTreeMap<u64, PooledTransaction>>,
+    sequence: u64,
+}
+
+impl Default for TransactionPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransactionPool {
+    pub fn new() -> Self {
+        Self {
+            price_bump: DEFAULT_PRICE_BUMP,
+            senders: BTreeMap::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Add a transaction, returning the one it replaced, if any.
+    ///
+    /// Nonces already used in `state` are rejected. A transaction with the
+    /// same sender and nonce is only replaced when the new one raises both
+    /// the fee cap and the tip cap by at least [`TransactionPool::price_bump`].
+    pub fn add(
+        &mut self,
+        block_tx: BlockTransaction,
+        state: &EvmState,
+    ) -> Result<Option<BlockTransaction>, RunnerError> {
+        let tx = &block_tx.transaction;
+        let sender = tx.caller();
+        let account_nonce = account_nonce(state, sender);
+        if tx.nonce() < account_nonce {
+            return Err(RunnerError::NonceMismatch {
+                expected: account_nonce,
+                got: tx.nonce(),
+            });
+        }
+
+        let queue = self.senders.entry(sender).or_default();
+        if let Some(existing) = queue.get(&tx.nonce()) {
+            let old = &existing.block_tx.transaction;
+            let bumped = |fee: u128| fee.saturating_mul(100 + self.price_bump) / 100;
+            if tx.max_fee_per_gas() < bumped(old.max_fee_per_gas())
+                || tx.max_priority_fee_per_gas() < bumped(old.max_priority_fee_per_gas())
+            {
+                return Err(RunnerError::ReplacementUnderpriced);
+            }
+        }
+
+        self.sequence += 1;
+        let replaced = queue.insert(
+            tx.nonce(),
+            PooledTransaction {
+                block_tx,
+                sequence: self.sequence,
+            },
+        );
+        Ok(replaced.map(|pooled| pooled.block_tx))
+    }
+
+    pub fn remove(&mut self, hash: [u8; 32]) -> Option<BlockTransaction> {
+        let (sender, nonce) = self.position(hash)?;
+        let queue = self.senders.get_mut(&sender)?;
+        let removed = queue.remove(&nonce);
+        if queue.is_empty() {
+            self.senders.remove(&sender);
+        }
+        removed.map(|pooled| pooled.block_tx)
+    }
+
+    pub fn get(&self, hash: [u8; 32]) -> Option<&BlockTransaction> {
+        let (sender, nonce) = self.position(hash)?;
+        Some(&self.senders[&sender][&nonce].block_tx)
+    }
+
+    pub fn len(&self) -> usize {
+        self.senders.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    /// Transactions executable now: per sender, the run of consecutive nonces
+    /// starting at the account nonce in `state`.
+    pub fn pending(&self, state: &EvmState) -> Vec<&BlockTransaction> {
+        self.split(state).0
+    }
+
+    /// Transactions behind a nonce gap, waiting for the missing nonce.
+    pub fn queued(&self, state: &EvmState) -> Vec<&BlockTransaction> {
+        self.split(state).1
+    }
+
+    /// Drop transactions whose nonce `state` has already used.
+    pub fn prune(&mut self, state: &EvmState) {
+        self.senders.retain(|sender, queue| {
+            let account_nonce = account_nonce(state, *sender);
+            queue.retain(|nonce, _| *nonce >= account_nonce);
+            !queue.is_empty()
+        });
+    }
+
+    /// Pick pending transactions for a block, highest effective tip first.
+    ///
+    /// Each sender's transactions stay in nonce order, so a sender's next
+    /// transaction only competes once the previous one is in. Transactions
+    /// whose fee cap is below `basefee` wait, and so do the later nonces of
+    /// their sender. The gas limits of the picked transactions fit in
+    /// `gas_limit`.
+    pub fn best_transactions(
+        &self,
+        state: &EvmState,
+        basefee: u128,
+        gas_limit: u64,
+    ) -> Vec<BlockTransaction> {
+        // (小费, 先到先得, 发送者, nonce)
+        let mut candidates = BinaryHeap::new();
+        let push_next = |candidates: &mut BinaryHeap<_>, sender: [u8; 20], nonce: u64| {
+            let Some(pooled) = self.senders.get(&sender).and_then(|queue| queue.get(&nonce)) else {
+                return;
+            };
+            let tx = &pooled.block_tx.transaction;
+            if tx.max_fee_per_gas() < basefee {
+                return;
+            }
+            let tip = tx.effective_gas_price(basefee) - basefee;
+            candidates.push((tip, Reverse(pooled.sequence), sender, nonce));
+        };
+        for sender in self.senders.keys() {
+            push_next(&mut candidates, *sender, account_nonce(state, *sender));
+        }
+
+        let mut selected = Vec::new();
+        let mut gas_left = gas_limit;
+        while let Some((_, _, sender, nonce)) = candidates.pop() {
+            let block_tx = &self.senders[&sender][&nonce].block_tx;
+            // 放不下时跳过该发送者 其后续nonce也无法打包
+            if block_tx.transaction.gas_limit() > gas_left {
+                continue;
+            }
+            gas_left -= block_tx.transaction.gas_limit();
+            selected.push(block_tx.clone());
+            push_next(&mut candidates, sender, nonce + 1);
+        }
+        selected
+    }
+
+    fn position(&self, hash: [u8; 32]) -> Option<([u8; 20], u64)> {
+        self.senders.iter().find_map(|(sender, queue)| {
+            queue
+                .iter()
+                .find(|(_, pooled)| pooled.block_tx.hash == hash)
+                .map(|(nonce, _)| (*sender, *nonce))
+        })
+    }
+
+    fn split(&self, state: &EvmState) -> (Vec<&BlockTransaction>, Vec<&BlockTransaction>) {
+        let mut pending = Vec::new();
+        let mut queued = Vec::new();
+        for (sender, queue) in &self.senders {
+            let mut next = account_nonce(state, *sender);
+            for (nonce, pooled) in queue {
+                if *nonce == next {
+                    pending.push(&pooled.block_tx);
+                    next += 1;
+                } else {
+                    queued.push(&pooled.block_tx);
+                }
+            }
+        }
+        (pending, queued)
+    }
+}
+
+fn account_nonce(state: &EvmState, address: [u8; 20]) -> u64 {
+    state.accounts.get(&address).map_or(0, |account| account.nonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::AccountState;
+    use crate::evm_core::transaction::types::{Transaction, TxEip1559};
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const BOB: [u8; 20] = [0xb0; 20];
+
+    // ALICE的nonce为1 BOB的nonce为0
+    fn state() -> EvmState {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                nonce: 1,
+                ..Default::default()
+            },
+        );
+        let mut state = EvmState::with_database(db);
+        state.load_account(ALICE).unwrap();
+        state
+    }
+
+    fn pooled(from: [u8; 20], nonce: u64, max_fee: u128, tip: u128) -> BlockTransaction {
+        let mut hash = [0u8; 32];
+        hash[0] = from[0];
+        hash[1] = nonce as u8;
+        hash[2] = max_fee as u8;
+        hash[3] = tip as u8;
+        BlockTransaction {
+            hash,
+            transaction: Transaction::Eip1559(TxEip1559 {
+                from,
+                chain_id: 1,
+                nonce,
+                max_priority_fee_per_gas: tip,
+                max_fee_per_gas: max_fee,
+                gas_limit: 21_000,
+                to: Some(BOB),
+                ..Default::default()
+            }),
+            raw: Vec::new(),
+        }
+    }
+
+    fn hashes(transactions: &[BlockTransaction]) -> Vec<[u8; 32]> {
+        transactions.iter().map(|block_tx| block_tx.hash).collect()
+    }
+
+    #[test]
+    fn used_nonce_is_rejected() {
+        let mut pool = TransactionPool::new();
+        assert_eq!(
+            pool.add(pooled(ALICE, 0, 10, 1), &state()),
+            Err(RunnerError::NonceMismatch {
+                expected: 1,
+                got: 0
+            })
+        );
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn replacement_needs_price_bump() {
+        let state = state();
+        let mut pool = TransactionPool::new();
+        let original = pooled(ALICE, 1, 100, 10);
+        pool.add(original.clone(), &state).unwrap();
+
+        // 两项费用都需至少提高10%
+        assert_eq!(
+            pool.add(pooled(ALICE, 1, 110, 10), &state),
+            Err(RunnerError::ReplacementUnderpriced)
+        );
+        assert_eq!(
+            pool.add(pooled(ALICE, 1, 200, 10), &state),
+            Err(RunnerError::ReplacementUnderpriced)
+        );
+        let replacement = pooled(ALICE, 1, 110, 11);
+        assert_eq!(pool.add(replacement.clone(), &state), Ok(Some(original.clone())));
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.get(replacement.hash), Some(&replacement));
+        assert_eq!(pool.get(original.hash), None);
+    }
+
+    #[test]
+    fn pending_and_queued() {
+        let state = state();
+        let mut pool = TransactionPool::new();
+        for tx in [
+            pooled(ALICE, 1, 10, 1),
+            pooled(ALICE, 2, 10, 1),
+            pooled(ALICE, 4, 10, 1),
+            pooled(BOB, 1, 10, 1),
+        ] {
+            pool.add(tx, &state).unwrap();
+        }
+
+        let pending: Vec<[u8; 32]> = pool.pending(&state).iter().map(|tx| tx.hash).collect();
+        let queued: Vec<[u8; 32]> = pool.queued(&state).iter().map(|tx| tx.hash).collect();
+        assert_eq!(
+            pending,
+            hashes(&[pooled(ALICE, 1, 10, 1), pooled(ALICE, 2, 10, 1)])
+        );
+        assert_eq!(
+            queued,
+            hashes(&[pooled(ALICE, 4, 10, 1), pooled(BOB, 1, 10, 1)])
+        );
+    }
+
+    #[test]
+    fn prune_drops_used_nonces() {
+        let mut state = state();
+        let mut pool = TransactionPool::new();
+        pool.add(pooled(ALICE, 1, 10, 1), &state).unwrap();
+        pool.add(pooled(ALICE, 2, 10, 1), &state).unwrap();
+
+        state.accounts.get_mut(&ALICE).unwrap().nonce = 2;
+        pool.prune(&state);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(
+            hashes(&pool.best_transactions(&state, 0, 30_000_000)),
+            hashes(&[pooled(ALICE, 2, 10, 1)])
+        );
+
+        state.accounts.get_mut(&ALICE).unwrap().nonce = 3;
+        pool.prune(&state);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn best_transactions_by_tip() {
+        let state = state();
+        let mut pool = TransactionPool::new();
+        for tx in [
+            pooled(ALICE, 1, 100, 2),
+            pooled(ALICE, 2, 100, 50),
+            pooled(BOB, 0, 100, 5),
+            pooled(BOB, 1, 100, 1),
+        ] {
+            pool.add(tx, &state).unwrap();
+        }
+
+        // ALICE的高小费交易须等nonce 1打包后才参与竞争
+        assert_eq!(
+            hashes(&pool.best_transactions(&state, 10, 30_000_000)),
+            hashes(&[
+                pooled(BOB, 0, 100, 5),
+                pooled(ALICE, 1, 100, 2),
+                pooled(ALICE, 2, 100, 50),
+                pooled(BOB, 1, 100, 1),
+            ])
+        );
+        // 有效小费受费用上限限制 100-98=2
+        assert_eq!(
+            hashes(&pool.best_transactions(&state, 98, 30_000_000)),
+            hashes(&[
+                pooled(ALICE, 1, 100, 2),
+                pooled(ALICE, 2, 100, 50),
+                pooled(BOB, 0, 100, 5),
+                pooled(BOB, 1, 100, 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn best_transactions_skip_rest_of_sender() {
+        let state = state();
+        let mut pool = TransactionPool::new();
+        pool.add(pooled(ALICE, 1, 5, 1), &state).unwrap();
+        pool.add(pooled(ALICE, 2, 100, 1), &state).unwrap();
+        pool.add(pooled(BOB, 0, 100, 1), &state).unwrap();
+
+        // 费用上限低于base fee 该发送者的后续nonce一并等待
+        assert_eq!(
+            hashes(&pool.best_transactions(&state, 10, 30_000_000)),
+            hashes(&[pooled(BOB, 0, 100, 1)])
+        );
+        // 区块只放得下一笔
+        assert_eq!(
+            hashes(&pool.best_transactions(&state, 0, 30_000)),
+            hashes(&[pooled(ALICE, 1, 5, 1)])
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use crate::evm_core::block::receipt::Receipt;
+use crate::evm_core::block::types::{Block, BlockHeader, BlockTransaction};
+
+/// When the chain turns pending transactions into blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MiningMode {
+    /// Every transaction is mined into its own block as soon as it is sent.
+    #[default]
+    Auto,
+    /// A block, possibly empty, every `n` seconds of simulated time.
+    Interval(u64),
+    /// Blocks are only mined by [`Chain::mine`](super::local::Chain::mine).
+    Manual,
+}
+
+/// A block produced by the chain together with its receipts.
+#[derive(Debug, Clone)]
+pub struct MinedBlock {
+    pub block: Block,
+    pub receipts: Vec<Receipt>,
+}
+
+impl MinedBlock {
+    pub fn header(&self) -> &BlockHeader {
+        &self.block.header
+    }
+
+    pub fn transactions(&self) -> &[BlockTransaction] {
+        &self.block.transactions
+    }
+}
@@ -1,17 +1,35 @@
+use std::collections::HashMap;
+
 use alloy_primitives::B256;
+use serde::{Deserialize, Serialize};
+
+use super::spec::SpecId;
+use crate::evm_core::gas::blob::blob_base_fee;
+use crate::evm_core::utils::serde_hex;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EvmContext {
+    #[serde(with = "serde_hex::option")]
+    pub chain_id: Option<[u8; 32]>,
+    #[serde(with = "serde_hex::option")]
     pub blockhash: Option<[u8; 32]>,
+    #[serde(with = "serde_hex::option")]
     pub block_number: Option<[u8; 32]>,
+    #[serde(with = "serde_hex::option")]
     pub coinbase: Option<[u8; 20]>,
+    #[serde(with = "serde_hex::option")]
     pub timestamp: Option<[u8; 32]>,
+    #[serde(with = "serde_hex::option")]
     pub gas_price: Option<[u8; 32]>,
+    #[serde(with = "serde_hex::option")]
     pub gas_limit: Option<[u8; 32]>,
+    #[serde(with = "serde_hex::option")]
     pub basefee: Option<[u8; 32]>,
     /// The difficulty of the block.
     ///
     /// Unused after the Paris (AKA the merge) upgrade, and replaced by `prevrandao`.
+    #[serde(with = "serde_hex::option")]
     pub difficulty: Option<[u8; 32]>,
     /// The output of the randomness beacon provided by the beacon chain.
     ///
@@ -20,12 +38,24 @@ pub struct EvmContext {
     /// NOTE: `prevrandao` can be found in a block in place of `mix_hash`.
     ///
     /// [EIP-4399]: https://eips.ethereum.org/EIPS/eip-4399
+    #[serde(with = "serde_hex::option")]
     pub prevrandao: Option<B256>,
+    /// Excess blob gas of the block, which sets the blob base fee (EIP-4844).
+    pub excess_blob_gas: Option<u64>,
+    /// Versioned hashes of the executing transaction's blobs, read by BLOBHASH.
+    #[serde(with = "serde_hex::vec")]
+    pub blob_hashes: Vec<[u8; 32]>,
+    /// Hashes of recent blocks by number, read by BLOCKHASH.
+    #[serde(with = "serde_hex::map")]
+    pub block_hashes: HashMap<u64, [u8; 32]>,
+    /// The hard fork rules to execute with.
+    pub spec_id: SpecId,
 }
 
 impl EvmContext {
     pub fn new() -> Self {
         Self {
+            chain_id: None,
             blockhash: None,
             block_number: None,
             coinbase: None,
@@ -35,6 +65,22 @@ impl EvmContext {
             basefee: None,
             difficulty: None,
             prevrandao: None,
+            excess_blob_gas: None,
+            blob_hashes: Vec::new(),
+            block_hashes: HashMap::new(),
+            spec_id: SpecId::default(),
         }
     }
+
+    //未设置excess_blob_gas时按0计算 即最低价格
+    pub fn blob_base_fee(&self) -> u128 {
+        blob_base_fee(self.excess_blob_gas.unwrap_or(0), self.spec_id)
+    }
+}
+
+
+impl Default for EvmContext {
+    fn default() -> Self {
+        Self::new()
+    }
 }
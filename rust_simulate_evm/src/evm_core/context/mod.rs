@@ -1 +1,3 @@
-pub mod evm_context;
\ No newline at end of file
+pub mod evm_context;
+
+pub mod spec;
\ No newline at end of file
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// Hard fork selection for the interpreter.
+///
+/// Variants are ordered by activation, so a spec enables every rule
+/// introduced by the forks before it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SpecId {
+    Frontier,
+    Homestead,
+    Tangerine,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Merge,
+    Shanghai,
+    #[default]
+    Cancun,
+    Prague,
+}
+
+impl SpecId {
+    /// Returns `true` if the rules of `other` are active under `self`.
+    pub fn is_enabled_in(&self, other: SpecId) -> bool {
+        *self >= other
+    }
+}
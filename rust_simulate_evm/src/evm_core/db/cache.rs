@@ -0,0 +1,488 @@
+/*
+分叉缓存
+记录模式: 读取透传给远程数据库 并把每个响应(账户 代码 存储 区块哈希)记录下来 写入JSON文件
+回放模式: 没有远程数据库 只从缓存文件读取 未命中时报错
+缓存按链id和区块号区分 回放时需与文件中的一致
+*/
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ethers::types::U256;
+use serde_json::{json, Map, Value};
+
+use crate::evm_core::storage::KECCAK_EMPTY;
+use crate::evm_core::utils::byte_operate::{hex_to_address, hex_to_bytes, hex_to_u64, hex_to_word};
+use crate::evm_core::utils::error::RunnerError;
+
+use super::database::{AccountInfo, Database};
+
+/// Remote state read by a fork at one block of one chain.
+///
+/// Maps are ordered so that a saved cache diffs cleanly when checked in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForkCache {
+    pub chain_id: u64,
+    pub block_number: u64,
+    /// Accounts by address, `None` for accounts the remote reported missing.
+    /// Code is kept in [`ForkCache::codes`], never in the account.
+    pub accounts: BTreeMap<[u8; 20], Option<AccountInfo>>,
+    pub codes: BTreeMap<[u8; 32], Vec<u8>>,
+    pub storage: BTreeMap<[u8; 20], BTreeMap<[u8; 32], [u8; 32]>>,
+    pub block_hashes: BTreeMap<u64, [u8; 32]>,
+}
+
+impl ForkCache {
+    pub fn new(chain_id: u64, block_number: u64) -> Self {
+        Self {
+            chain_id,
+            block_number,
+            ..Default::default()
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RunnerError> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|e| file_error(path, e))?;
+        let value: Value = serde_json::from_str(&json).map_err(|e| file_error(path, e))?;
+        Self::from_json(&value)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RunnerError> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(&self.to_json()).map_err(|e| file_error(path, e))?;
+        fs::write(path, json).map_err(|e| file_error(path, e))
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Cache db                                  */
+/* -------------------------------------------------------------------------- */
+/// A [`Database`] that records what a remote database returns, or replays a
+/// recording without one.
+#[derive(Debug)]
+pub struct CacheDb {
+    pub cache: ForkCache,
+    //回放模式下为None
+    remote: Option<Box<dyn Database>>,
+    //记录模式下Drop时写入的文件
+    path: Option<PathBuf>,
+    dirty: bool,
+}
+
+impl CacheDb {
+    /// Record reads from `remote`, a fork of chain `chain_id` at
+    /// `block_number`, to the JSON file at `path`.
+    ///
+    /// An existing recording of the same fork at `path` is extended, so only
+    /// what it lacks is fetched. The file is written by [`CacheDb::flush`] and
+    /// when the database is dropped.
+    pub fn record(
+        remote: impl Database + 'static,
+        chain_id: u64,
+        block_number: u64,
+        path: impl Into<PathBuf>,
+    ) -> Result<Self, RunnerError> {
+        let path = path.into();
+        let cache = match path.exists() {
+            true => ForkCache::load(&path)?,
+            false => ForkCache::new(chain_id, block_number),
+        };
+        if (cache.chain_id, cache.block_number) != (chain_id, block_number) {
+            return Err(RunnerError::DatabaseError(format!(
+                "{} records chain {} at block {}, not chain {} at block {}",
+                path.display(),
+                cache.chain_id,
+                cache.block_number,
+                chain_id,
+                block_number
+            )));
+        }
+        Ok(Self {
+            cache,
+            remote: Some(Box::new(remote)),
+            path: Some(path),
+            dirty: false,
+        })
+    }
+
+    /// Serve reads from `cache` only. Anything it lacks is a
+    /// [`RunnerError::ForkCacheMiss`].
+    pub fn replay(cache: ForkCache) -> Self {
+        Self {
+            cache,
+            remote: None,
+            path: None,
+            dirty: false,
+        }
+    }
+
+    /// Replay the recording at `path`.
+    pub fn replay_file(path: impl AsRef<Path>) -> Result<Self, RunnerError> {
+        Ok(Self::replay(ForkCache::load(path)?))
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.remote.is_none()
+    }
+
+    /// Write what was recorded since the last flush.
+    pub fn flush(&mut self) -> Result<(), RunnerError> {
+        if let (Some(path), true) = (&self.path, self.dirty) {
+            self.cache.save(path)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    //未命中时 记录模式查询远程数据库 回放模式报错
+    fn remote(&mut self, missing: impl FnOnce() -> String) -> Result<&mut dyn Database, RunnerError> {
+        match &mut self.remote {
+            Some(remote) => {
+                self.dirty = true;
+                Ok(remote.as_mut())
+            }
+            None => Err(RunnerError::ForkCacheMiss(missing())),
+        }
+    }
+}
+
+impl Drop for CacheDb {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl Database for CacheDb {
+    fn basic(&mut self, address: [u8; 20]) -> Result<Option<AccountInfo>, RunnerError> {
+        if !self.cache.accounts.contains_key(&address) {
+            let mut info = self
+                .remote(|| format!("account {}", hex_string(&address)))?
+                .basic(address)?;
+            if let Some(info) = &mut info {
+                if let Some(code) = info.code.take() {
+                    self.cache.codes.insert(info.code_hash, code);
+                }
+            }
+            self.cache.accounts.insert(address, info);
+        }
+        Ok(self.cache.accounts[&address].clone().map(|mut info| {
+            info.code = self.cache.codes.get(&info.code_hash).cloned();
+            info
+        }))
+    }
+
+    fn code_by_hash(&mut self, code_hash: [u8; 32]) -> Result<Vec<u8>, RunnerError> {
+        if code_hash == KECCAK_EMPTY {
+            return Ok(Vec::new());
+        }
+        if let Some(code) = self.cache.codes.get(&code_hash) {
+            return Ok(code.clone());
+        }
+        let code = self
+            .remote(|| format!("code {}", hex_string(&code_hash)))?
+            .code_by_hash(code_hash)?;
+        self.cache.codes.insert(code_hash, code.clone());
+        Ok(code)
+    }
+
+    fn storage(&mut self, address: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError> {
+        if let Some(value) = self.cache.storage.get(&address).and_then(|slots| slots.get(&slot)) {
+            return Ok(*value);
+        }
+        let value = self
+            .remote(|| format!("storage slot {} of {}", hex_string(&slot), hex_string(&address)))?
+            .storage(address, slot)?;
+        self.cache.storage.entry(address).or_default().insert(slot, value);
+        Ok(value)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<[u8; 32], RunnerError> {
+        if let Some(hash) = self.cache.block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        let hash = self
+            .remote(|| format!("hash of block {}", number))?
+            .block_hash(number)?;
+        self.cache.block_hashes.insert(number, hash);
+        Ok(hash)
+    }
+
+    //只转发缓存中没有的部分 回放模式下无需预取
+    fn prefetch(&mut self, accounts: &[[u8; 20]], storage: &[([u8; 20], [u8; 32])]) {
+        let Some(remote) = &mut self.remote else {
+            return;
+        };
+        let accounts: Vec<_> = accounts
+            .iter()
+            .filter(|address| !self.cache.accounts.contains_key(*address))
+            .copied()
+            .collect();
+        let storage: Vec<_> = storage
+            .iter()
+            .filter(|(address, slot)| {
+                !self
+                    .cache
+                    .storage
+                    .get(address)
+                    .is_some_and(|slots| slots.contains_key(slot))
+            })
+            .copied()
+            .collect();
+        remote.prefetch(&accounts, &storage);
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    JSON                                    */
+/* -------------------------------------------------------------------------- */
+impl ForkCache {
+    /// Hex strings throughout: quantities without leading zeros, addresses,
+    /// hashes and slots at full width.
+    pub fn to_json(&self) -> Value {
+        let accounts: Map<String, Value> = self
+            .accounts
+            .iter()
+            .map(|(address, info)| {
+                let info = info.as_ref().map_or(Value::Null, |info| {
+                    json!({
+                        "balance": quantity(&info.balance),
+                        "nonce": format!("{:#x}", info.nonce),
+                        "codeHash": hex_string(&info.code_hash),
+                    })
+                });
+                (hex_string(address), info)
+            })
+            .collect();
+        let codes: Map<String, Value> = self
+            .codes
+            .iter()
+            .map(|(hash, code)| (hex_string(hash), json!(hex_string(code))))
+            .collect();
+        let storage: Map<String, Value> = self
+            .storage
+            .iter()
+            .map(|(address, slots)| {
+                let slots: Map<String, Value> = slots
+                    .iter()
+                    .map(|(slot, value)| (hex_string(slot), json!(quantity(value))))
+                    .collect();
+                (hex_string(address), Value::Object(slots))
+            })
+            .collect();
+        let block_hashes: Map<String, Value> = self
+            .block_hashes
+            .iter()
+            .map(|(number, hash)| (format!("{:#x}", number), json!(hex_string(hash))))
+            .collect();
+
+        json!({
+            "chainId": format!("{:#x}", self.chain_id),
+            "blockNumber": format!("{:#x}", self.block_number),
+            "accounts": accounts,
+            "codes": codes,
+            "storage": storage,
+            "blockHashes": block_hashes,
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, RunnerError> {
+        let mut cache = ForkCache::new(
+            hex_to_u64(field(value, "chainId")?)?,
+            hex_to_u64(field(value, "blockNumber")?)?,
+        );
+
+        for (address, info) in object(value, "accounts")? {
+            let info = match info {
+                Value::Null => None,
+                info => Some(AccountInfo {
+                    balance: hex_to_word(field(info, "balance")?)?,
+                    nonce: hex_to_u64(field(info, "nonce")?)?,
+                    code_hash: hex_to_word(field(info, "codeHash")?)?,
+                    code: None,
+                }),
+            };
+            cache.accounts.insert(hex_to_address(address)?, info);
+        }
+        for (hash, code) in object(value, "codes")? {
+            let code = code.as_str().ok_or_else(|| invalid_cache("code is not a string"))?;
+            cache.codes.insert(hex_to_word(hash)?, hex_to_bytes(code)?);
+        }
+        for (address, slots) in object(value, "storage")? {
+            let slots = slots
+                .as_object()
+                .ok_or_else(|| invalid_cache("storage is not an object"))?;
+            let entry = cache.storage.entry(hex_to_address(address)?).or_default();
+            for (slot, value) in slots {
+                let value = value.as_str().ok_or_else(|| invalid_cache("slot is not a string"))?;
+                entry.insert(hex_to_word(slot)?, hex_to_word(value)?);
+            }
+        }
+        for (number, hash) in object(value, "blockHashes")? {
+            let hash = hash
+                .as_str()
+                .ok_or_else(|| invalid_cache("block hash is not a string"))?;
+            cache.block_hashes.insert(hex_to_u64(number)?, hex_to_word(hash)?);
+        }
+        Ok(cache)
+    }
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+//数值按JSON-RPC规则去掉前导0
+fn quantity(word: &[u8; 32]) -> String {
+    format!("{:#x}", U256::from_big_endian(word))
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Result<&'a str, RunnerError> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid_cache(&format!("missing field {}", key)))
+}
+
+//缺少的表按空处理
+fn object<'a>(value: &'a Value, key: &str) -> Result<impl Iterator<Item = (&'a String, &'a Value)>, RunnerError> {
+    match value.get(key) {
+        None => Ok(None.into_iter().flatten()),
+        Some(Value::Object(map)) => Ok(Some(map.iter()).into_iter().flatten()),
+        Some(_) => Err(invalid_cache(&format!("{} is not an object", key))),
+    }
+}
+
+fn invalid_cache(reason: &str) -> RunnerError {
+    RunnerError::DatabaseError(format!("invalid fork cache: {}", reason))
+}
+
+fn file_error(path: &Path, error: impl std::fmt::Display) -> RunnerError {
+    RunnerError::DatabaseError(format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::AccountState;
+    use ethers::utils::keccak256;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const TOKEN: [u8; 20] = [0x70; 20];
+    const NOBODY: [u8; 20] = [0x0b; 20];
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("minirevm-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    fn remote() -> MemoryDb {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                nonce: 3,
+                balance: value(9),
+                ..Default::default()
+            },
+        );
+        db.insert_code(TOKEN, vec![0x60, 0x00, 0x54, 0x00]);
+        db.insert_storage(TOKEN, value(1), value(7));
+        db
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let path = temp_path("record");
+        let mut db = CacheDb::record(remote(), 1, 100, &path).unwrap();
+        assert!(!db.is_replay());
+        let alice = db.basic(ALICE).unwrap();
+        let token = db.basic(TOKEN).unwrap().unwrap();
+        assert_eq!(token.code, Some(vec![0x60, 0x00, 0x54, 0x00]));
+        assert_eq!(db.storage(TOKEN, value(1)).unwrap(), value(7));
+        assert_eq!(db.basic(NOBODY).unwrap(), None);
+        let hash = db.block_hash(99).unwrap();
+        // 代码只存一份 不放在账户里
+        assert_eq!(db.cache.accounts[&TOKEN].as_ref().unwrap().code, None);
+        drop(db);
+
+        let mut db = CacheDb::replay_file(&path).unwrap();
+        assert!(db.is_replay());
+        assert_eq!(db.basic(ALICE).unwrap(), alice);
+        assert_eq!(db.basic(TOKEN).unwrap().unwrap(), token);
+        assert_eq!(db.code_by_hash(token.code_hash).unwrap(), vec![0x60, 0x00, 0x54, 0x00]);
+        assert_eq!(db.storage(TOKEN, value(1)).unwrap(), value(7));
+        assert_eq!(db.basic(NOBODY).unwrap(), None);
+        assert_eq!(db.block_hash(99).unwrap(), hash);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn replay_miss_is_an_error() {
+        let mut db = CacheDb::replay(ForkCache::new(1, 100));
+        assert!(matches!(db.basic(ALICE), Err(RunnerError::ForkCacheMiss(_))));
+        assert!(matches!(db.storage(TOKEN, value(1)), Err(RunnerError::ForkCacheMiss(_))));
+        assert!(matches!(db.block_hash(99), Err(RunnerError::ForkCacheMiss(_))));
+        assert!(matches!(
+            db.code_by_hash(keccak256([0x00])),
+            Err(RunnerError::ForkCacheMiss(_))
+        ));
+        // 空代码不需要缓存
+        assert_eq!(db.code_by_hash(KECCAK_EMPTY).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn record_extends_the_same_fork_only() {
+        let path = temp_path("extend");
+        let mut db = CacheDb::record(remote(), 1, 100, &path).unwrap();
+        db.basic(ALICE).unwrap();
+        db.flush().unwrap();
+        drop(db);
+
+        // 已缓存的账户不再向远程查询
+        let mut db = CacheDb::record(MemoryDb::new(), 1, 100, &path).unwrap();
+        assert_eq!(db.basic(ALICE).unwrap().unwrap().nonce, 3);
+        drop(db);
+
+        assert!(matches!(
+            CacheDb::record(remote(), 5, 100, &path),
+            Err(RunnerError::DatabaseError(_))
+        ));
+        assert!(matches!(
+            CacheDb::record(remote(), 1, 101, &path),
+            Err(RunnerError::DatabaseError(_))
+        ));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut db = CacheDb::replay(ForkCache::new(1, 100));
+        db.remote = Some(Box::new(remote()));
+        db.basic(ALICE).unwrap();
+        db.basic(TOKEN).unwrap();
+        db.basic(NOBODY).unwrap();
+        db.storage(TOKEN, value(1)).unwrap();
+        db.block_hash(99).unwrap();
+
+        let json = db.cache.to_json();
+        assert_eq!(json["chainId"], "0x1");
+        assert_eq!(json["blockNumber"], "0x64");
+        assert_eq!(json["accounts"][hex_string(&ALICE)]["balance"], "0x9");
+        assert_eq!(json["accounts"][hex_string(&NOBODY)], Value::Null);
+        assert_eq!(json["storage"][hex_string(&TOKEN)][hex_string(&value(1))], "0x7");
+        assert_eq!(ForkCache::from_json(&json).unwrap(), db.cache);
+
+        assert!(ForkCache::from_json(&json!({ "chainId": "0x1" })).is_err());
+        let codes_not_object = json!({ "chainId": "0x1", "blockNumber": "0x1", "codes": [] });
+        assert!(ForkCache::from_json(&codes_not_object).is_err());
+    }
+}
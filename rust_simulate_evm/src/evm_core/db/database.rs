@@ -0,0 +1,100 @@
+/*
+状态数据库接口
+EvmState只缓存执行中读写过的账户 缓存中没有的数据通过Database读取
+自定义后端(快照 测试数据 缓存层)实现该trait即可接入
+*/
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::evm_core::storage::KECCAK_EMPTY;
+use crate::evm_core::utils::error::RunnerError;
+
+/// Account fields stored outside the storage trie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountInfo {
+    pub balance: [u8; 32],
+    pub nonce: u64,
+    pub code_hash: [u8; 32],
+    /// The code, when the backend has it at hand. Otherwise it is fetched
+    /// with [`Database::code_by_hash`].
+    pub code: Option<Vec<u8>>,
+}
+
+impl Default for AccountInfo {
+    fn default() -> Self {
+        Self {
+            balance: [0u8; 32],
+            nonce: 0,
+            code_hash: KECCAK_EMPTY,
+            code: None,
+        }
+    }
+}
+
+/// Every account a database holds with the storage slots it sets, see
+/// [`Database::state_keys`].
+pub type StateKeys = BTreeMap<[u8; 20], Vec<[u8; 32]>>;
+
+/// Read access to the state an [`EvmState`](crate::EvmState) starts from.
+///
+/// Each value is read at most once per state and then served from its cache,
+/// so backends need not cache themselves. Methods take `&mut self` so that
+/// they can, e.g. to record what was read. Backends are `Send` so that a
+/// state can move to another thread.
+pub trait Database: Send {
+    /// The account at `address`, or `None` if it does not exist.
+    fn basic(&mut self, address: [u8; 20]) -> Result<Option<AccountInfo>, RunnerError>;
+
+    /// Code with keccak256 `code_hash`.
+    fn code_by_hash(&mut self, code_hash: [u8; 32]) -> Result<Vec<u8>, RunnerError>;
+
+    /// Value of storage `slot` of `address`, zero when unset.
+    fn storage(&mut self, address: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError>;
+
+    /// Hash of block `number`.
+    fn block_hash(&mut self, number: u64) -> Result<[u8; 32], RunnerError>;
+
+    /// Hint that `accounts` and `storage` slots are likely to be read soon,
+    /// so that a remote backend can fetch them together. Failures are left
+    /// for the later reads to report. Does nothing by default.
+    fn prefetch(&mut self, _accounts: &[[u8; 20]], _storage: &[([u8; 20], [u8; 32])]) {}
+
+    /// Every account and storage slot held, so that a state root can cover
+    /// what was never read. `None`, the default, when the backend cannot
+    /// list them, e.g. a remote node.
+    fn state_keys(&mut self) -> Result<Option<StateKeys>, RunnerError> {
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for dyn Database {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Database")
+    }
+}
+
+impl<T: Database + ?Sized> Database for Box<T> {
+    fn basic(&mut self, address: [u8; 20]) -> Result<Option<AccountInfo>, RunnerError> {
+        (**self).basic(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: [u8; 32]) -> Result<Vec<u8>, RunnerError> {
+        (**self).code_by_hash(code_hash)
+    }
+
+    fn storage(&mut self, address: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError> {
+        (**self).storage(address, slot)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<[u8; 32], RunnerError> {
+        (**self).block_hash(number)
+    }
+
+    fn prefetch(&mut self, accounts: &[[u8; 20]], storage: &[([u8; 20], [u8; 32])]) {
+        (**self).prefetch(accounts, storage)
+    }
+
+    fn state_keys(&mut self) -> Result<Option<StateKeys>, RunnerError> {
+        (**self).state_keys()
+    }
+}
@@ -0,0 +1,889 @@
+/*
+基于文件的持久化状态库
+只追加的日志文件 每条记录为一个账户 代码 存储槽或区块哈希 内存中只保留 键哈希->最新记录偏移 的索引
+同一索引键的记录通过prev偏移串成链 读取时沿链找到键相同的最新记录 不同键哈希冲突时同样沿链区分
+每次写入以COMMIT记录结束 打开时丢弃最后一个COMMIT之后不完整的部分 重建索引
+快照即日志长度 只读取该位置之前的记录 日志只追加 所以重启后位置依然有效
+账户记录带存储纪元 存储被整体替换(新建 销毁)时纪元为该记录的偏移 纪元之前的槽记录视为0
+*/
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use ethers::utils::keccak256;
+
+use crate::evm_core::storage::{EvmState, KECCAK_EMPTY};
+use crate::evm_core::utils::byte_operate::u64_to_u256_array;
+use crate::evm_core::utils::error::RunnerError;
+
+use super::database::{AccountInfo, Database, StateKeys};
+
+//版本2: 索引改用FNV-1a 记录中的链接依赖索引哈希
+const MAGIC: &[u8; 8] = b"MREVMDB2";
+
+//记录类型
+const ACCOUNT: u8 = 1;
+const CODE: u8 = 2;
+const STORAGE: u8 = 3;
+const BLOCK_HASH: u8 = 4;
+const COMMIT: u8 = 5;
+
+//长度u32之后: 类型u8 prev偏移u64 键 值
+const BODY_HEADER_LEN: usize = 9;
+
+/// A persistent [`Database`] in a single append-only file.
+///
+/// Only an index from key hashes to file offsets is kept in memory, about
+/// 16 bytes per stored key, so states far larger than memory fit. Values are
+/// read from the file on demand. Clones share the file.
+///
+/// Writes come from [`FileDb::commit`] and, with a remote database, from
+/// storing what the remote returns, so a fork is fetched only once across
+/// runs. Each write is atomic: one cut short by a crash is dropped when the
+/// file is opened again.
+#[derive(Debug, Clone)]
+pub struct FileDb {
+    store: Arc<Mutex<Store>>,
+    //快照读取的日志长度 None为最新状态
+    end: Option<u64>,
+}
+
+impl FileDb {
+    /// Open the store at `path`, creating an empty one if there is none.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, RunnerError> {
+        Self::with_store(Store::open(path.into(), None)?)
+    }
+
+    /// Like [`FileDb::open`], reading what the store lacks from `remote` and
+    /// storing it. The store must only ever be used with the same remote
+    /// state, e.g. a fork pinned to one block.
+    pub fn with_remote(
+        path: impl Into<PathBuf>,
+        remote: impl Database + 'static,
+    ) -> Result<Self, RunnerError> {
+        Self::with_store(Store::open(path.into(), Some(Box::new(remote)))?)
+    }
+
+    fn with_store(store: Store) -> Result<Self, RunnerError> {
+        Ok(Self {
+            store: Arc::new(Mutex::new(store)),
+            end: None,
+        })
+    }
+
+    /// The current length of the log. Reading at it with [`FileDb::at`]
+    /// gives the state as it is now, also after the file is reopened.
+    pub fn position(&self) -> u64 {
+        self.end.unwrap_or_else(|| self.store().len)
+    }
+
+    /// A read-only view of the state as it is now, unaffected by later
+    /// commits.
+    pub fn snapshot(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            end: Some(self.position()),
+        }
+    }
+
+    /// A read-only view of the state at `position`, as returned by
+    /// [`FileDb::position`].
+    pub fn at(&self, position: u64) -> Result<Self, RunnerError> {
+        let store = self.store();
+        if position < MAGIC.len() as u64 || position > store.len {
+            return Err(file_error(
+                &store.path,
+                format!("no position {} in a log of {} bytes", position, store.len),
+            ));
+        }
+        Ok(Self {
+            store: self.store.clone(),
+            end: Some(position),
+        })
+    }
+
+    pub fn is_snapshot(&self) -> bool {
+        self.end.is_some()
+    }
+
+    //持锁的线程panic后仍可使用 与RefCell的行为一致
+    fn store(&self) -> MutexGuard<'_, Store> {
+        self.store.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Store the accounts, code and storage cached in `state`, typically a
+    /// state built on this database after running transactions. Accounts the
+    /// state found missing or destroyed are stored as missing.
+    ///
+    /// Readers of the latest state see the changes at once, snapshots do not.
+    pub fn commit(&self, state: &EvmState) -> Result<(), RunnerError> {
+        let mut store = self.store();
+        if self.end.is_some() {
+            return Err(file_error(&store.path, "cannot commit to a snapshot"));
+        }
+        store.commit(state)
+    }
+
+    /// Write buffered records to the file. Commits flush by themselves.
+    pub fn flush(&self) -> Result<(), RunnerError> {
+        self.store().flush()
+    }
+}
+
+impl Database for FileDb {
+    fn basic(&mut self, address: [u8; 20]) -> Result<Option<AccountInfo>, RunnerError> {
+        let end = self.end.unwrap_or(u64::MAX);
+        let mut store = self.store();
+        if let Some(account) = store.account(address, end)? {
+            return Ok(account.info());
+        }
+        let Some(remote) = &mut store.remote else {
+            return Ok(None);
+        };
+        let info = remote.basic(address)?;
+        // 快照不能写入 否则新记录会遮住之后提交的值
+        if self.end.is_none() {
+            store.store_remote_account(address, &info)?;
+        }
+        Ok(info)
+    }
+
+    fn code_by_hash(&mut self, code_hash: [u8; 32]) -> Result<Vec<u8>, RunnerError> {
+        if code_hash == KECCAK_EMPTY {
+            return Ok(Vec::new());
+        }
+        let end = self.end.unwrap_or(u64::MAX);
+        let mut store = self.store();
+        if let Some((_, code)) = store.find(CODE, &code_hash, end)? {
+            return Ok(code);
+        }
+        let Some(remote) = &mut store.remote else {
+            return Err(file_error(
+                &store.path,
+                format!("code {} is not stored", hex_string(&code_hash)),
+            ));
+        };
+        let code = remote.code_by_hash(code_hash)?;
+        if self.end.is_none() {
+            store.append(CODE, &code_hash, &code)?;
+            store.end_write()?;
+        }
+        Ok(code)
+    }
+
+    fn storage(&mut self, address: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError> {
+        let end = self.end.unwrap_or(u64::MAX);
+        let mut store = self.store();
+        let account = store.account(address, end)?;
+        let epoch = account.map_or(0, |account| account.epoch);
+        if let Some(value) = store.slot(address, slot, epoch, end)? {
+            return Ok(value);
+        }
+        // 存储被替换过或账户不存在时 未写入的槽为0
+        if account.is_some_and(|account| !account.exists || account.epoch != 0) {
+            return Ok([0u8; 32]);
+        }
+        let Some(remote) = &mut store.remote else {
+            return Ok([0u8; 32]);
+        };
+        let value = remote.storage(address, slot)?;
+        if self.end.is_none() {
+            store.append(STORAGE, &slot_key(address, slot), &value)?;
+            store.end_write()?;
+        }
+        Ok(value)
+    }
+
+    //未存储且没有远程数据库时沿用keccak256(number)作为哈希 与MemoryDb一致
+    fn block_hash(&mut self, number: u64) -> Result<[u8; 32], RunnerError> {
+        let end = self.end.unwrap_or(u64::MAX);
+        let mut store = self.store();
+        if let Some((_, hash)) = store.find(BLOCK_HASH, &number.to_be_bytes(), end)? {
+            return Ok(word(&hash));
+        }
+        let Some(remote) = &mut store.remote else {
+            return Ok(keccak256(u64_to_u256_array(number)));
+        };
+        let hash = remote.block_hash(number)?;
+        if self.end.is_none() {
+            store.append(BLOCK_HASH, &number.to_be_bytes(), &hash)?;
+            store.end_write()?;
+        }
+        Ok(hash)
+    }
+
+    //只转发本地没有的部分
+    fn prefetch(&mut self, accounts: &[[u8; 20]], storage: &[([u8; 20], [u8; 32])]) {
+        let end = self.end.unwrap_or(u64::MAX);
+        let mut store = self.store();
+        if store.remote.is_none() {
+            return;
+        }
+        let accounts: Vec<_> = accounts
+            .iter()
+            .filter(|address| matches!(store.account(**address, end), Ok(None)))
+            .copied()
+            .collect();
+        let storage: Vec<_> = storage
+            .iter()
+            .filter(|(address, slot)| match store.account(*address, end) {
+                Ok(None) => true,
+                Ok(Some(account)) => {
+                    account.exists
+                        && account.epoch == 0
+                        && matches!(store.slot(*address, *slot, 0, end), Ok(None))
+                }
+                Err(_) => false,
+            })
+            .copied()
+            .collect();
+        if let Some(remote) = &mut store.remote {
+            remote.prefetch(&accounts, &storage);
+        }
+    }
+
+    //有远程数据库时本地只有读到过的部分 无法列出全部状态
+    fn state_keys(&mut self) -> Result<Option<StateKeys>, RunnerError> {
+        let end = self.end.unwrap_or(u64::MAX);
+        let mut store = self.store();
+        if store.remote.is_some() {
+            return Ok(None);
+        }
+        store.keys(end).map(Some)
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Store                                   */
+/* -------------------------------------------------------------------------- */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StoredAccount {
+    exists: bool,
+    balance: [u8; 32],
+    nonce: u64,
+    code_hash: [u8; 32],
+    //此偏移之前的存储槽记录无效 0表示存储从未被替换
+    epoch: u64,
+}
+
+impl StoredAccount {
+    fn missing(epoch: u64) -> Self {
+        Self {
+            exists: false,
+            balance: [0u8; 32],
+            nonce: 0,
+            code_hash: KECCAK_EMPTY,
+            epoch,
+        }
+    }
+
+    fn info(&self) -> Option<AccountInfo> {
+        self.exists.then_some(AccountInfo {
+            balance: self.balance,
+            nonce: self.nonce,
+            code_hash: self.code_hash,
+            code: None,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(81);
+        bytes.push(self.exists as u8);
+        bytes.extend_from_slice(&self.balance);
+        bytes.extend_from_slice(&self.nonce.to_be_bytes());
+        bytes.extend_from_slice(&self.code_hash);
+        bytes.extend_from_slice(&self.epoch.to_be_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        Self {
+            exists: bytes[0] != 0,
+            balance: word(&bytes[1..33]),
+            nonce: u64::from_be_bytes(bytes[33..41].try_into().unwrap()),
+            code_hash: word(&bytes[41..73]),
+            epoch: u64::from_be_bytes(bytes[73..81].try_into().unwrap()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Store {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    reader: File,
+    //键哈希 -> 该键哈希最新记录的偏移
+    index: HashMap<u64, u64>,
+    //含缓冲中未写入文件的记录
+    len: u64,
+    remote: Option<Box<dyn Database>>,
+}
+
+impl Store {
+    fn open(path: PathBuf, remote: Option<Box<dyn Database>>) -> Result<Self, RunnerError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| file_error(&path, e))?;
+        let mut index = HashMap::new();
+        let file_len = file.metadata().map_err(|e| file_error(&path, e))?.len();
+        let len = match file_len {
+            0 => {
+                file.write_all(MAGIC).map_err(|e| file_error(&path, e))?;
+                MAGIC.len() as u64
+            }
+            _ => scan(&file, file_len, &mut index).map_err(|e| file_error(&path, e))?,
+        };
+        // 丢弃未完成的写入
+        if len < file_len {
+            file.set_len(len).map_err(|e| file_error(&path, e))?;
+        }
+        file.seek(SeekFrom::Start(len))
+            .map_err(|e| file_error(&path, e))?;
+        let reader = File::open(&path).map_err(|e| file_error(&path, e))?;
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+            reader,
+            index,
+            len,
+            remote,
+        })
+    }
+
+    fn append(&mut self, tag: u8, key: &[u8], value: &[u8]) -> Result<u64, RunnerError> {
+        let offset = self.len;
+        let index_key = index_key(tag, key);
+        let prev = self.index.get(&index_key).copied().unwrap_or(0);
+        self.write_record(tag, prev, key, value)?;
+        self.index.insert(index_key, offset);
+        Ok(offset)
+    }
+
+    //结束一次写入 打开文件时只保留以COMMIT结尾的部分
+    fn end_write(&mut self) -> Result<(), RunnerError> {
+        self.write_record(COMMIT, 0, &[], &[])
+    }
+
+    fn write_record(
+        &mut self,
+        tag: u8,
+        prev: u64,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), RunnerError> {
+        let body_len = BODY_HEADER_LEN + key.len() + value.len();
+        let mut record = Vec::with_capacity(4 + body_len);
+        record.extend_from_slice(&(body_len as u32).to_le_bytes());
+        record.push(tag);
+        record.extend_from_slice(&prev.to_le_bytes());
+        record.extend_from_slice(key);
+        record.extend_from_slice(value);
+        self.writer
+            .write_all(&record)
+            .map_err(|e| file_error(&self.path, e))?;
+        self.len += record.len() as u64;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), RunnerError> {
+        self.writer.flush().map_err(|e| file_error(&self.path, e))
+    }
+
+    /// The value of the latest record of `key` before `end`, with its offset.
+    fn find(
+        &mut self,
+        tag: u8,
+        key: &[u8],
+        end: u64,
+    ) -> Result<Option<(u64, Vec<u8>)>, RunnerError> {
+        let Some(mut offset) = self.index.get(&index_key(tag, key)).copied() else {
+            return Ok(None);
+        };
+        if !self.writer.buffer().is_empty() {
+            self.flush()?;
+        }
+        while offset != 0 {
+            let body = self.read_body(offset)?;
+            if offset < end
+                && body[0] == tag
+                && &body[BODY_HEADER_LEN..BODY_HEADER_LEN + key.len()] == key
+            {
+                return Ok(Some((offset, body[BODY_HEADER_LEN + key.len()..].to_vec())));
+            }
+            offset = u64::from_le_bytes(body[1..9].try_into().unwrap());
+        }
+        Ok(None)
+    }
+
+    fn read_body(&mut self, offset: u64) -> Result<Vec<u8>, RunnerError> {
+        let path = &self.path;
+        let mut len = [0u8; 4];
+        self.reader
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| self.reader.read_exact(&mut len))
+            .map_err(|e| file_error(path, e))?;
+        let mut body = vec![0u8; u32::from_le_bytes(len) as usize];
+        self.reader
+            .read_exact(&mut body)
+            .map_err(|e| file_error(path, e))?;
+        Ok(body)
+    }
+
+    fn account(
+        &mut self,
+        address: [u8; 20],
+        end: u64,
+    ) -> Result<Option<StoredAccount>, RunnerError> {
+        Ok(self
+            .find(ACCOUNT, &address, end)?
+            .map(|(_, value)| StoredAccount::decode(&value)))
+    }
+
+    //纪元之前写入的槽已失效
+    fn slot(
+        &mut self,
+        address: [u8; 20],
+        slot: [u8; 32],
+        epoch: u64,
+        end: u64,
+    ) -> Result<Option<[u8; 32]>, RunnerError> {
+        Ok(self
+            .find(STORAGE, &slot_key(address, slot), end)?
+            .filter(|(offset, _)| *offset >= epoch)
+            .map(|(_, value)| word(&value)))
+    }
+
+    /// Every address and storage slot with a record before `end`. Deleted
+    /// accounts and stale slots are included and read back as missing or zero.
+    fn keys(&mut self, end: u64) -> Result<StateKeys, RunnerError> {
+        if !self.writer.buffer().is_empty() {
+            self.flush()?;
+        }
+        let end = end.min(self.len);
+        let path = &self.path;
+        let mut reader = BufReader::new(&self.reader);
+        reader
+            .seek(SeekFrom::Start(MAGIC.len() as u64))
+            .map_err(|e| file_error(path, e))?;
+        let mut keys = StateKeys::new();
+        let mut offset = MAGIC.len() as u64;
+        while offset < end {
+            let mut len = [0u8; 4];
+            reader.read_exact(&mut len).map_err(|e| file_error(path, e))?;
+            let mut body = vec![0u8; u32::from_le_bytes(len) as usize];
+            reader.read_exact(&mut body).map_err(|e| file_error(path, e))?;
+            let key = &body[BODY_HEADER_LEN..];
+            match body[0] {
+                ACCOUNT => {
+                    keys.entry(key[..20].try_into().unwrap()).or_default();
+                }
+                STORAGE => keys
+                    .entry(key[..20].try_into().unwrap())
+                    .or_default()
+                    .push(word(&key[20..52])),
+                _ => {}
+            }
+            offset += 4 + body.len() as u64;
+        }
+        for slots in keys.values_mut() {
+            slots.sort();
+            slots.dedup();
+        }
+        Ok(keys)
+    }
+
+    fn store_remote_account(
+        &mut self,
+        address: [u8; 20],
+        info: &Option<AccountInfo>,
+    ) -> Result<(), RunnerError> {
+        let account = match info {
+            Some(info) => {
+                if let Some(code) = &info.code {
+                    if info.code_hash != KECCAK_EMPTY
+                        && self.find(CODE, &info.code_hash, u64::MAX)?.is_none()
+                    {
+                        self.append(CODE, &info.code_hash, code)?;
+                    }
+                }
+                StoredAccount {
+                    exists: true,
+                    balance: info.balance,
+                    nonce: info.nonce,
+                    code_hash: info.code_hash,
+                    epoch: 0,
+                }
+            }
+            None => StoredAccount::missing(0),
+        };
+        self.append(ACCOUNT, &address, &account.encode())?;
+        self.end_write()
+    }
+
+    fn commit(&mut self, state: &EvmState) -> Result<(), RunnerError> {
+        let addresses: BTreeSet<_> = state
+            .loaded
+            .iter()
+            .chain(state.accounts.keys())
+            .copied()
+            .collect();
+        for address in addresses {
+            let stored = self.account(address, u64::MAX)?;
+            let Some(account) = state.accounts.get(&address) else {
+                if stored.is_none_or(|stored| stored.exists) {
+                    let epoch = self.len;
+                    self.append(ACCOUNT, &address, &StoredAccount::missing(epoch).encode())?;
+                }
+                continue;
+            };
+
+            if account.has_code() && self.find(CODE, &account.code_hash, u64::MAX)?.is_none() {
+                if let Some(code) = state.codes.get(&account.code_hash) {
+                    self.append(CODE, &account.code_hash, code)?;
+                }
+            }
+            // 不是从数据库读取的账户 存储被整体替换 旧的槽全部失效
+            let replaced = !state.db_accounts.contains(&address);
+            let epoch = match replaced {
+                true => self.len,
+                false => stored.map_or(0, |stored| stored.epoch),
+            };
+            let updated = StoredAccount {
+                exists: true,
+                balance: account.balance,
+                nonce: account.nonce,
+                code_hash: account.code_hash,
+                epoch,
+            };
+            if stored != Some(updated) {
+                self.append(ACCOUNT, &address, &updated.encode())?;
+            }
+
+            for (slot, value) in &account.storage {
+                let changed = match replaced {
+                    true => *value != [0u8; 32],
+                    false => match self.slot(address, *slot, epoch, u64::MAX)? {
+                        Some(stored) => stored != *value,
+                        // 远程数据库中可能有非0的原值
+                        None => *value != [0u8; 32] || (epoch == 0 && self.remote.is_some()),
+                    },
+                };
+                if changed {
+                    self.append(STORAGE, &slot_key(address, *slot), value)?;
+                }
+            }
+        }
+        self.end_write()?;
+        self.flush()?;
+        self.writer
+            .get_ref()
+            .sync_data()
+            .map_err(|e| file_error(&self.path, e))
+    }
+}
+
+//读取整个日志重建索引 返回最后一个COMMIT之后的偏移
+fn scan(file: &File, file_len: u64, index: &mut HashMap<u64, u64>) -> std::io::Result<u64> {
+    let mut reader = BufReader::new(file);
+    let mut magic = [0u8; 8];
+    if !read_full(&mut reader, &mut magic)? || &magic != MAGIC {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "not a state file",
+        ));
+    }
+    let mut offset = MAGIC.len() as u64;
+    let mut committed = offset;
+    let mut pending = Vec::new();
+    loop {
+        let mut len = [0u8; 4];
+        if !read_full(&mut reader, &mut len)? {
+            break;
+        }
+        let len = u32::from_le_bytes(len) as u64;
+        if len < BODY_HEADER_LEN as u64 || offset + 4 + len > file_len {
+            break;
+        }
+        let mut body = vec![0u8; len as usize];
+        if !read_full(&mut reader, &mut body)? {
+            break;
+        }
+        match body[0] {
+            COMMIT => {
+                index.extend(pending.drain(..));
+                committed = offset + 4 + len;
+            }
+            tag => {
+                let Some(key_len) =
+                    key_len(tag).filter(|key_len| BODY_HEADER_LEN + key_len <= body.len())
+                else {
+                    break;
+                };
+                let key = &body[BODY_HEADER_LEN..BODY_HEADER_LEN + key_len];
+                pending.push((index_key(tag, key), offset));
+            }
+        }
+        offset += 4 + len;
+    }
+    Ok(committed)
+}
+
+//读满buf 文件在此之前结束时返回false
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn key_len(tag: u8) -> Option<usize> {
+    match tag {
+        ACCOUNT => Some(20),
+        CODE => Some(32),
+        STORAGE => Some(52),
+        BLOCK_HASH => Some(8),
+        _ => None,
+    }
+}
+
+//FNV-1a(tag || key) 结果与平台和Rust版本无关
+fn index_key(tag: u8, key: &[u8]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+    std::iter::once(&tag)
+        .chain(key)
+        .fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+fn slot_key(address: [u8; 20], slot: [u8; 32]) -> [u8; 52] {
+    let mut key = [0u8; 52];
+    key[..20].copy_from_slice(&address);
+    key[20..].copy_from_slice(&slot);
+    key
+}
+
+fn word(bytes: &[u8]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word.copy_from_slice(bytes);
+    word
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn file_error(path: &Path, error: impl std::fmt::Display) -> RunnerError {
+    RunnerError::DatabaseError(format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::storage::AccountState;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const TOKEN: [u8; 20] = [0x70; 20];
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("minirevm-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    fn state(balance: u8, slot_value: u8) -> EvmState {
+        let mut state = EvmState::default();
+        state.replace_account(
+            ALICE,
+            AccountState {
+                nonce: 1,
+                balance: value(balance),
+                ..Default::default()
+            },
+        );
+        let code = vec![0x60, 0x00, 0x54, 0x00];
+        let mut token = AccountState {
+            code_hash: keccak256(&code),
+            ..Default::default()
+        };
+        token.storage.insert(value(1), value(slot_value));
+        state.codes.insert(token.code_hash, code);
+        state.replace_account(TOKEN, token);
+        state
+    }
+
+    fn file_len(path: &Path) -> u64 {
+        std::fs::metadata(path).unwrap().len()
+    }
+
+    #[test]
+    fn index_key_is_fnv1a() {
+        // FNV-1a 64位 "a"
+        assert_eq!(index_key(b'a', &[]), 0xaf63dc4c8601ec8c);
+        assert_eq!(index_key(ACCOUNT, &ALICE), index_key(ACCOUNT, &ALICE));
+        assert_ne!(index_key(ACCOUNT, &ALICE), index_key(CODE, &ALICE));
+    }
+
+    #[test]
+    fn commit_and_reopen() {
+        let path = temp_path("reopen");
+        FileDb::open(&path).unwrap().commit(&state(5, 7)).unwrap();
+
+        let mut db = FileDb::open(&path).unwrap();
+        let info = db.basic(ALICE).unwrap().unwrap();
+        assert_eq!((info.nonce, info.balance), (1, value(5)));
+        let info = db.basic(TOKEN).unwrap().unwrap();
+        assert_eq!(db.code_by_hash(info.code_hash).unwrap(), vec![0x60, 0x00, 0x54, 0x00]);
+        assert_eq!(db.storage(TOKEN, value(1)).unwrap(), value(7));
+        assert_eq!(db.storage(TOKEN, value(2)).unwrap(), [0u8; 32]);
+        assert_eq!(db.basic([0xeeu8; 20]).unwrap(), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn torn_write_is_dropped() {
+        let path = temp_path("torn");
+        let db = FileDb::open(&path).unwrap();
+        db.commit(&state(5, 7)).unwrap();
+        let first = file_len(&path);
+        db.commit(&state(6, 8)).unwrap();
+        let second = file_len(&path);
+        drop(db);
+
+        // 第二次提交写到一半时崩溃
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(second - 3).unwrap();
+        drop(file);
+
+        let mut db = FileDb::open(&path).unwrap();
+        assert_eq!(file_len(&path), first);
+        assert_eq!(db.basic(ALICE).unwrap().unwrap().balance, value(5));
+        assert_eq!(db.storage(TOKEN, value(1)).unwrap(), value(7));
+
+        // 打开后继续写入不受影响
+        db.commit(&state(9, 9)).unwrap();
+        drop(db);
+        let mut db = FileDb::open(&path).unwrap();
+        assert_eq!(db.basic(ALICE).unwrap().unwrap().balance, value(9));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn uncommitted_records_are_dropped() {
+        let path = temp_path("uncommitted");
+        FileDb::open(&path).unwrap().commit(&state(5, 7)).unwrap();
+        let committed = file_len(&path);
+
+        // 一条完整但没有COMMIT的账户记录 再加上不完整的长度
+        let mut store = Store::open(path.clone(), None).unwrap();
+        let account = StoredAccount {
+            exists: true,
+            balance: value(99),
+            nonce: 3,
+            code_hash: KECCAK_EMPTY,
+            epoch: 0,
+        };
+        store.append(ACCOUNT, &ALICE, &account.encode()).unwrap();
+        store.flush().unwrap();
+        drop(store);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0x20, 0x00]).unwrap();
+        drop(file);
+
+        let mut db = FileDb::open(&path).unwrap();
+        assert_eq!(file_len(&path), committed);
+        assert_eq!(db.basic(ALICE).unwrap().unwrap().balance, value(5));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn snapshots_keep_old_values() {
+        let path = temp_path("snapshot");
+        let db = FileDb::open(&path).unwrap();
+        db.commit(&state(5, 7)).unwrap();
+        let position = db.position();
+        let mut snapshot = db.snapshot();
+        db.commit(&state(6, 8)).unwrap();
+
+        assert_eq!(snapshot.basic(ALICE).unwrap().unwrap().balance, value(5));
+        assert_eq!(snapshot.storage(TOKEN, value(1)).unwrap(), value(7));
+        assert!(snapshot.commit(&state(1, 1)).is_err());
+
+        // 位置在重新打开后仍然有效
+        drop((db, snapshot));
+        let db = FileDb::open(&path).unwrap();
+        let mut old = db.at(position).unwrap();
+        assert_eq!(old.basic(ALICE).unwrap().unwrap().balance, value(5));
+        assert_eq!(db.clone().basic(ALICE).unwrap().unwrap().balance, value(6));
+        assert!(db.at(1).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn destroyed_storage_reads_zero() {
+        let path = temp_path("destroyed");
+        let db = FileDb::open(&path).unwrap();
+        db.commit(&state(5, 7)).unwrap();
+
+        // 账户被重新创建 旧的槽不再可见
+        let mut state = EvmState::with_database(db.clone());
+        state.load_account(TOKEN).unwrap();
+        state.replace_account(TOKEN, AccountState::default());
+        db.commit(&state).unwrap();
+
+        let mut db = FileDb::open(&path).unwrap();
+        assert_eq!(db.storage(TOKEN, value(1)).unwrap(), [0u8; 32]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn state_root_covers_stored_state() {
+        let path = temp_path("root");
+        let db = FileDb::open(&path).unwrap();
+        db.commit(&state(5, 7)).unwrap();
+        let snapshot = db.snapshot();
+
+        // ALICE被删除 TOKEN的槽1清零
+        let mut changed = EvmState::with_database(db.clone());
+        changed.load_account(ALICE).unwrap();
+        changed.put_account(ALICE, None, true);
+        changed.load_account(TOKEN).unwrap();
+        changed.sstore(TOKEN, value(1), [0u8; 32]).unwrap();
+        let expected = changed.state_root().unwrap();
+        assert_ne!(expected, state(5, 7).cached_state_root());
+        db.commit(&changed).unwrap();
+
+        let mut latest = EvmState::with_database(db.clone());
+        assert_eq!(latest.state_root().unwrap(), expected);
+        let mut old = EvmState::with_database(snapshot);
+        assert_eq!(old.state_root().unwrap(), state(5, 7).cached_state_root());
+
+        // 有远程数据库时无法列出全部状态
+        let remote_path = temp_path("root-remote");
+        let remote = FileDb::with_remote(&remote_path, db).unwrap();
+        let mut forked = EvmState::with_database(remote);
+        assert_eq!(forked.state_root(), Err(RunnerError::IncompleteState));
+        std::fs::remove_file(&remote_path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = temp_path("magic");
+        std::fs::write(&path, b"MREVMDB1").unwrap();
+        assert!(FileDb::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
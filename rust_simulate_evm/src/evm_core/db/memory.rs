@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use ethers::utils::keccak256;
+
+use crate::evm_core::storage::AccountState;
+use crate::evm_core::utils::byte_operate::u64_to_u256_array;
+use crate::evm_core::utils::error::RunnerError;
+
+use super::database::{AccountInfo, Database, StateKeys};
+
+/// A [`Database`] held in hash maps, e.g. for test fixtures. The default
+/// backend of [`EvmState`](crate::EvmState), empty unless filled.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDb {
+    pub accounts: HashMap<[u8; 20], AccountState>,
+    pub codes: HashMap<[u8; 32], Vec<u8>>,
+    pub block_hashes: HashMap<u64, [u8; 32]>,
+}
+
+impl MemoryDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_account(&mut self, address: [u8; 20], account: AccountState) {
+        self.accounts.insert(address, account);
+    }
+
+    /// Set the code of `address`, creating the account if needed.
+    pub fn insert_code(&mut self, address: [u8; 20], code: Vec<u8>) {
+        let code_hash = keccak256(&code);
+        self.codes.insert(code_hash, code);
+        self.accounts.entry(address).or_default().code_hash = code_hash;
+    }
+
+    pub fn insert_storage(&mut self, address: [u8; 20], slot: [u8; 32], value: [u8; 32]) {
+        self.accounts
+            .entry(address)
+            .or_default()
+            .storage
+            .insert(slot, value);
+    }
+}
+
+impl Database for MemoryDb {
+    fn basic(&mut self, address: [u8; 20]) -> Result<Option<AccountInfo>, RunnerError> {
+        Ok(self.accounts.get(&address).map(|account| AccountInfo {
+            balance: account.balance,
+            nonce: account.nonce,
+            code_hash: account.code_hash,
+            code: self.codes.get(&account.code_hash).cloned(),
+        }))
+    }
+
+    fn code_by_hash(&mut self, code_hash: [u8; 32]) -> Result<Vec<u8>, RunnerError> {
+        Ok(self.codes.get(&code_hash).cloned().unwrap_or_default())
+    }
+
+    fn storage(&mut self, address: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError> {
+        Ok(self
+            .accounts
+            .get(&address)
+            .and_then(|account| account.storage.get(&slot))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    //未记录的区块沿用keccak256(number)作为哈希
+    fn block_hash(&mut self, number: u64) -> Result<[u8; 32], RunnerError> {
+        Ok(self
+            .block_hashes
+            .get(&number)
+            .copied()
+            .unwrap_or_else(|| keccak256(u64_to_u256_array(number))))
+    }
+
+    fn state_keys(&mut self) -> Result<Option<StateKeys>, RunnerError> {
+        Ok(Some(
+            self.accounts
+                .iter()
+                .map(|(address, account)| (*address, account.storage.keys().copied().collect()))
+                .collect(),
+        ))
+    }
+}
@@ -0,0 +1,11 @@
+pub mod database;
+
+pub mod memory;
+
+pub mod provider;
+
+pub mod cache;
+
+pub mod prefetch;
+
+pub mod file;
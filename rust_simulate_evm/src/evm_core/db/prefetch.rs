@@ -0,0 +1,60 @@
+/*
+字节码静态分析 预测执行时会读取的存储槽和账户 供Database::prefetch提前并发获取
+只识别常量: PUSH后紧跟SLOAD的槽 以及PUSH20推入的地址
+mapping和数组的槽由keccak计算得到 无法预测
+*/
+use crate::evm_core::utils::byte_operate::pad_left;
+
+const AND: u8 = 0x16;
+const SLOAD: u8 = 0x54;
+const PUSH0: u8 = 0x5f;
+const PUSH1: u8 = 0x60;
+const PUSH20: u8 = 0x73;
+const PUSH32: u8 = 0x7f;
+
+/// Storage slots `code` reads with a constant key, i.e. `PUSHn key SLOAD`.
+pub fn predict_storage_keys(code: &[u8]) -> Vec<[u8; 32]> {
+    let mut slots: Vec<_> = pushes(code)
+        .filter(|(_, _, next)| *next == Some(SLOAD))
+        .map(|(_, data, _)| pad_left(data))
+        .collect();
+    slots.sort_unstable();
+    slots.dedup();
+    slots
+}
+
+/// Addresses `code` pushes with `PUSH20`, leaving out masks used with `AND`.
+pub fn predict_accounts(code: &[u8]) -> Vec<[u8; 20]> {
+    let mut accounts: Vec<[u8; 20]> = pushes(code)
+        .filter(|(opcode, _, next)| *opcode == PUSH20 && *next != Some(AND))
+        .filter_map(|(_, data, _)| data.try_into().ok())
+        .filter(|address: &[u8; 20]| address.iter().any(|byte| *byte != 0xff))
+        .collect();
+    accounts.sort_unstable();
+    accounts.dedup();
+    accounts
+}
+
+//(PUSH操作码, 推入的数据, 下一个操作码) 跳过被截断的PUSH
+fn pushes(code: &[u8]) -> impl Iterator<Item = (u8, &[u8], Option<u8>)> {
+    let mut pc = 0;
+    std::iter::from_fn(move || {
+        while pc < code.len() {
+            let opcode = code[pc];
+            let size = match opcode {
+                PUSH1..=PUSH32 => (opcode - PUSH1 + 1) as usize,
+                _ => 0,
+            };
+            let start = pc + 1;
+            pc = start + size;
+            if opcode != PUSH0 && size == 0 {
+                continue;
+            }
+            if pc > code.len() {
+                return None;
+            }
+            return Some((opcode, &code[start..pc], code.get(pc).copied()));
+        }
+        None
+    })
+}
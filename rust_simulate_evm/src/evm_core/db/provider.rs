@@ -0,0 +1,244 @@
+/*
+基于JSON-RPC节点的数据库 即lazy fork
+账户(余额 nonce 代码)在首次访问时获取 存储槽在首次读取时获取 结果由EvmState缓存
+所有请求固定在同一区块 未指定时为最新区块
+请求在进程共享的tokio运行时上执行 预取的账户和存储槽并发获取 暂存到被读取为止
+*/
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{mpsc, Arc, OnceLock};
+
+use ethers::prelude::{Http, Middleware, Provider, ProviderError};
+use ethers::types::{Address, BlockId, BlockNumber, H256};
+use ethers::utils::keccak256;
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::evm_core::storage::KECCAK_EMPTY;
+use crate::evm_core::utils::error::RunnerError;
+
+use super::database::{AccountInfo, Database};
+
+//预取时同时进行的请求数上限 避免触发节点限流
+const MAX_CONCURRENT_REQUESTS: usize = 32;
+
+/// A [`Database`] reading the state of a node over JSON-RPC.
+#[derive(Debug, Clone)]
+pub struct ProviderDb {
+    pub provider: Provider<Http>,
+    /// Block every request is made at, `None` for the latest block.
+    pub block_number: Option<u64>,
+    //预取结果 读取一次后移除
+    prefetched_accounts: HashMap<[u8; 20], Option<AccountInfo>>,
+    prefetched_storage: HashMap<([u8; 20], [u8; 32]), [u8; 32]>,
+}
+
+impl ProviderDb {
+    /// Read the latest state. Later blocks may be seen as the node advances,
+    /// use [`ProviderDb::pinned`] for a consistent view.
+    pub fn new(url: &str) -> Result<Self, RunnerError> {
+        let provider = Provider::<Http>::try_from(url)
+            .map_err(|e| RunnerError::DatabaseError(e.to_string()))?;
+        Ok(Self {
+            provider,
+            block_number: None,
+            prefetched_accounts: HashMap::new(),
+            prefetched_storage: HashMap::new(),
+        })
+    }
+
+    /// Read the state as of `block_number`, or of the node's current block
+    /// when `None`, which is looked up once here.
+    pub fn pinned(url: &str, block_number: Option<u64>) -> Result<Self, RunnerError> {
+        let mut db = Self::new(url)?;
+        let block_number = match block_number {
+            Some(block_number) => block_number,
+            None => {
+                let provider = db.provider.clone();
+                block_on(async move { provider.get_block_number().await })
+                    .map_err(provider_error)?
+                    .as_u64()
+            }
+        };
+        db.block_number = Some(block_number);
+        Ok(db)
+    }
+
+    /// Chain id reported by the node.
+    pub fn chain_id(&self) -> Result<u64, RunnerError> {
+        let provider = self.provider.clone();
+        let chain_id = block_on(async move { provider.get_chainid().await }).map_err(provider_error)?;
+        Ok(chain_id.as_u64())
+    }
+
+    fn block_id(&self) -> Option<BlockId> {
+        self.block_number
+            .map(|number| BlockId::Number(BlockNumber::Number(number.into())))
+    }
+}
+
+impl Database for ProviderDb {
+    fn basic(&mut self, address: [u8; 20]) -> Result<Option<AccountInfo>, RunnerError> {
+        if let Some(info) = self.prefetched_accounts.remove(&address) {
+            return Ok(info);
+        }
+        block_on(fetch_account(self.provider.clone(), address, self.block_id()))
+            .map_err(provider_error)
+    }
+
+    //代码总是随账户一起返回 不会按哈希单独查询
+    fn code_by_hash(&mut self, _code_hash: [u8; 32]) -> Result<Vec<u8>, RunnerError> {
+        Ok(Vec::new())
+    }
+
+    fn storage(&mut self, address: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError> {
+        if let Some(value) = self.prefetched_storage.remove(&(address, slot)) {
+            return Ok(value);
+        }
+        block_on(fetch_storage(self.provider.clone(), address, slot, self.block_id()))
+            .map_err(provider_error)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<[u8; 32], RunnerError> {
+        let provider = self.provider.clone();
+        let block = BlockId::Number(BlockNumber::Number(number.into()));
+        let block = block_on(async move { provider.get_block(block).await }).map_err(provider_error)?;
+        Ok(block
+            .and_then(|block| block.hash)
+            .map_or([0u8; 32], |hash| hash.to_fixed_bytes()))
+    }
+
+    //所有请求同时发出 总耗时约为一次往返 失败的请求留给之后的读取重试
+    fn prefetch(&mut self, accounts: &[[u8; 20]], storage: &[([u8; 20], [u8; 32])]) {
+        let accounts: Vec<_> = accounts
+            .iter()
+            .filter(|address| !self.prefetched_accounts.contains_key(*address))
+            .copied()
+            .collect();
+        let storage: Vec<_> = storage
+            .iter()
+            .filter(|key| !self.prefetched_storage.contains_key(*key))
+            .copied()
+            .collect();
+        if accounts.is_empty() && storage.is_empty() {
+            return;
+        }
+
+        let (provider, block) = (self.provider.clone(), self.block_id());
+        let (fetched_accounts, fetched_storage) = block_on(async move {
+            let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
+            let mut account_tasks = JoinSet::new();
+            for address in accounts {
+                let (provider, permits) = (provider.clone(), permits.clone());
+                account_tasks.spawn(async move {
+                    let _permit = permits.acquire_many(3).await;
+                    (address, fetch_account(provider, address, block).await)
+                });
+            }
+            let mut storage_tasks = JoinSet::new();
+            for (address, slot) in storage {
+                let (provider, permits) = (provider.clone(), permits.clone());
+                storage_tasks.spawn(async move {
+                    let _permit = permits.acquire().await;
+                    ((address, slot), fetch_storage(provider, address, slot, block).await)
+                });
+            }
+
+            let mut fetched_accounts = Vec::new();
+            while let Some(task) = account_tasks.join_next().await {
+                if let Ok((address, Ok(info))) = task {
+                    fetched_accounts.push((address, info));
+                }
+            }
+            let mut fetched_storage = Vec::new();
+            while let Some(task) = storage_tasks.join_next().await {
+                if let Ok((key, Ok(value))) = task {
+                    fetched_storage.push((key, value));
+                }
+            }
+            (fetched_accounts, fetched_storage)
+        });
+        self.prefetched_accounts.extend(fetched_accounts);
+        self.prefetched_storage.extend(fetched_storage);
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Requests                                  */
+/* -------------------------------------------------------------------------- */
+async fn fetch_account(
+    provider: Provider<Http>,
+    address: [u8; 20],
+    block: Option<BlockId>,
+) -> Result<Option<AccountInfo>, ProviderError> {
+    let address = Address::from(address);
+    let (balance, nonce, code) = tokio::try_join!(
+        provider.get_balance(address, block),
+        provider.get_transaction_count(address, block),
+        provider.get_code(address, block),
+    )?;
+
+    // 节点不区分不存在和空账户 空账户按不存在处理
+    if balance.is_zero() && nonce.is_zero() && code.is_empty() {
+        return Ok(None);
+    }
+    let mut info = AccountInfo {
+        nonce: nonce.as_u64(),
+        code_hash: KECCAK_EMPTY,
+        ..Default::default()
+    };
+    balance.to_big_endian(&mut info.balance);
+    if !code.is_empty() {
+        info.code_hash = keccak256(&code);
+        info.code = Some(code.to_vec());
+    }
+    Ok(Some(info))
+}
+
+async fn fetch_storage(
+    provider: Provider<Http>,
+    address: [u8; 20],
+    slot: [u8; 32],
+    block: Option<BlockId>,
+) -> Result<[u8; 32], ProviderError> {
+    let value = provider
+        .get_storage_at(Address::from(address), H256::from(slot), block)
+        .await?;
+    Ok(value.to_fixed_bytes())
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                   Runtime                                  */
+/* -------------------------------------------------------------------------- */
+//所有ProviderDb共用的运行时 首次请求时创建
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .thread_name("provider-db")
+            .enable_all()
+            .build()
+            .expect("Could not create a Runtime")
+    })
+}
+
+//同步等待future 已处于tokio上下文中时不能嵌套block_on 改为交给共享运行时执行并等待结果
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    if Handle::try_current().is_err() {
+        return runtime().block_on(future);
+    }
+    let (sender, receiver) = mpsc::channel();
+    runtime().spawn(async move {
+        let _ = sender.send(future.await);
+    });
+    receiver.recv().expect("provider request task panicked")
+}
+
+fn provider_error(error: ProviderError) -> RunnerError {
+    RunnerError::DatabaseError(error.to_string())
+}
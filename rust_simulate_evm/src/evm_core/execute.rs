@@ -5,15 +5,16 @@ use ethers::types::U256;
 use super::memory::Memory;
 use super::opcodes;
 use super::stack::Stack;
-use super::storage::{AccountState, EvmState};
+use super::storage::{delegated_address, EvmState};
 use super::utils;
-use crate::evm_core::utils::enviroment::{increment_nonce, init_account};
+use crate::evm_core::utils::enviroment::{get_balance, increment_nonce, init_account};
 use super::utils::error::RunnerError;
 
 // Colored output
 use colored::*;
 // use crate::evm_core::context::account_state_ex_context::AccountStateEx;
 use crate::evm_core::context::evm_context::EvmContext;
+use crate::evm_core::context::spec::SpecId;
 use crate::evm_core::utils::assembly::get_op_code;
 
 use crate::debug;
@@ -37,7 +38,8 @@ pub struct Execute {
     //data
     pub stack:Stack,
     pub memory: Memory,
-    pub returndata: Memory,
+    pub returndata: Memory, //上一次子调用返回的数据
+    pub output: Vec<u8>,    //当前帧RETURN的数据
     pub calldata: Memory,
     pub state: EvmState,
 
@@ -85,11 +87,12 @@ impl Execute {
             stack: Stack::new(),
             memory: Memory::new(None),
             returndata: Memory::new(None),
+            output: Vec::new(),
             calldata: Memory::new(calldata),
             state: if state.is_some() {
                 state.unwrap()
             } else {
-                EvmState::new(None)
+                EvmState::default()
             },
             // EVM env
             evm_context: evm_context,
@@ -111,6 +114,14 @@ impl Execute {
         self.pc = pc;
     }
 
+    //当前执行的硬分叉规则 未指定时默认Cancun
+    pub fn spec_id(&self) -> SpecId {
+        self.evm_context
+            .as_ref()
+            .map(|evm_context| evm_context.spec_id)
+            .unwrap_or_default()
+    }
+
     //上下文切换
     pub fn call(
         &mut self,
@@ -120,71 +131,187 @@ impl Execute {
         _gas: u64,
         delegate: bool,
     ) -> Result<(), RunnerError> {
-        let mut error: Option<RunnerError> = None;
+        let caller = self.address;
+        let transfers_value = !delegate && value != [0u8; 32];
+        let code = self.load_code(to)?;
+
+        // 帧开始时设置检查点 失败时回滚帧内的全部修改 包括转账
+        let checkpoint = self.state.snapshot();
+        // 进入新帧时先转账 余额不足则调用失败 不执行被调用者
+        if transfers_value {
+            if let Err(err) = self.state.transfer(caller, to, value) {
+                self.state.revert_to(checkpoint);
+                self.returndata = Memory::new(None);
+                return Err(err);
+            }
+        }
 
-        // Store the initial runner state
-        let initial_caller = self.caller.clone();
-        let initial_callvalue = self.callvalue.clone();
-        let initial_address = self.address.clone();
+        if !delegate {
+            self.state.touch(to);
+        }
+        let result = self.execute_frame(code, to, value, calldata, !delegate);
+        self.returndata = Memory::new(Some(Self::frame_return_data(&result)));
 
-        let initial_calldata = self.calldata.clone();
-        let initial_returndata = self.returndata.clone();
+        match result {
+            Ok(_) => {
+                self.state.discard_snapshot(checkpoint);
+                Ok(())
+            }
+            Err(err) => {
+                self.state.revert_to(checkpoint);
+                Err(err)
+            }
+        }
+    }
 
-        let initial_memory = self.memory.clone();
-        let initial_stack = self.stack.clone();
-        let initial_pc = self.pc.clone();
-        let initial_bytecode = self.bytecode.clone();
+    /// Run `init_code` as a new contract at `address` and deploy the code it returns.
+    ///
+    /// The endowment moves before the init code runs, so the constructor sees
+    /// its own balance. On success the return-data buffer is left empty.
+    pub fn create(
+        &mut self,
+        address: [u8; 20],
+        value: [u8; 32],
+        init_code: Vec<u8>,
+    ) -> Result<(), RunnerError> {
+        let creator = self.address;
 
-        // 状态更新和环境设置
-        if !delegate {
-            self.caller = self.address.clone();
-            self.callvalue = value;
-            self.address = to;
+        // 余额不足时创建失败 不消耗nonce
+        let balance = U256::from_big_endian(&get_balance(creator, self)?);
+        if balance < U256::from_big_endian(&value) {
+            self.returndata = Memory::new(None);
+            return Err(RunnerError::InsufficientBalance);
         }
+        init_account(creator, self)?;
+        increment_nonce(creator, self)?;
 
-        self.call_depth += 1;
-        self.calldata = Memory::new(Some(calldata));
-        self.returndata = Memory::new(None);
+        self.create_frame(address, value, init_code)
+    }
 
-        self.memory = Memory::new(None);
-        self.stack = Stack::new();
-        self.pc = 0;
+    /// Create frame after the creator's nonce was bumped.
+    ///
+    /// Everything the frame changes is rolled back if it fails; the nonce
+    /// bump is not.
+    pub(crate) fn create_frame(
+        &mut self,
+        address: [u8; 20],
+        value: [u8; 32],
+        init_code: Vec<u8>,
+    ) -> Result<(), RunnerError> {
+        let checkpoint = self.state.snapshot();
+        let result = self.deploy(address, value, init_code);
+        match result {
+            Ok(_) => self.state.discard_snapshot(checkpoint),
+            Err(_) => self.state.revert_to(checkpoint),
+        };
+        result
+    }
 
-        // 重新加载和执行字节码
-        let code = self.state.get_code_at(to);
-        if code.is_some() {
-            let interpret_result = self.interpret(code.unwrap().to_owned(), false);
-            if interpret_result.is_err() {
-                error = Some(interpret_result.unwrap_err());
+    fn deploy(
+        &mut self,
+        address: [u8; 20],
+        value: [u8; 32],
+        init_code: Vec<u8>,
+    ) -> Result<(), RunnerError> {
+        let creator = self.address;
+
+        // 目标地址已有代码或nonce时视为地址冲突
+        self.state.load_account(address)?;
+        if let Some(account) = self.state.accounts.get(&address) {
+            if account.nonce != 0 || self.state.get_code_at(address).is_some() {
+                self.returndata = Memory::new(None);
+                return Err(RunnerError::CreateCollision);
             }
         }
-        let return_data = self.returndata.heap.clone();
 
-        if !delegate {
-            // call状态恢复
-            self.caller = initial_caller;
-            self.callvalue = initial_callvalue;
-            self.address = initial_address;
+        init_account(address, self)?;
+        self.state.mark_created(address);
+        // EIP-161: 新合约账户的nonce从1开始
+        if self.spec_id().is_enabled_in(SpecId::SpuriousDragon) {
+            increment_nonce(address, self)?;
         }
+        if value != [0u8; 32] {
+            self.state.transfer(creator, address, value)?;
+        }
+
+        let result = self.execute_frame(Some(init_code), address, value, Vec::new(), true);
+        let return_data = Self::frame_return_data(&result);
+        match result {
+            Ok(code) => {
+                if !code.is_empty() {
+                    self.state.put_code_at(address, code)?;
+                }
+                self.returndata = Memory::new(None);
+                Ok(())
+            }
+            Err(err) => {
+                self.returndata = Memory::new(Some(return_data));
+                Err(err)
+            }
+        }
+    }
+
+    /// Run `code` in a fresh frame and restore the current frame afterwards.
+    ///
+    /// `switch_context` moves caller, callvalue and address to the new frame
+    /// (CALL, CREATE); DELEGATECALL keeps them. Returns the frame's RETURN output.
+    fn execute_frame(
+        &mut self,
+        code: Option<Vec<u8>>,
+        address: [u8; 20],
+        value: [u8; 32],
+        calldata: Vec<u8>,
+        switch_context: bool,
+    ) -> Result<Vec<u8>, RunnerError> {
+        // Store the initial runner state
+        let initial_caller = self.caller;
+        let initial_callvalue = self.callvalue;
+        let initial_address = self.address;
+
+        let initial_calldata = std::mem::replace(&mut self.calldata, Memory::new(Some(calldata)));
+        let initial_returndata = std::mem::replace(&mut self.returndata, Memory::new(None));
+        let initial_output = std::mem::take(&mut self.output);
+
+        let initial_memory = std::mem::replace(&mut self.memory, Memory::new(None));
+        let initial_stack = std::mem::replace(&mut self.stack, Stack::new());
+        let initial_pc = self.pc;
+        let initial_bytecode = std::mem::take(&mut self.bytecode);
+
+        // 状态更新和环境设置
+        if switch_context {
+            self.caller = self.address;
+            self.callvalue = value;
+            self.address = address;
+        }
+        self.call_depth += 1;
+        self.pc = 0;
+
+        // 没有代码的账户直接成功返回
+        let result = match code {
+            Some(code) if !code.is_empty() => self
+                .interpret(code, false)
+                .map(|_| std::mem::take(&mut self.output)),
+            _ => Ok(Vec::new()),
+        };
+
         //执行完毕后会恢复调用前的状态
+        self.caller = initial_caller;
+        self.callvalue = initial_callvalue;
+        self.address = initial_address;
+
         self.calldata = initial_calldata;
         self.returndata = initial_returndata;
+        self.output = initial_output;
 
         self.memory = initial_memory;
         self.stack = initial_stack;
         self.pc = initial_pc;
         self.bytecode = initial_bytecode;
         self.call_depth -= 1;
-        self.returndata.heap = return_data;
-
-        increment_nonce(self.address, self)?;
-
-        if let Some(err) = error {
-            return Err(err);
-        }
 
-        Ok(())
+        result
     }
+
     //callcode 已废弃
     pub fn _call_inner(
         &mut self,
@@ -194,85 +321,74 @@ impl Execute {
         _gas: u64,
         is_callcode: bool
     ) -> Result<(), RunnerError> {
-        let mut error: Option<RunnerError> = None;
-
-        // Store the initial runner state
-        let initial_caller = self.caller.clone();
-        let initial_callvalue = self.callvalue.clone();
-        let initial_address = self.address.clone();
-        let initial_calldata = self.calldata.clone();
-        let initial_returndata = self.returndata.clone();
-        let initial_memory = self.memory.clone();
-        let initial_stack = self.stack.clone();
-        let initial_pc = self.pc.clone();
-        let initial_bytecode = self.bytecode.clone();
-
-        // 状态更新和环境设置
-        if !is_callcode {
-            // CALL操作，更新caller和address
-            self.caller = self.address.clone();
-            self.callvalue = value;
-            self.address = to;
-        } else {
-            // CALLCODE操作，不更改caller和address，只更改callvalue
-            self.callvalue = value;
+        // CALLCODE的value转给自己 但仍要求余额充足
+        let balance = U256::from_big_endian(&get_balance(self.address, self)?);
+        if balance < U256::from_big_endian(&value) {
+            self.returndata = Memory::new(None);
+            return Err(RunnerError::InsufficientBalance);
         }
 
-        self.call_depth += 1;
-        self.calldata = Memory::new(Some(calldata));
-        self.returndata = Memory::new(None);
-
-        self.memory = Memory::new(None);
-        self.stack = Stack::new();
-        self.pc = 0;
-
-        // 重新加载和执行字节码
-        let code_address = if is_callcode {
+        // 加载被调用者的字节码 在对应的存储上下文中执行
+        let context_address = if is_callcode {
             // CALLCODE，使用caller的存储
             self.address
         } else {
             // CALL操作，使用被调用者to的存储
             to
         };
+        let code = self.load_code(to)?;
 
-        let code = self.state.get_code_at(code_address);
-
-        if let Some(code) = code {
-            let interpret_result = self.interpret(code.to_owned(), false);
-            if interpret_result.is_err() {
-                error = Some(interpret_result.unwrap_err());
-            }
-        }
-
-        // 获取返回数据
-        let return_data = self.returndata.heap.clone();
-
-        // 恢复调用前的环境状态
-        if !is_callcode {
-            self.caller = initial_caller;
+        let checkpoint = self.state.snapshot();
+        let result = if is_callcode {
+            // CALLCODE操作，不更改caller和address，只更改callvalue
+            let initial_callvalue = self.callvalue;
+            self.callvalue = value;
+            let result = self.execute_frame(code, context_address, value, calldata, false);
             self.callvalue = initial_callvalue;
-            self.address = initial_address;
-        }
-
-        self.calldata = initial_calldata;
-        self.returndata = initial_returndata;
-        self.memory = initial_memory;
-        self.stack = initial_stack;
-        self.pc = initial_pc;
-        self.bytecode = initial_bytecode;
-        self.call_depth -= 1;
-
-        // 将返回数据写回初始状态
-        self.returndata.heap = return_data;
+            result
+        } else {
+            // CALL操作，更新caller和address
+            self.execute_frame(code, context_address, value, calldata, true)
+        };
+        match result {
+            Ok(_) => self.state.discard_snapshot(checkpoint),
+            Err(_) => self.state.revert_to(checkpoint),
+        };
 
-        // 增加调用者的nonce
-        increment_nonce(self.address, self)?;
+        // 将返回数据写回调用者的returndata缓冲区
+        self.returndata = Memory::new(Some(Self::frame_return_data(&result)));
+        result.map(|_| ())
+    }
 
-        if let Some(err) = error {
-            return Err(err);
+    /// Code run when `to` is called.
+    ///
+    /// From Prague an EIP-7702 designator is followed to its target's code.
+    /// Only one hop is taken: a designator pointing at another designator
+    /// runs the second designator, which halts on the 0xef byte.
+    fn load_code(&mut self, to: [u8; 20]) -> Result<Option<Vec<u8>>, RunnerError> {
+        self.state.load_account(to)?;
+        let Some(code) = self.state.get_code_at(to) else {
+            return Ok(None);
+        };
+        match delegated_address(code) {
+            Some(target) if self.spec_id().is_enabled_in(SpecId::Prague) => {
+                self.state.load_account(target)?;
+                Ok(self.state.get_code_at(target).cloned())
+            }
+            _ => Ok(Some(code.clone())),
         }
+    }
 
-        Ok(())
+    /// Return data a finished frame hands to its caller.
+    ///
+    /// RETURN output on success, the revert payload on REVERT and nothing
+    /// on an exceptional halt.
+    fn frame_return_data(result: &Result<Vec<u8>, RunnerError>) -> Vec<u8> {
+        match result {
+            Ok(output) => output.clone(),
+            Err(RunnerError::Revert(data)) => data.clone(),
+            Err(_) => Vec::new(),
+        }
     }
 
     /*==============解析器==================*/
@@ -289,6 +405,15 @@ impl Execute {
             if put_code_result.is_err() {
                 return Err(put_code_result.unwrap_err());
             }
+
+            // 顶层调用同样在执行前转账 余额不足时不执行
+            if self.callvalue != [0u8; 32] {
+                let transfer_result = self.state.transfer(self.caller, self.address, self.callvalue);
+                if let Err(err) = transfer_result {
+                    self.state.finalize_transaction(false, self.spec_id());
+                    return Err(err);
+                }
+            }
         }
 
         /* -------------------------------------------------------------------------- */
@@ -330,6 +455,10 @@ impl Execute {
         /*                            Print execution error                           */
         /* -------------------------------------------------------------------------- */
 
+        if initial_interpretation {
+            self.state.finalize_transaction(error.is_none(), self.spec_id());
+        }
+
         if error.is_some() {
             println!(
                 "{} {}\n  {}: 0x{:X}\n  {}: 0x{:X}\n  {}\n op_count: {}",
@@ -353,9 +482,145 @@ impl Execute {
     /* 轮询执行每个opcode */
     pub fn interpret_op_code(&mut self, opcode: u8) -> Result<(), RunnerError> {
         match opcode {
-           _ => println!("Hello my cycle reference"),
+            /* ---------------------------- Execution OpCodes --------------------------- */
+            0x00 => opcodes::jump_flow::stop(self),
+
+            /* ------------------------- Math operations OpCodes ------------------------ */
+            0x01 => opcodes::mathematical::add(self),
+            0x02 => opcodes::mathematical::mul(self),
+            0x03 => opcodes::mathematical::sub(self),
+            0x04 => opcodes::mathematical::div(self),
+            0x05 => opcodes::mathematical::sdiv(self),
+            0x06 => opcodes::mathematical::modulo(self),
+            0x07 => opcodes::mathematical::smod(self),
+            0x08 => opcodes::mathematical::addmod(self),
+            0x09 => opcodes::mathematical::mulmod(self),
+            0x0a => opcodes::mathematical::exp(self),
+            0x0b => opcodes::mathematical::signextend(self),
+
+            /* --------------------------- Comparison OpCodes --------------------------- */
+            0x10 => opcodes::mathematical::lt(self),
+            0x11 => opcodes::mathematical::gt(self),
+            0x12 => opcodes::mathematical::slt(self),
+            0x13 => opcodes::mathematical::sgt(self),
+            0x14 => opcodes::mathematical::eq(self),
+            0x15 => opcodes::mathematical::iszero(self),
+
+            /* ----------------------- Bitwise Operations OpCodes ----------------------- */
+            0x16 => opcodes::mathematical::and(self),
+            0x17 => opcodes::mathematical::or(self),
+            0x18 => opcodes::mathematical::xor(self),
+            0x19 => opcodes::mathematical::not(self),
+            0x1a => opcodes::mathematical::byte(self),
+            0x1b => opcodes::mathematical::shl(self),
+            0x1c => opcodes::mathematical::shr(self),
+            0x1d => opcodes::mathematical::sar(self),
+            0x20 => opcodes::mathematical::keccak(self),
+
+            /* ---------------------------- Environment OpCodes ------------------------- */
+            0x30 => opcodes::enviroment::address(self),
+            0x31 => opcodes::enviroment::balance(self),
+            0x32 => opcodes::enviroment::origin(self),
+            0x33 => opcodes::enviroment::caller(self),
+            0x34 => opcodes::enviroment::callvalue(self),
+            0x35 => opcodes::enviroment::calldataload(self),
+            0x36 => opcodes::enviroment::calldatasize(self),
+            0x37 => opcodes::enviroment::calldatacopy(self),
+            0x38 => opcodes::enviroment::codesize(self),
+            0x39 => opcodes::enviroment::codecopy(self),
+            0x3a => opcodes::enviroment::gasprice(self),
+            0x3b => opcodes::enviroment::extcodesize(self),
+            0x3c => opcodes::enviroment::extcodecopy(self),
+            0x3d => opcodes::enviroment::returndatasize(self),
+            0x3e => opcodes::enviroment::returndatacopy(self),
+            0x3f => opcodes::enviroment::extcodehash(self),
+            0x40 => opcodes::enviroment::blockhash(self),
+            0x41 => opcodes::enviroment::coinbase(self),
+            0x42 => opcodes::enviroment::timestamp(self),
+            0x43 => opcodes::enviroment::number(self),
+            0x44 => opcodes::enviroment::difficulty(self),
+            0x45 => opcodes::enviroment::gaslimit(self),
+            0x46 => opcodes::enviroment::chainid(self),
+            0x47 => opcodes::enviroment::selfbalance(self),
+            0x48 => opcodes::enviroment::basefee(self),
+            0x49 => opcodes::enviroment::blobhash(self),
+            0x4a => opcodes::enviroment::blobbasefee(self),
+
+            /* ------------------------------ Stack OpCodes ----------------------------- */
+            0x50 => opcodes::stack::pop::pop(self),
+            0x5f..=0x7f => opcodes::stack::push::push(self, (opcode - 0x5f) as usize),
+            0x80 => opcodes::stack::dup::dup1(self),
+            0x81 => opcodes::stack::dup::dup2(self),
+            0x82 => opcodes::stack::dup::dup3(self),
+            0x83 => opcodes::stack::dup::dup4(self),
+            0x84 => opcodes::stack::dup::dup5(self),
+            0x85 => opcodes::stack::dup::dup6(self),
+            0x86 => opcodes::stack::dup::dup7(self),
+            0x87 => opcodes::stack::dup::dup8(self),
+            0x88 => opcodes::stack::dup::dup9(self),
+            0x89 => opcodes::stack::dup::dup10(self),
+            0x8a => opcodes::stack::dup::dup11(self),
+            0x8b => opcodes::stack::dup::dup12(self),
+            0x8c => opcodes::stack::dup::dup13(self),
+            0x8d => opcodes::stack::dup::dup14(self),
+            0x8e => opcodes::stack::dup::dup15(self),
+            0x8f => opcodes::stack::dup::dup16(self),
+            0x90 => opcodes::stack::swap::swap1(self),
+            0x91 => opcodes::stack::swap::swap2(self),
+            0x92 => opcodes::stack::swap::swap3(self),
+            0x93 => opcodes::stack::swap::swap4(self),
+            0x94 => opcodes::stack::swap::swap5(self),
+            0x95 => opcodes::stack::swap::swap6(self),
+            0x96 => opcodes::stack::swap::swap7(self),
+            0x97 => opcodes::stack::swap::swap8(self),
+            0x98 => opcodes::stack::swap::swap9(self),
+            0x99 => opcodes::stack::swap::swap10(self),
+            0x9a => opcodes::stack::swap::swap11(self),
+            0x9b => opcodes::stack::swap::swap12(self),
+            0x9c => opcodes::stack::swap::swap13(self),
+            0x9d => opcodes::stack::swap::swap14(self),
+            0x9e => opcodes::stack::swap::swap15(self),
+            0x9f => opcodes::stack::swap::swap16(self),
+
+            /* ----------------------------- Memory OpCodes ----------------------------- */
+            0x51 => opcodes::memory::mload(self),
+            0x52 => opcodes::memory::mstore(self),
+            0x53 => opcodes::memory::mstore8(self),
+            0x59 => opcodes::memory::msize(self),
+            0x5e => opcodes::memory::mcopy(self),
+
+            /* ----------------------------- Storage OpCodes ---------------------------- */
+            0x54 => opcodes::storage::sload(self),
+            0x55 => opcodes::storage::sstore(self),
+
+            /* ------------------------------ Flow OpCodes ------------------------------ */
+            0x56 => opcodes::jump_flow::jump(self),
+            0x57 => opcodes::jump_flow::jumpi(self),
+            0x58 => opcodes::jump_flow::pc(self),
+            0x5a => opcodes::jump_flow::gas(self),
+            0x5b => opcodes::jump_flow::jumpdest(self),
+            0xfd => opcodes::jump_flow::revert(self),
+
+            /* ------------------------------- Log OpCodes ------------------------------ */
+            0xa0 => opcodes::log::log0(self),
+            0xa1 => opcodes::log::log1(self),
+            0xa2 => opcodes::log::log2(self),
+            0xa3 => opcodes::log::log3(self),
+            0xa4 => opcodes::log::log4(self),
+
+            /* ----------------------------- System OpCodes ----------------------------- */
+            0xf0 => opcodes::system::create(self),
+            0xf1 => opcodes::system::call(self, false),
+            0xf2 => opcodes::system::callcode(self, false),
+            0xf3 => opcodes::system::return_(self),
+            0xf4 => opcodes::system::delegatecall(self),
+            0xf5 => opcodes::system::create2(self),
+            0xfa => opcodes::system::staticcall(self),
+            0xff => opcodes::system::selfdestruct(self),
+
+            // INVALID (0xfe) and unassigned op codes
+            _ => opcodes::system::invalid(self),
         }
-        Ok(())
     }
 
     /*==============调试器==================*/
@@ -425,4 +690,145 @@ impl Execute {
     fn debug_storage(&mut self) {
         self.state.debug_state();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evm_core::context::evm_context::EvmContext;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::{AccountState, EvmState};
+    use crate::evm_core::transaction::transact::{transact, TransactionResult};
+    use crate::evm_core::transaction::types::{Transaction, TxLegacy};
+    use crate::evm_core::utils::error::RunnerError;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const CONTRACT: [u8; 20] = [0xcc; 20];
+    const RETURNER: [u8; 20] = [0x0a; 20];
+    const REVERTER: [u8; 20] = [0x0b; 20];
+    const NO_CODE: [u8; 20] = [0x0c; 20];
+
+    // 0x2a写入内存后RETURN 32字节
+    const RETURN_WORD: [u8; 10] = [0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+    // 写存储后REVERT 32字节 0x2b
+    const REVERT_WORD: [u8; 15] = [
+        0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x2b, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xfd,
+    ];
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    // CALL(gas, to, 0, 0, 0, ret_offset, ret_size) 结果留在栈顶
+    fn call(to: [u8; 20], ret_offset: u8, ret_size: u8) -> Vec<u8> {
+        let mut code = vec![0x60, ret_size, 0x60, ret_offset, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+        code.extend_from_slice(&to);
+        code.extend_from_slice(&[0x62, 0xff, 0xff, 0xff, 0xf1]);
+        code
+    }
+
+    // 栈顶写入存储槽
+    fn store(slot: u8) -> [u8; 3] {
+        [0x60, slot, 0x55]
+    }
+
+    fn run(code: Vec<u8>) -> (EvmState, TransactionResult) {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: value(100),
+                ..Default::default()
+            },
+        );
+        for (address, code) in [
+            (CONTRACT, code),
+            (RETURNER, RETURN_WORD.to_vec()),
+            (REVERTER, REVERT_WORD.to_vec()),
+        ] {
+            db.insert_account(address, AccountState::default());
+            db.insert_code(address, code);
+        }
+        let mut state = EvmState::with_database(db);
+
+        let tx = Transaction::Legacy(TxLegacy {
+            from: ALICE,
+            gas_limit: 1_000_000,
+            to: Some(CONTRACT),
+            ..Default::default()
+        });
+        let result = transact(&mut state, &EvmContext::default(), tx).unwrap();
+        (state, result)
+    }
+
+    #[test]
+    fn call_to_no_code_account_clears_return_data() {
+        let mut code = call(RETURNER, 0, 0);
+        code.extend(store(2));
+        // RETURNDATASIZE
+        code.push(0x3d);
+        code.extend(store(1));
+        code.extend(call(NO_CODE, 0, 0));
+        code.push(0x3d);
+        code.extend(store(0));
+
+        let (mut state, result) = run(code);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(state.sload(CONTRACT, value(2)).unwrap(), value(1));
+        assert_eq!(state.sload(CONTRACT, value(1)).unwrap(), value(32));
+        assert_eq!(state.sload(CONTRACT, value(0)).unwrap(), value(0));
+    }
+
+    #[test]
+    fn return_data_survives_child_revert() {
+        let mut code = call(REVERTER, 0, 0);
+        code.extend(store(2));
+        code.push(0x3d);
+        code.extend(store(1));
+        // RETURNDATACOPY(0, 0, 32) MLOAD(0)
+        code.extend_from_slice(&[0x60, 0x20, 0x60, 0x00, 0x60, 0x00, 0x3e, 0x60, 0x00, 0x51]);
+        code.extend(store(0));
+
+        let (mut state, result) = run(code);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(state.sload(CONTRACT, value(2)).unwrap(), value(0));
+        assert_eq!(state.sload(CONTRACT, value(1)).unwrap(), value(32));
+        assert_eq!(state.sload(CONTRACT, value(0)).unwrap(), value(0x2b));
+        // 子调用的存储修改已回滚
+        assert_eq!(state.sload(REVERTER, value(0)).unwrap(), value(0));
+    }
+
+    #[test]
+    fn call_output_keeps_memory_past_return_data() {
+        // MSTORE8(64, 0x77) 之后以ret_size 64调用 只写入返回的32字节
+        let mut code = vec![0x60, 0x77, 0x60, 0x40, 0x53];
+        code.extend(call(RETURNER, 32, 64));
+        code.extend(store(1));
+        // MLOAD(33) 取返回数据的末尾和原有的0x77
+        code.extend_from_slice(&[0x60, 0x21, 0x51]);
+        code.extend(store(0));
+
+        let (mut state, result) = run(code);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(state.sload(CONTRACT, value(1)).unwrap(), value(1));
+        let mut expected = value(0x77);
+        expected[30] = 0x2a;
+        assert_eq!(state.sload(CONTRACT, value(0)).unwrap(), expected);
+    }
+
+    #[test]
+    fn returndatacopy_out_of_bounds_halts() {
+        let mut code = call(RETURNER, 0, 0);
+        // RETURNDATACOPY(0, 0, 33) 比返回数据多一个字节
+        code.extend_from_slice(&[0x60, 0x21, 0x60, 0x00, 0x60, 0x00, 0x3e]);
+        code.extend_from_slice(&[0x60, 0x01]);
+        code.extend(store(0));
+
+        let (mut state, result) = run(code);
+        assert!(!result.success);
+        assert_eq!(result.error, Some(RunnerError::ReturnDataOutOfBounds));
+        assert_eq!(result.gas_used, 1_000_000);
+        assert_eq!(state.sload(CONTRACT, value(0)).unwrap(), value(0));
+    }
+}
@@ -0,0 +1,133 @@
+/*
+EIP-4844 blob gas
+blob base fee 由区块头的 excess_blob_gas 通过 fake_exponential 计算
+Prague (EIP-7691) 提高了目标值和上限 并调整了更新系数
+*/
+use ethers::types::U256;
+
+use crate::evm_core::context::spec::SpecId;
+
+use super::constant::{
+    BLOB_BASE_FEE_UPDATE_FRACTION_CANCUN, BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE,
+    MAX_BLOB_GAS_PER_BLOCK_CANCUN, MAX_BLOB_GAS_PER_BLOCK_PRAGUE, MIN_BLOB_GASPRICE,
+    TARGET_BLOB_GAS_PER_BLOCK_CANCUN, TARGET_BLOB_GAS_PER_BLOCK_PRAGUE,
+};
+
+/// Approximates `factor * e ** (numerator / denominator)` with a Taylor expansion.
+///
+/// Saturates at `u128::MAX`, a price no balance can pay.
+pub fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> u128 {
+    let factor = U256::from(factor);
+    let numerator = U256::from(numerator);
+    let denominator = U256::from(denominator);
+
+    let mut i = U256::one();
+    let mut output = U256::zero();
+    let mut numerator_accum = factor * denominator;
+    while !numerator_accum.is_zero() {
+        // 溢出时当前项已超过 2^192 结果必然超过u128
+        let Some(sum) = output.checked_add(numerator_accum) else {
+            return u128::MAX;
+        };
+        output = sum;
+        let Some(product) = numerator_accum.checked_mul(numerator) else {
+            return u128::MAX;
+        };
+        numerator_accum = product / (denominator * i);
+        i += U256::one();
+    }
+    let output = output / denominator;
+    if output > U256::from(u128::MAX) {
+        u128::MAX
+    } else {
+        output.as_u128()
+    }
+}
+
+/// Price per unit of blob gas for a block with `excess_blob_gas`.
+pub fn blob_base_fee(excess_blob_gas: u64, spec_id: SpecId) -> u128 {
+    let fraction = if spec_id.is_enabled_in(SpecId::Prague) {
+        BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE
+    } else {
+        BLOB_BASE_FEE_UPDATE_FRACTION_CANCUN
+    };
+    fake_exponential(MIN_BLOB_GASPRICE as u64, excess_blob_gas, fraction)
+}
+
+pub fn max_blob_gas_per_block(spec_id: SpecId) -> u64 {
+    if spec_id.is_enabled_in(SpecId::Prague) {
+        MAX_BLOB_GAS_PER_BLOCK_PRAGUE
+    } else {
+        MAX_BLOB_GAS_PER_BLOCK_CANCUN
+    }
+}
+
+/// `excess_blob_gas` of the child of a block with the given blob totals.
+pub fn calc_excess_blob_gas(
+    parent_excess_blob_gas: u64,
+    parent_blob_gas_used: u64,
+    spec_id: SpecId,
+) -> u64 {
+    let target = if spec_id.is_enabled_in(SpecId::Prague) {
+        TARGET_BLOB_GAS_PER_BLOCK_PRAGUE
+    } else {
+        TARGET_BLOB_GAS_PER_BLOCK_CANCUN
+    };
+    parent_excess_blob_gas
+        .saturating_add(parent_blob_gas_used)
+        .saturating_sub(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fake_exponential_vectors() {
+        // go-ethereum eip4844 测试中的向量
+        let vectors = [
+            (1, 0, 1, 1),
+            (38493, 0, 1000, 38493),
+            (0, 1234, 2345, 0),
+            (1, 2, 1, 6),
+            (1, 4, 2, 6),
+            (1, 3, 1, 16),
+            (1, 6, 2, 18),
+            (1, 4, 1, 49),
+            (1, 8, 2, 50),
+            (10, 8, 2, 542),
+            (11, 8, 2, 596),
+            (1, 5, 1, 136),
+            (1, 5, 2, 11),
+            (2, 5, 2, 23),
+            (1, 50000000, 2225652, 5709098764),
+        ];
+        for (factor, numerator, denominator, expected) in vectors {
+            assert_eq!(fake_exponential(factor, numerator, denominator), expected);
+        }
+    }
+
+    #[test]
+    fn fake_exponential_saturates() {
+        assert_eq!(fake_exponential(u64::MAX, u64::MAX, 1), u128::MAX);
+        assert_eq!(fake_exponential(1, u64::MAX, 3338477), u128::MAX);
+    }
+
+    #[test]
+    fn blob_base_fee_by_fork() {
+        assert_eq!(blob_base_fee(0, SpecId::Cancun), 1);
+        assert_eq!(blob_base_fee(10_000_000, SpecId::Cancun), 19);
+        assert_eq!(blob_base_fee(10_000_000, SpecId::Prague), 7);
+        assert_eq!(max_blob_gas_per_block(SpecId::Cancun), 786432);
+        assert_eq!(max_blob_gas_per_block(SpecId::Prague), 1179648);
+    }
+
+    #[test]
+    fn excess_blob_gas() {
+        // 低于目标值时归零 高于目标值时累积差额
+        assert_eq!(calc_excess_blob_gas(0, 131072, SpecId::Cancun), 0);
+        assert_eq!(calc_excess_blob_gas(100, 786432, SpecId::Cancun), 393316);
+        assert_eq!(calc_excess_blob_gas(0, 1179648, SpecId::Prague), 393216);
+        assert_eq!(calc_excess_blob_gas(u64::MAX, u64::MAX, SpecId::Cancun), u64::MAX - 393216);
+    }
+}
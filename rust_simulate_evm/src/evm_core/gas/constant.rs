@@ -13,3 +13,27 @@ pub const VERYLOW_LOG4: u64 = 1875;
 pub const VERYLOW_SLOAD: u64 = 100;
 pub const VERYLOW_SSTORE: u64 = 100;
 
+/* Transaction */
+pub const TX_BASE: u64 = 21000;
+pub const TX_CREATE: u64 = 32000;
+pub const TX_DATA_ZERO: u64 = 4;
+pub const TX_DATA_NON_ZERO: u64 = 16;
+pub const TX_DATA_NON_ZERO_FRONTIER: u64 = 68;
+pub const ACCESS_LIST_ADDRESS: u64 = 2400;
+pub const ACCESS_LIST_STORAGE_KEY: u64 = 1900;
+pub const INITCODE_WORD: u64 = 2;
+
+/* Blob (EIP-4844) */
+pub const GAS_PER_BLOB: u64 = 131072;
+pub const MIN_BLOB_GASPRICE: u128 = 1;
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+pub const TARGET_BLOB_GAS_PER_BLOCK_CANCUN: u64 = 393216;
+pub const MAX_BLOB_GAS_PER_BLOCK_CANCUN: u64 = 786432;
+pub const BLOB_BASE_FEE_UPDATE_FRACTION_CANCUN: u64 = 3338477;
+pub const TARGET_BLOB_GAS_PER_BLOCK_PRAGUE: u64 = 786432;
+pub const MAX_BLOB_GAS_PER_BLOCK_PRAGUE: u64 = 1179648;
+pub const BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE: u64 = 5007716;
+
+/* Set code (EIP-7702) */
+pub const PER_EMPTY_ACCOUNT_COST: u64 = 25000;
+pub const PER_AUTH_BASE_COST: u64 = 12500;
@@ -1 +1,3 @@
-pub mod constant;
\ No newline at end of file
+pub mod constant;
+
+pub mod blob;
\ No newline at end of file
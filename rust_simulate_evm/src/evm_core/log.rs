@@ -6,19 +6,51 @@ topic是事件签名的哈希值，data是由indexed修饰的事件参数
 use std::fmt;
 use colored::Colorize;
 
+use serde::{Deserialize, Serialize};
+
 use super::utils::debug;
+use super::utils::serde_hex;
 
 /* -------------------------------------------------------------------------- */
 /*                                 Log struct                                 */
 /* -------------------------------------------------------------------------- */
 /// Represents a log entry in the Ethereum Virtual Machine (EVM) state.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Log {
     /// The address of the contract that generated the log.
+    #[serde(with = "serde_hex::bytes")]
     pub address: [u8; 20],
     /// The topics associated with the log.
+    #[serde(with = "serde_hex::vec")]
     pub topics: Vec<[u8; 32]>,
     /// The data associated with the log.
+    #[serde(with = "serde_hex::bytes")]
     pub data: Vec<u8>,
+    /// The block the log was emitted in.
+    pub block_number: u64,
+    /// The hash of the emitting transaction, zero when unknown.
+    #[serde(with = "serde_hex::bytes")]
+    pub transaction_hash: [u8; 32],
+    /// The position of the emitting transaction in its block.
+    pub transaction_index: u64,
+    /// The position of the log in its block.
+    pub log_index: u64,
+}
+
+impl Log {
+    //区块和交易信息在交易执行后填充
+    pub fn new(address: [u8; 20], topics: Vec<[u8; 32]>, data: Vec<u8>) -> Self {
+        Self {
+            address,
+            topics,
+            data,
+            block_number: 0,
+            transaction_hash: [0u8; 32],
+            transaction_index: 0,
+            log_index: 0,
+        }
+    }
 }
 
 impl fmt::Debug for Log {
@@ -14,4 +14,15 @@ pub mod utils;
 
 pub mod opcodes;
 
-pub mod gas;
\ No newline at end of file
+pub mod gas;
+
+pub mod transaction;
+
+pub mod block;
+
+pub mod trie;
+pub mod chain;
+
+pub mod db;
+
+pub mod state;
@@ -1,10 +1,11 @@
+use crate::evm_core::context::evm_context::EvmContext;
+use crate::evm_core::context::spec::SpecId;
 use crate::evm_core::execute::Execute;
 use crate::evm_core::utils::byte_operate::{bytes32_to_address, pad_left};
-use crate::evm_core::utils::enviroment::get_balance;
+use crate::evm_core::utils::enviroment::{get_balance, get_code_hash};
 use crate::evm_core::utils::error::RunnerError;
 
 use ethers::types::U256;
-use ethers::utils::keccak256;
 
 /* -------------------------------------------------------------------------- */
 /*                              Get env info from EVM                         */
@@ -111,18 +112,10 @@ pub fn calldatacopy(execute: &mut Execute) -> Result<(), RunnerError> {
 }
 /*     code operate      */
 //return current contract codesize
+//当前执行的代码 创建合约时为init code
 pub fn codesize(execute: &mut Execute) -> Result<(), RunnerError> {
-    let code = execute.state.get_code_at(execute.address);
-    let codesize = if code.is_none() {
-        [0u8; 32]
-    } else {
-        pad_left(&code.unwrap().len().to_be_bytes())
-    };
-
-    let result = execute.stack.push(codesize);
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    let codesize = pad_left(&execute.bytecode.len().to_be_bytes());
+    execute.stack.push(codesize)?;
 
     execute.increase_pc(1)
 }
@@ -132,27 +125,23 @@ pub fn codecopy(execute: &mut Execute) -> Result<(), RunnerError> {
     let offset = U256::from_big_endian(&execute.stack.pop()?).as_usize();
     let size = U256::from_big_endian(&execute.stack.pop()?).as_usize();
 
-    let code = execute.state.get_code_at(execute.address);
-
-    let code = if code.is_none() {
-        vec![]
-    } else {
-        // Slice the code to the correct size
-        let code = code.unwrap();
-        let mut code_vec = code.to_vec();
-        code_vec.resize(offset + size, 0);
-        let code = code_vec.as_slice();
-        code[offset..offset + size].to_vec()
-    };
+    // Slice the code to the correct size 超出部分补0
+    let mut code = vec![0u8; size];
+    if offset < execute.bytecode.len() {
+        let end = execute.bytecode.len().min(offset + size);
+        code[..end - offset].copy_from_slice(&execute.bytecode[offset..end]);
+    }
     execute.memory.write(dest_offset, code)?;
 
     execute.increase_pc(1)
 }
 
 //return special contract codesize
+//EXTCODE* 不跟随EIP-7702委托 看到的是委托标识 0xef0100 || address
 pub fn extcodesize(execute: &mut Execute) -> Result<(), RunnerError> {
-    let address = execute.stack.pop()?;
-    let code = execute.state.get_code_at(bytes32_to_address(&address));
+    let address = bytes32_to_address(&execute.stack.pop()?);
+    execute.state.load_account(address)?;
+    let code = execute.state.get_code_at(address);
     let codesize = if code.is_none() {
         [0u8; 32]
     } else {
@@ -173,17 +162,19 @@ pub fn extcodecopy(execute: &mut Execute) -> Result<(), RunnerError> {
     let offset = U256::from_big_endian(&execute.stack.pop()?).as_usize();
     let size = U256::from_big_endian(&execute.stack.pop()?).as_usize();
 
-    let code = execute.state.get_code_at(bytes32_to_address(&address));
-    let code = if code.is_none() {
-        vec![]
-    } else {
-        // Slice the code to the correct size
-        let code = code.unwrap();
-        let mut code_vec = code.to_vec();
-        code_vec.resize(32, 0);
-        let code = code_vec.as_slice();
-        code[offset..offset + size].to_vec()
-    };
+    // 越界部分补0 EIP-7702委托账户复制的是23字节的委托标识本身
+    let address = bytes32_to_address(&address);
+    execute.state.load_account(address)?;
+    let source = execute
+        .state
+        .get_code_at(address)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut code = vec![0u8; size];
+    if offset < source.len() {
+        let end = source.len().min(offset + size);
+        code[..end - offset].copy_from_slice(&source[offset..end]);
+    }
     execute.memory.write(dest_offset, code)?;
 
     execute.increase_pc(1)
@@ -206,7 +197,14 @@ pub fn returndatacopy(execute: &mut Execute) -> Result<(), RunnerError> {
     let dest_offset = U256::from_big_endian(&execute.stack.pop()?).as_usize();
     let _offset = U256::from_big_endian(&execute.stack.pop()?).as_usize();
     let _size = U256::from_big_endian(&execute.stack.pop()?).as_usize();
-    let returndata = execute.returndata.read(_offset, _size)?;
+    //越界读取returndata是异常终止而不是补0
+    let end = _offset
+        .checked_add(_size)
+        .ok_or(RunnerError::ReturnDataOutOfBounds)?;
+    if end > execute.returndata.heap.len() {
+        return Err(RunnerError::ReturnDataOutOfBounds);
+    }
+    let returndata = execute.returndata.heap[_offset..end].to_vec();
     let result = execute.memory.write(dest_offset, returndata);
 
     if result.is_err() {
@@ -216,28 +214,55 @@ pub fn returndatacopy(execute: &mut Execute) -> Result<(), RunnerError> {
     execute.increase_pc(1)
 }
 
+//tx.gasprice 交易的实际gas价格
+pub fn gasprice(execute: &mut Execute) -> Result<(), RunnerError> {
+    let gas_price = match &execute.evm_context {
+        None => pad_left(&[0x0a]),
+        Some(evm_context) => evm_context.gas_price.unwrap_or(pad_left(&[0x0a])),
+    };
+
+    execute.stack.push(gas_price)?;
+
+    execute.increase_pc(1)
+}
+
 /*     环境变量      */
 pub fn extcodehash(execute: &mut Execute) -> Result<(), RunnerError> {
     let address = execute.stack.pop()?;
+    let codehash = get_code_hash(bytes32_to_address(&address), execute)?;
+    execute.stack.push(codehash)?;
 
-    Ok(
-        if let Some(code) = execute.state.get_code_at(bytes32_to_address(&address)) {
-            let codehash = keccak256(code);
-            let result = execute.stack.push(codehash);
-            if result.is_err() {
-                return Err(result.unwrap_err());
-            }
-
-            execute.increase_pc(1);
-        },
-    )
+    execute.increase_pc(1)
 }
 
+//只能查询最近256个区块 当前和未来的区块返回0 没有历史记录时向数据库查询
 pub fn blockhash(execute: &mut Execute) -> Result<(), RunnerError> {
     let block: U256 = U256::from_big_endian(&execute.stack.pop()?);
-    let mut bytes = [0; 32];
-    block.to_big_endian(&mut bytes);
-    let blockhash = keccak256(bytes);
+
+    let current = execute
+        .evm_context
+        .as_ref()
+        .and_then(|evm_context| evm_context.block_number)
+        .map(|number| U256::from_big_endian(&number));
+    let in_range = match current {
+        Some(current) => block < current && current - block <= U256::from(256),
+        None => true,
+    };
+    // 超出u64的区块号不存在 不截断
+    let blockhash = if !in_range || block > U256::from(u64::MAX) {
+        [0u8; 32]
+    } else {
+        let number = block.as_u64();
+        let history = execute
+            .evm_context
+            .as_ref()
+            .map(|evm_context| &evm_context.block_hashes)
+            .filter(|block_hashes| !block_hashes.is_empty());
+        match history {
+            Some(block_hashes) => block_hashes.get(&number).copied().unwrap_or([0u8; 32]),
+            None => execute.state.block_hash(number)?,
+        }
+    };
 
     let result = execute.stack.push(blockhash);
     if result.is_err() {
@@ -308,14 +333,17 @@ pub fn number(execute: &mut Execute) -> Result<(), RunnerError> {
     execute.increase_pc(1)
 }
 
+//merge之后0x44为PREVRANDAO
 pub fn difficulty(execute: &mut Execute) -> Result<(), RunnerError> {
-    //硬编码
-    let difficulty = pad_left(&[0x45; 8]);
-    let result = execute.stack.push(difficulty);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    let default = pad_left(&[0x45; 8]);
+    let difficulty = match &execute.evm_context {
+        None => default,
+        Some(evm_context) => match evm_context.prevrandao {
+            Some(prevrandao) if evm_context.spec_id.is_enabled_in(SpecId::Merge) => prevrandao.0,
+            _ => evm_context.difficulty.unwrap_or(default),
+        },
+    };
+    execute.stack.push(difficulty)?;
 
     execute.increase_pc(1)
 }
@@ -342,7 +370,10 @@ pub fn gaslimit(execute: &mut Execute) -> Result<(), RunnerError> {
 }
 
 pub fn chainid(execute: &mut Execute) -> Result<(), RunnerError> {
-    let chainid = pad_left(&[0x01]);
+    let chainid = match &execute.evm_context {
+        None => pad_left(&[0x01]),
+        Some(evm_context) => evm_context.chain_id.unwrap_or(pad_left(&[0x01])),
+    };
     let result = execute.stack.push(chainid);
 
     if result.is_err() {
@@ -383,4 +414,102 @@ pub fn basefee(execute: &mut Execute) -> Result<(), RunnerError> {
     }
 
     execute.increase_pc(1)
-}
\ No newline at end of file
+}
+//EIP-4844 当前交易第index个blob的版本化哈希 越界返回0
+pub fn blobhash(execute: &mut Execute) -> Result<(), RunnerError> {
+    let index = U256::from_big_endian(&execute.stack.pop()?);
+    let blobhash = match &execute.evm_context {
+        Some(evm_context) if index < U256::from(evm_context.blob_hashes.len()) => {
+            evm_context.blob_hashes[index.as_usize()]
+        }
+        _ => [0u8; 32],
+    };
+    execute.stack.push(blobhash)?;
+
+    execute.increase_pc(1)
+}
+
+//EIP-7516 当前区块的blob base fee
+pub fn blobbasefee(execute: &mut Execute) -> Result<(), RunnerError> {
+    let blob_base_fee = match &execute.evm_context {
+        None => EvmContext::new().blob_base_fee(),
+        Some(evm_context) => evm_context.blob_base_fee(),
+    };
+    execute.stack.push(pad_left(&blob_base_fee.to_be_bytes()))?;
+
+    execute.increase_pc(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evm_core::context::evm_context::EvmContext;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::{AccountState, EvmState, KECCAK_EMPTY};
+    use crate::evm_core::transaction::transact::transact;
+    use crate::evm_core::transaction::types::{Transaction, TxLegacy};
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const CONTRACT: [u8; 20] = [0xcc; 20];
+    const MISSING: [u8; 20] = [0xdd; 20];
+    const CODELESS: [u8; 20] = [0xb0; 20];
+    const EMPTY: [u8; 20] = [0xe0; 20];
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    // PUSH20 target EXTCODEHASH PUSH1 0 SSTORE
+    fn extcodehash(target: [u8; 20]) -> [u8; 32] {
+        let mut code = vec![0x73];
+        code.extend_from_slice(&target);
+        code.extend_from_slice(&[0x3f, 0x60, 0x00, 0x55]);
+
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: value(100),
+                ..Default::default()
+            },
+        );
+        db.insert_account(
+            CODELESS,
+            AccountState {
+                balance: value(1),
+                ..Default::default()
+            },
+        );
+        db.insert_account(EMPTY, AccountState::default());
+        db.insert_account(CONTRACT, AccountState::default());
+        db.insert_code(CONTRACT, code);
+        let mut state = EvmState::with_database(db);
+
+        let tx = Transaction::Legacy(TxLegacy {
+            from: ALICE,
+            gas_limit: 100_000,
+            to: Some(CONTRACT),
+            ..Default::default()
+        });
+        let result = transact(&mut state, &EvmContext::default(), tx).unwrap();
+        assert!(result.success, "{:?}", result.error);
+        state.sload(CONTRACT, [0u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn extcodehash_of_missing_account_is_zero() {
+        assert_eq!(extcodehash(MISSING), [0u8; 32]);
+    }
+
+    #[test]
+    fn extcodehash_of_codeless_account_is_empty_hash() {
+        assert_eq!(extcodehash(CODELESS), KECCAK_EMPTY);
+    }
+
+    #[test]
+    fn extcodehash_of_empty_account_is_zero() {
+        // EIP-1052: EIP-161意义上的空账户与不存在的账户相同
+        assert_eq!(extcodehash(EMPTY), [0u8; 32]);
+    }
+}
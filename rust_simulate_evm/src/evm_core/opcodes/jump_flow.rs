@@ -18,9 +18,8 @@ pub fn revert(execute: &mut Execute) -> Result<(), RunnerError>  {
     //从内存中读取数据
     let offset = U256::from_big_endian(&execute.stack.pop()?);
     let size = U256::from_big_endian(&execute.stack.pop()?);
+    //revert数据通过RunnerError::Revert交给调用者
     let revert_data = execute.memory.read(offset.as_usize(), size.as_usize());
-    //读取到returndata.heap
-    execute.returndata.heap = revert_data.as_ref().unwrap().to_owned();
 
     //回滚状态返回的信息
     let err;
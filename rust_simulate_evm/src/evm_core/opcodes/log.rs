@@ -19,13 +19,9 @@ pub fn log0(execute: &mut Execute) -> Result<(), RunnerError> {
 
     let log_data =  execute.memory.read(offset.as_usize(), size.as_usize())?;
 
-    let log = Log {
-        address: execute.address,
-        topics: vec![],
-        data: log_data.clone(),
-    };
+    let log = Log::new(execute.address, vec![], log_data);
 
-    execute.state.logs.push(log);
+    execute.state.push_log(log);
 
     // Increment PC
     execute.increase_pc(1)
@@ -49,13 +45,9 @@ pub fn log1(execute: &mut Execute) -> Result<(), RunnerError> {
 
     let log_data = execute.memory.read(offset.as_usize(), size.as_usize())?;
 
-    let log = Log {
-        address: execute.address,
-        topics: vec![topic1],
-        data: log_data.clone(),
-    };
+    let log = Log::new(execute.address, vec![topic1], log_data);
 
-    execute.state.logs.push(log);
+    execute.state.push_log(log);
 
     // Increment PC
     execute.increase_pc(1)
@@ -83,13 +75,9 @@ pub fn log2(execute: &mut Execute) -> Result<(), RunnerError> {
 
     let log_data = execute.memory.read(offset.as_usize(), size.as_usize())?;
 
-    let log = Log {
-        address: execute.address,
-        topics: vec![topic1, topic2],
-        data: log_data.clone(),
-    };
+    let log = Log::new(execute.address, vec![topic1, topic2], log_data);
 
-    execute.state.logs.push(log);
+    execute.state.push_log(log);
 
     // Increment PC
     execute.increase_pc(1)
@@ -121,13 +109,9 @@ pub fn log3(execute: &mut Execute) -> Result<(), RunnerError> {
 
     let log_data = execute.memory.read(offset.as_usize(), size.as_usize())? ;
 
-    let log = Log {
-        address: execute.address,
-        topics: vec![topic1, topic2, topic3],
-        data: log_data.clone(),
-    };
+    let log = Log::new(execute.address, vec![topic1, topic2, topic3], log_data);
 
-    execute.state.logs.push(log);
+    execute.state.push_log(log);
 
     // Increment PC
     execute.increase_pc(1)
@@ -163,13 +147,9 @@ pub fn log4(execute: &mut Execute) -> Result<(), RunnerError> {
 
     let log_data = execute.memory.read(offset.as_usize(), size.as_usize())?;
 
-    let log = Log {
-        address: execute.address,
-        topics: vec![topic1, topic2, topic3, topic4],
-        data: log_data.clone(),
-    };
+    let log = Log::new(execute.address, vec![topic1, topic2, topic3, topic4], log_data);
 
-    execute.state.logs.push(log);
+    execute.state.push_log(log);
 
     // Increment PC
     execute.increase_pc(1)
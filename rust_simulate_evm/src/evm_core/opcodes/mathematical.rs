@@ -3,7 +3,147 @@ use crate::evm_core::utils::error::RunnerError;
 use crate::evm_core::execute::Execute;
 
 // Primitive types
-use ethers::types::{I256, U256};
+use ethers::types::{I256, U256, U512};
+use ethers::utils::keccak256;
+
+//U256转换为32字节大端序
+fn to_word(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+//弹出两个操作数 a为栈顶
+fn pop_pair(execute: &mut Execute) -> Result<(U256, U256), RunnerError> {
+    let a = U256::from_big_endian(&execute.stack.pop()?);
+    let b = U256::from_big_endian(&execute.stack.pop()?);
+    Ok((a, b))
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            Arithmetic operation                            */
+/* -------------------------------------------------------------------------- */
+pub fn add(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    execute.stack.push(to_word(a.overflowing_add(b).0))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn mul(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    execute.stack.push(to_word(a.overflowing_mul(b).0))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn sub(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    execute.stack.push(to_word(a.overflowing_sub(b).0))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+//除数为0时结果为0
+pub fn div(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    let result = if b.is_zero() { U256::zero() } else { a / b };
+    execute.stack.push(to_word(result))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn sdiv(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    let a = I256::from_raw(a);
+    let b = I256::from_raw(b);
+    //MIN / -1 溢出后仍为MIN
+    let result = if b.is_zero() { I256::zero() } else { a.overflowing_div(b).0 };
+    execute.stack.push(to_word(result.into_raw()))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn modulo(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    let result = if b.is_zero() { U256::zero() } else { a % b };
+    execute.stack.push(to_word(result))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+//结果的符号与被除数相同
+pub fn smod(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    let a = I256::from_raw(a);
+    let b = I256::from_raw(b);
+    let result = if b.is_zero() { I256::zero() } else { a.overflowing_rem(b).0 };
+    execute.stack.push(to_word(result.into_raw()))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+//中间结果使用512位 避免溢出
+pub fn addmod(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    let n = U256::from_big_endian(&execute.stack.pop()?);
+    let result = if n.is_zero() {
+        U256::zero()
+    } else {
+        let sum = (U512::from(a) + U512::from(b)) % U512::from(n);
+        U256::try_from(sum).unwrap_or_default()
+    };
+    execute.stack.push(to_word(result))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn mulmod(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    let n = U256::from_big_endian(&execute.stack.pop()?);
+    let result = if n.is_zero() {
+        U256::zero()
+    } else {
+        let product = a.full_mul(b) % U512::from(n);
+        U256::try_from(product).unwrap_or_default()
+    };
+    execute.stack.push(to_word(result))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn exp(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (base, exponent) = pop_pair(execute)?;
+    execute.stack.push(to_word(base.overflowing_pow(exponent).0))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+//将b+1字节长度的有符号数扩展到32字节
+pub fn signextend(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (b, x) = pop_pair(execute)?;
+    let result = if b < U256::from(31) {
+        let sign_bit = b.as_usize() * 8 + 7;
+        let mask = (U256::one() << sign_bit) - U256::one();
+        if x.bit(sign_bit) { x | !mask } else { x & mask }
+    } else {
+        x
+    };
+    execute.stack.push(to_word(result))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
 
 /* -------------------------------------------------------------------------- */
 /*                             Logical operation                              */
@@ -117,4 +257,93 @@ pub fn sgt(execute: &mut Execute) -> Result<(), RunnerError> {
     execute.increase_pc(1)
 }
 
+/* -------------------------------------------------------------------------- */
+/*                             Bitwise operation                              */
+/* -------------------------------------------------------------------------- */
+pub fn and(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    execute.stack.push(to_word(a & b))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn or(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    execute.stack.push(to_word(a | b))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn xor(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (a, b) = pop_pair(execute)?;
+    execute.stack.push(to_word(a ^ b))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn not(execute: &mut Execute) -> Result<(), RunnerError> {
+    let a = U256::from_big_endian(&execute.stack.pop()?);
+    execute.stack.push(to_word(!a))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+//取第i个字节 i从最高位开始计数
+pub fn byte(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (i, x) = pop_pair(execute)?;
+    let result = if i < U256::from(32) {
+        pad_left(&[to_word(x)[i.as_usize()]])
+    } else {
+        [0u8; 32]
+    };
+    execute.stack.push(result)?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn shl(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (shift, value) = pop_pair(execute)?;
+    let result = if shift < U256::from(256) { value << shift.as_usize() } else { U256::zero() };
+    execute.stack.push(to_word(result))?;
 
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+pub fn shr(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (shift, value) = pop_pair(execute)?;
+    let result = if shift < U256::from(256) { value >> shift.as_usize() } else { U256::zero() };
+    execute.stack.push(to_word(result))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+//算术右移 保留符号位
+pub fn sar(execute: &mut Execute) -> Result<(), RunnerError> {
+    let (shift, value) = pop_pair(execute)?;
+    let value = I256::from_raw(value);
+    let shift = if shift < U256::from(256) { shift.as_usize() } else { 256 };
+    execute.stack.push(to_word(value.asr(shift).into_raw()))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Hash                                    */
+/* -------------------------------------------------------------------------- */
+pub fn keccak(execute: &mut Execute) -> Result<(), RunnerError> {
+    let offset = U256::from_big_endian(&execute.stack.pop()?);
+    let size = U256::from_big_endian(&execute.stack.pop()?);
+    let data = execute.memory.read(offset.as_usize(), size.as_usize())?;
+    execute.stack.push(keccak256(data))?;
+
+    // Increment PC
+    execute.increase_pc(1)
+}
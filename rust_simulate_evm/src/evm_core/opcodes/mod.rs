@@ -12,3 +12,4 @@ pub mod system;
 
 pub mod mathematical;
 
+pub mod stack;
\ No newline at end of file
@@ -1,20 +1,12 @@
-use lib_utils::error::RunnerError;
-use interpreter_execute::execute::Execute;
-use gas::constant::VERYLOW;
-
-use crate::core
-use crate::core_module::utils::errors::ExecutionError;
-
+use crate::evm_core::utils::error::RunnerError;
+use crate::evm_core::execute::Execute;
+use crate::evm_core::gas::constant::VERYLOW;
 
 pub fn dup1(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(1);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(1)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -24,11 +16,7 @@ pub fn dup2(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(2);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(2)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -38,11 +26,7 @@ pub fn dup3(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(3);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(3)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -52,11 +36,7 @@ pub fn dup4(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(4);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(4)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -66,11 +46,7 @@ pub fn dup5(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(5);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(5)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -80,11 +56,7 @@ pub fn dup6(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(6);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(6)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -94,11 +66,7 @@ pub fn dup7(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(7);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(7)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -108,11 +76,7 @@ pub fn dup8(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(8);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(8)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -122,11 +86,7 @@ pub fn dup9(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(9);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(9)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -136,11 +96,7 @@ pub fn dup10(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(10);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(10)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -150,11 +106,7 @@ pub fn dup11(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(11);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(11)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -164,11 +116,7 @@ pub fn dup12(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(12);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(12)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -178,11 +126,7 @@ pub fn dup13(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(13);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(13)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -192,11 +136,7 @@ pub fn dup14(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(14);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(14)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -206,11 +146,7 @@ pub fn dup15(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(15);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(15)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -220,11 +156,7 @@ pub fn dup16(execute: &mut Execute) ->  Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.dup(16);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.dup(16)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -1,6 +1,6 @@
-use lib_utils::error::RunnerError;
-use interpreter_execute::execute::Execute;
-use gas::constant::VERYLOW_2;
+use crate::evm_core::utils::error::RunnerError;
+use crate::evm_core::execute::Execute;
+use crate::evm_core::gas::constant::VERYLOW_2;
 
 pub fn pop(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW_2 {
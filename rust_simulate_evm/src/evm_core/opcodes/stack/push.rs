@@ -1,6 +1,6 @@
-use lib_utils::error::RunnerError;
-use interpreter_execute::execute::Execute;
-use gas::constant::{VERYLOW, VERYLOW_2};
+use crate::evm_core::utils::error::RunnerError;
+use crate::evm_core::execute::Execute;
+use crate::evm_core::gas::constant::VERYLOW;
 
 
 //数据右对齐 计算偏移量
@@ -22,10 +22,7 @@ pub fn push(execute: &mut Execute, data_len: usize) -> Result<(), RunnerError> {
 
     let data = &execute.bytecode[execute.pc + 1..execute.pc + 1 + data_len];
     let padded = prepare_data(data);
-    let result = execute.stack.push(padded);
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.push(padded)?;
 
     execute.increase_pc(1 + data_len)
 
@@ -1,6 +1,6 @@
-use lib_utils::error::RunnerError;
-use interpreter_execute::execute::Execute;
-use gas::constant::VERYLOW;
+use crate::evm_core::utils::error::RunnerError;
+use crate::evm_core::execute::Execute;
+use crate::evm_core::gas::constant::VERYLOW;
 
 
 pub fn swap1(execute: &mut Execute) -> Result<(), RunnerError> {
@@ -8,11 +8,7 @@ pub fn swap1(execute: &mut Execute) -> Result<(), RunnerError> {
         return Err(RunnerError::OutOfGas)
     }
 
-    let result = execute.stack.swap(1);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(1)?;
     // Increment PC
     execute.increase_pc(1)
 }
@@ -21,11 +17,7 @@ pub fn swap2(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(2);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(2)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -35,11 +27,7 @@ pub fn swap3(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(3);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(3)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -49,11 +37,7 @@ pub fn swap4(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(4);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(4)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -63,11 +47,7 @@ pub fn swap5(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(5);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(5)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -77,11 +57,7 @@ pub fn swap6(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(6);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(6)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -91,11 +67,7 @@ pub fn swap7(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(7);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(7)?;
 
     // Increment PC
     execute.increase_pc(1)
@@ -105,11 +77,7 @@ pub fn swap8(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(8);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(8)?;
     // Increment PC
     execute.increase_pc(1)
 }
@@ -118,11 +86,7 @@ pub fn swap9(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(9);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(9)?;
     // Increment PC
     execute.increase_pc(1)
 }
@@ -131,11 +95,7 @@ pub fn swap10(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(10);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(10)?;
     // Increment PC
     execute.increase_pc(1)
 }
@@ -144,11 +104,7 @@ pub fn swap11(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(11);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(11)?;
     // Increment PC
     execute.increase_pc(1)
 }
@@ -157,11 +113,7 @@ pub fn swap12(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(12);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(12)?;
     // Increment PC
     execute.increase_pc(1)
 }
@@ -170,11 +122,7 @@ pub fn swap13(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(13);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(13)?;
     // Increment PC
     execute.increase_pc(1)
 }
@@ -183,11 +131,7 @@ pub fn swap14(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(14);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(14)?;
     // Increment PC
     execute.increase_pc(1)
 }
@@ -196,11 +140,7 @@ pub fn swap15(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(15);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(15)?;
     // Increment PC
     execute.increase_pc(1)
 }
@@ -209,11 +149,7 @@ pub fn swap16(execute: &mut Execute) -> Result<(), RunnerError> {
     if execute.gas < VERYLOW {
         return Err(RunnerError::OutOfGas)
     }
-    let result = execute.stack.swap(16);
-
-    if result.is_err() {
-        return Err(result.unwrap_err());
-    }
+    execute.stack.swap(16)?;
     // Increment PC
     execute.increase_pc(1)
 }
\ No newline at end of file
@@ -3,11 +3,11 @@ use ethers::utils::keccak256;
 use revm_primitives::Address;
 use crate::evm_core::utils::error::RunnerError;
 use crate::evm_core::execute::Execute;
+use crate::evm_core::context::spec::SpecId;
 
 use super::super::utils::byte_operate::{pad_left, bytes32_to_address, u64_to_u256_array};
 use super::super::utils::enviroment::
 {
-    delete_account,
     get_balance,
     get_nonce,
     init_account,
@@ -18,30 +18,28 @@ pub fn invalid(execute: &mut Execute) -> Result<(), RunnerError> {
 }
 
 pub fn create(execute: &mut Execute) -> Result<(), RunnerError> {
+    if execute.state.static_mode {
+        return Err(RunnerError::StaticCallStateChanged);
+    }
+
     let value = execute.stack.pop()?;
     let offset = U256::from_big_endian(&execute.stack.pop()?);
     let size = U256::from_big_endian(&execute.stack.pop()?);
     //从指定的内存位置读取init_code
     let init_code = execute.memory.read(offset.as_usize(), size.as_usize())?;
 
-    // 使用地址的nonce计算新合约地址
+    // 使用当前合约地址和nonce派生新合约地址
     let nonce = get_nonce(
         execute.address,
         execute,
     )?;
     let nonce = U256::from_big_endian(&nonce).0[0];
-    let caller = &execute.caller;
-    //caller, nonce 用于派生生成合约地址
-    let create_address = Address::from_slice(caller).create(nonce);
-    // 创建账户并存储init_code到init_account
-    init_account(*create_address.0, execute)?;
-    execute
-        .state
-        .put_code_at(*create_address.0, init_code)?;
-    //构造函数
-    let call_result = execute.call(*create_address.0, value, Vec::new(), execute.gas, false);
+    let create_address = Address::from_slice(&execute.address).create(nonce);
 
-    if call_result.is_err() {
+    //执行构造函数并部署返回的代码
+    let create_result = execute.create(*create_address.0, value, init_code);
+
+    if create_result.is_err() {
         execute
             .stack
             .push(pad_left(&[0x00]))?;
@@ -50,19 +48,15 @@ pub fn create(execute: &mut Execute) -> Result<(), RunnerError> {
             .stack
             .push(pad_left(&*create_address.0))?;
     }
-    //存储合约代码
-    let returndata = execute.returndata.heap.clone();
-    execute
-        .state
-        .put_code_at(*create_address.0, returndata.clone())?;
-    execute
-        .state
-        .transfer(execute.caller, *create_address.0, value)?;
 
     execute.increase_pc(1)
 }
 //可以根据salt和init code hash推导contract address
 pub fn create2(execute: &mut Execute) -> Result<(), RunnerError> {
+    if execute.state.static_mode {
+        return Err(RunnerError::StaticCallStateChanged);
+    }
+
     let value = execute.stack.pop()?;
     let offset = U256::from_big_endian(&execute.stack.pop()?);
     let size = U256::from_big_endian(&execute.stack.pop()?);
@@ -70,17 +64,13 @@ pub fn create2(execute: &mut Execute) -> Result<(), RunnerError> {
     let salt = execute.stack.pop()?;
     let init_code = execute.memory.read(offset.as_usize(), size.as_usize())?;
     let init_code_hash = keccak256(init_code.clone());
-    let caller = &execute.caller;
 
-    // caller, init_code_hash, salt => precompute Address
-    let create_address = Address::from_slice(caller).create2(salt, init_code_hash);
-    //初始化合约账户状态
-    init_account(*create_address.0, execute)?;
-    execute.state.put_code_at(*create_address.0, init_code)?;
+    // address, init_code_hash, salt => precompute Address
+    let create_address = Address::from_slice(&execute.address).create2(salt, init_code_hash);
 
-    let call_result = execute.call(*create_address.0, value, Vec::new(), execute.gas, false);
+    let create_result = execute.create(*create_address.0, value, init_code);
 
-    if call_result.is_err() {
+    if create_result.is_err() {
         execute
             .stack
             .push(pad_left(&[0x00]))?;
@@ -90,32 +80,55 @@ pub fn create2(execute: &mut Execute) -> Result<(), RunnerError> {
             .push(pad_left(&(*create_address.0)))?;
     }
 
-    let returndata = execute.returndata.heap.clone();
-    execute
-        .state
-        .put_code_at(*create_address.0, returndata)?;
-    execute
-        .state
-        .transfer(execute.caller, *create_address.0, value)?;
-
     execute.increase_pc(1)
 }
 
 pub fn selfdestruct(execute: &mut Execute) -> Result<(), RunnerError> {
-    let address = execute.stack.pop()?;
+    if execute.state.static_mode {
+        return Err(RunnerError::StaticCallStateChanged);
+    }
+
+    let beneficiary = bytes32_to_address(&execute.stack.pop()?);
     let contract_balance = get_balance(execute.address, execute)?;
 
-    execute.state.transfer(
-        execute.address,
-        bytes32_to_address(&address),
-        contract_balance,
-    )?;
-    delete_account(execute.address, execute)?;
+    // 受益账户不存在时先创建
+    init_account(beneficiary, execute)?;
+    if beneficiary != execute.address {
+        execute
+            .state
+            .transfer(execute.address, beneficiary, contract_balance)?;
+    }
 
-    execute.increase_pc(1)
+    // EIP-6780: Cancun起只删除同一交易中创建的合约，删除推迟到交易结束
+    let address = execute.address;
+    if !execute.spec_id().is_enabled_in(SpecId::Cancun)
+        || execute.state.created_accounts.contains(&address)
+    {
+        execute.state.mark_selfdestruct(address);
+    }
+
+    // 终止当前帧
+    execute.set_pc(execute.bytecode.len());
+
+    Ok(())
 }
 
 
+//子调用返回数据只写入min(retSize, returndatasize)字节 不足部分保持内存原值
+fn write_call_output(
+    execute: &mut Execute,
+    returndata_offset: usize,
+    returndata_size: usize,
+) -> Result<(), RunnerError> {
+    let copy_size = returndata_size.min(execute.returndata.heap.len());
+    if copy_size == 0 {
+        return Ok(());
+    }
+
+    let return_data = execute.returndata.heap[..copy_size].to_vec();
+    execute.memory.write(returndata_offset, return_data)
+}
+
 /*  合约交互   */
 pub fn call(execute: &mut Execute, bypass_static: bool) -> Result<(), RunnerError> {
     if execute.state.static_mode && !bypass_static {
@@ -156,23 +169,8 @@ pub fn call(execute: &mut Execute, bypass_static: bool) -> Result<(), RunnerErro
         execute.stack.push(pad_left(&[0x01]))?;
     }
     //将调用后的返回数据写回内存
-    let mut return_data: Vec<u8> = execute.returndata.heap.clone();
-
-    if return_data.len() < returndata_size.as_usize() {
-        return_data.extend(vec![0; returndata_size.as_usize() - return_data.len()]);
-    }
-
-    return_data = return_data[0..returndata_size.as_usize()].to_vec();
-    execute
-        .memory
-        .write(returndata_offset.as_usize(), return_data)?
-    ;
-    // 对于CALLCODE，value为[0u8; 32]，因为CALLCODE不会转移value
-    if !value.eq(&[0u8; 32]) {
-        execute
-            .state
-            .transfer(execute.address, bytes32_to_address(&to), value)?;
-    }
+    write_call_output(execute, returndata_offset.as_usize(), returndata_size.as_usize())?;
+    // value已在Execute::call进入帧时转移 失败时退回
 
     execute.increase_pc(1)
 }
@@ -216,14 +214,8 @@ pub fn callcode(execute: &mut Execute,
         execute.stack.push(pad_left(&[0x01]))?;
     }
 
-    let mut return_data: Vec<u8> = execute.returndata.heap.clone();
-    if return_data.len() < returndata_size.as_usize() {
-        return_data.extend(vec![0; returndata_size.as_usize() - return_data.len()]);
-    }
-
     // 处理返回数据并写入内存
-    return_data = return_data[0..returndata_size.as_usize()].to_vec();
-    execute.memory.write(returndata_offset.as_usize(), return_data)?;
+    write_call_output(execute, returndata_offset.as_usize(), returndata_size.as_usize())?;
     // callcode不转移value
     execute.increase_pc(1)
 }
@@ -260,16 +252,7 @@ pub fn delegatecall(execute: &mut Execute) -> Result<(), RunnerError> {
         execute.stack.push(pad_left(&[0x01]))?;
     }
 
-    let mut return_data: Vec<u8> = execute.returndata.heap.clone();
-
-    if return_data.len() < returndata_size.as_usize() {
-        return_data.extend(vec![0; returndata_size.as_usize() - return_data.len()]);
-    }
-
-    return_data = return_data[0..returndata_size.as_usize()].to_vec();
-    execute
-        .memory
-        .write(returndata_offset.as_usize(), return_data)?;
+    write_call_output(execute, returndata_offset.as_usize(), returndata_size.as_usize())?;
 
     execute.increase_pc(1)
 }
@@ -287,11 +270,214 @@ pub fn return_(execute: &mut Execute) -> Result<(), RunnerError> {
     let offset = U256::from_big_endian(&execute.stack.pop()?);
     let size = U256::from_big_endian(&execute.stack.pop()?);
 
-    let returndata = unsafe { execute.memory.read(offset.as_usize(), size.as_usize())? };
-    execute.returndata.heap = returndata;
+    let output = execute.memory.read(offset.as_usize(), size.as_usize())?;
+    execute.output = output;
 
     // 当前合约执行完毕
     execute.set_pc(execute.bytecode.len());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::evm_core::context::evm_context::EvmContext;
+    use crate::evm_core::context::spec::SpecId;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::{AccountState, EvmState};
+    use crate::evm_core::transaction::transact::{transact, TransactionResult};
+    use crate::evm_core::transaction::types::{Transaction, TxLegacy};
+    use crate::evm_core::utils::error::RunnerError;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const BOB: [u8; 20] = [0xb0; 20];
+    const CONTRACT: [u8; 20] = [0xcc; 20];
+    const RECEIVER: [u8; 20] = [0xee; 20];
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    // PUSH20 beneficiary SELFDESTRUCT
+    fn selfdestruct_to(beneficiary: [u8; 20]) -> Vec<u8> {
+        let mut code = vec![0x73];
+        code.extend_from_slice(&beneficiary);
+        code.push(0xff);
+        code
+    }
+
+    // ADDRESS SELFDESTRUCT
+    const SELFDESTRUCT_TO_SELF: [u8; 2] = [0x30, 0xff];
+    // SELFBALANCE PUSH1 0 SSTORE 记录进入时看到的余额
+    const STORE_SELFBALANCE: [u8; 4] = [0x47, 0x60, 0x00, 0x55];
+
+    // CALL(gas, RECEIVER, val, 0, 0, 0, 0) PUSH1 0 SSTORE
+    fn call_receiver(val: u8) -> Vec<u8> {
+        let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, val, 0x73];
+        code.extend_from_slice(&RECEIVER);
+        code.extend_from_slice(&[0x62, 0xff, 0xff, 0xff, 0xf1, 0x60, 0x00, 0x55]);
+        code
+    }
+
+    fn state(code: Vec<u8>) -> EvmState {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: value(100),
+                ..Default::default()
+            },
+        );
+        db.insert_account(
+            CONTRACT,
+            AccountState {
+                nonce: 1,
+                balance: value(9),
+                ..Default::default()
+            },
+        );
+        db.insert_code(CONTRACT, code);
+        db.insert_storage(CONTRACT, value(1), value(7));
+        EvmState::with_database(db)
+    }
+
+    fn run(
+        state: &mut EvmState,
+        spec_id: SpecId,
+        to: Option<[u8; 20]>,
+        val: u8,
+        input: Vec<u8>,
+    ) -> TransactionResult {
+        let evm_context = EvmContext {
+            spec_id,
+            ..Default::default()
+        };
+        let tx = Transaction::Legacy(TxLegacy {
+            from: ALICE,
+            gas_limit: 100_000,
+            to,
+            value: value(val),
+            input,
+            ..Default::default()
+        });
+        let result = transact(state, &evm_context, tx).unwrap();
+        assert!(result.success, "{:?}", result.error);
+        result
+    }
+
+    #[test]
+    fn created_account_is_removed() {
+        let mut state = state(vec![0x00]);
+        let result = run(&mut state, SpecId::Cancun, None, 5, selfdestruct_to(BOB));
+        let address = result.contract_address.unwrap();
+
+        // 同一交易中创建的合约在交易结束时删除 余额转给受益人
+        assert!(!state.account_exists(address));
+        assert!(state.selfdestructs.is_empty());
+        assert_eq!(state.accounts[&BOB].balance, value(5));
+    }
+
+    #[test]
+    fn existing_account_keeps_code_and_storage() {
+        let mut state = state(selfdestruct_to(BOB));
+        run(&mut state, SpecId::Cancun, Some(CONTRACT), 0, Vec::new());
+
+        // Cancun起只转移余额 代码和存储保留
+        assert!(state.account_exists(CONTRACT));
+        assert_eq!(state.accounts[&CONTRACT].balance, value(0));
+        assert_eq!(state.accounts[&BOB].balance, value(9));
+        assert_eq!(state.get_code_at(CONTRACT), Some(&selfdestruct_to(BOB)));
+        assert_eq!(state.sload(CONTRACT, value(1)).unwrap(), value(7));
+    }
+
+    #[test]
+    fn existing_account_is_removed_before_cancun() {
+        let mut state = state(selfdestruct_to(BOB));
+        run(&mut state, SpecId::Shanghai, Some(CONTRACT), 0, Vec::new());
+
+        assert!(!state.account_exists(CONTRACT));
+        assert_eq!(state.accounts[&BOB].balance, value(9));
+        assert_eq!(state.sload(CONTRACT, value(1)).unwrap(), value(0));
+    }
+
+    #[test]
+    fn beneficiary_is_self() {
+        // 已存在的合约以自身为受益人时余额不变
+        let mut existing = state(SELFDESTRUCT_TO_SELF.to_vec());
+        run(&mut existing, SpecId::Cancun, Some(CONTRACT), 1, Vec::new());
+        assert_eq!(existing.accounts[&CONTRACT].balance, value(10));
+        assert_eq!(existing.get_code_at(CONTRACT), Some(&SELFDESTRUCT_TO_SELF.to_vec()));
+
+        // 同一交易中创建的合约被删除 余额随之销毁
+        let mut created = state(vec![0x00]);
+        let result = run(&mut created, SpecId::Cancun, None, 5, SELFDESTRUCT_TO_SELF.to_vec());
+        let address = result.contract_address.unwrap();
+        assert!(!created.account_exists(address));
+        assert_eq!(created.accounts[&ALICE].balance, value(95));
+    }
+
+    fn with_receiver(code: Vec<u8>) -> EvmState {
+        let mut state = state(code);
+        state.init_account(RECEIVER);
+        state.put_code_at(RECEIVER, STORE_SELFBALANCE.to_vec()).unwrap();
+        state
+    }
+
+    #[test]
+    fn call_moves_value_once() {
+        let mut state = with_receiver(call_receiver(4));
+        run(&mut state, SpecId::Cancun, Some(CONTRACT), 0, Vec::new());
+
+        assert_eq!(state.sload(CONTRACT, value(0)).unwrap(), value(1));
+        assert_eq!(state.accounts[&CONTRACT].balance, value(5));
+        assert_eq!(state.accounts[&RECEIVER].balance, value(4));
+        // 被调用者进入时已经看到转入的余额
+        assert_eq!(state.sload(RECEIVER, value(0)).unwrap(), value(4));
+    }
+
+    #[test]
+    fn call_with_insufficient_balance_fails() {
+        let mut state = with_receiver(call_receiver(10));
+        run(&mut state, SpecId::Cancun, Some(CONTRACT), 0, Vec::new());
+
+        // 调用返回0 被调用者没有执行 余额不变
+        assert_eq!(state.sload(CONTRACT, value(0)).unwrap(), value(0));
+        assert_eq!(state.sload(RECEIVER, value(0)).unwrap(), value(0));
+        assert_eq!(state.accounts[&CONTRACT].balance, value(9));
+        assert_eq!(state.accounts[&RECEIVER].balance, value(0));
+        assert_eq!(state.accounts[&CONTRACT].nonce, 1);
+    }
+
+    #[test]
+    fn create_with_insufficient_balance_fails() {
+        // CREATE(10, 0, 0) PUSH1 0 SSTORE
+        let code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x0a, 0xf0, 0x60, 0x00, 0x55];
+        let mut state = state(code);
+        run(&mut state, SpecId::Cancun, Some(CONTRACT), 0, Vec::new());
+
+        // 创建失败 不消耗nonce 余额不变
+        assert_eq!(state.sload(CONTRACT, value(0)).unwrap(), value(0));
+        assert_eq!(state.accounts[&CONTRACT].nonce, 1);
+        assert_eq!(state.accounts[&CONTRACT].balance, value(9));
+    }
+
+    #[test]
+    fn transaction_with_insufficient_balance_is_rejected() {
+        let mut state = with_receiver(Vec::new());
+        let tx = Transaction::Legacy(TxLegacy {
+            from: ALICE,
+            gas_limit: 100_000,
+            to: Some(RECEIVER),
+            value: value(101),
+            ..Default::default()
+        });
+        let err = transact(&mut state, &EvmContext::default(), tx).unwrap_err();
+
+        assert_eq!(err, RunnerError::InsufficientBalance);
+        assert_eq!(state.accounts[&ALICE].nonce, 0);
+        assert_eq!(state.accounts[&ALICE].balance, value(100));
+        assert_eq!(state.accounts[&RECEIVER].balance, value(0));
+    }
+}
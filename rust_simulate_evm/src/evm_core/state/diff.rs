@@ -0,0 +1,444 @@
+/*
+执行前后的状态差异
+按地址列出余额 nonce 代码的变化和每个存储槽的前后值 并标出新建和销毁的账户
+diff_since基于快照之后的修改日志计算: 快照之后才从数据库载入的账户和存储槽 原值重新从数据库读取
+*/
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use ethers::types::U256;
+use serde_json::{json, Map, Value};
+
+use crate::evm_core::state::journal::JournalEntry;
+use crate::evm_core::storage::{AccountState, EvmState, SnapshotId};
+use crate::evm_core::utils::error::RunnerError;
+
+/// How an account changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatus {
+    Created,
+    Destroyed,
+    Modified,
+}
+
+/// A value before and after.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<T> {
+    pub from: T,
+    pub to: T,
+}
+
+/// Changes to one account. Fields that did not change are `None`.
+///
+/// A created account changes from, and a destroyed one to, a zero balance,
+/// zero nonce, no code and all-zero storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub status: AccountStatus,
+    pub balance: Option<Change<[u8; 32]>>,
+    pub nonce: Option<Change<u64>>,
+    pub code: Option<Change<Vec<u8>>>,
+    /// Changed slots only.
+    pub storage: BTreeMap<[u8; 32], Change<[u8; 32]>>,
+}
+
+/// Per-address changes between two states.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub accounts: BTreeMap<[u8; 20], AccountDiff>,
+}
+
+impl StateDiff {
+    /// Diff two separate states, e.g. ones saved before and after a run.
+    ///
+    /// Only accounts and slots cached in either state are compared. A slot
+    /// cached on one side only counts as zero on the other.
+    pub fn between(pre: &EvmState, post: &EvmState) -> Self {
+        let addresses: BTreeSet<_> = pre.accounts.keys().chain(post.accounts.keys()).collect();
+        let mut diff = StateDiff::default();
+        for address in addresses {
+            let pre = pre.accounts.get(address).map(|account| (account, code(pre, account)));
+            let post = post.accounts.get(address).map(|account| (account, code(post, account)));
+            diff.insert(*address, pre, post);
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    pub fn created(&self) -> Vec<[u8; 20]> {
+        self.with_status(AccountStatus::Created)
+    }
+
+    pub fn destroyed(&self) -> Vec<[u8; 20]> {
+        self.with_status(AccountStatus::Destroyed)
+    }
+
+    fn with_status(&self, status: AccountStatus) -> Vec<[u8; 20]> {
+        self.accounts
+            .iter()
+            .filter(|(_, account)| account.status == status)
+            .map(|(address, _)| *address)
+            .collect()
+    }
+
+    fn insert(
+        &mut self,
+        address: [u8; 20],
+        pre: Option<(&AccountState, &[u8])>,
+        post: Option<(&AccountState, &[u8])>,
+    ) {
+        let status = match (pre, post) {
+            (None, None) => return,
+            (None, Some(_)) => AccountStatus::Created,
+            (Some(_), None) => AccountStatus::Destroyed,
+            (Some(_), Some(_)) => AccountStatus::Modified,
+        };
+        let empty = AccountState::default();
+        let (pre, pre_code) = pre.unwrap_or((&empty, &[]));
+        let (post, post_code) = post.unwrap_or((&empty, &[]));
+
+        let mut storage = BTreeMap::new();
+        for slot in pre.storage.keys().chain(post.storage.keys()) {
+            let from = pre.storage.get(slot).copied().unwrap_or_default();
+            let to = post.storage.get(slot).copied().unwrap_or_default();
+            if from != to {
+                storage.insert(*slot, Change { from, to });
+            }
+        }
+        let account = AccountDiff {
+            status,
+            balance: change(pre.balance, post.balance),
+            nonce: change(pre.nonce, post.nonce),
+            code: change(pre_code.to_vec(), post_code.to_vec()),
+            storage,
+        };
+        // 新建或销毁的账户即使各字段都为空也要列出
+        let unchanged = account.balance.is_none()
+            && account.nonce.is_none()
+            && account.code.is_none()
+            && account.storage.is_empty();
+        if status != AccountStatus::Modified || !unchanged {
+            self.accounts.insert(address, account);
+        }
+    }
+}
+
+impl EvmState {
+    /// Changes made since snapshot `id`, or `None` if there is no such
+    /// snapshot. The snapshot stays usable.
+    ///
+    /// Built from the journal kept since the snapshot. Values that were still
+    /// in the database at the snapshot, i.e. accounts and slots first read
+    /// after it, are read from the database again.
+    pub fn diff_since(&mut self, id: SnapshotId) -> Result<Option<StateDiff>, RunnerError> {
+        let Some(snapshot) = self.snapshots.iter().find(|snapshot| snapshot.id == id) else {
+            return Ok(None);
+        };
+
+        // 每个地址取快照之后最早的记录 即快照时的值
+        let mut changed: BTreeMap<[u8; 20], PreState> = BTreeMap::new();
+        for entry in &self.journal[snapshot.journal_len..] {
+            match entry {
+                JournalEntry::AccountChanged {
+                    address,
+                    nonce,
+                    balance,
+                    code_hash,
+                } => {
+                    let pre = changed.entry(*address).or_default();
+                    if pre.replaced.is_none() && pre.info.is_none() {
+                        pre.info = Some((*nonce, *balance, *code_hash));
+                    }
+                }
+                JournalEntry::AccountReplaced {
+                    address,
+                    prev,
+                    from_db,
+                    loaded,
+                } => {
+                    let pre = changed.entry(*address).or_default();
+                    if pre.replaced.is_none() {
+                        pre.replaced = Some((prev.clone(), *from_db, *loaded));
+                    }
+                }
+                // 账户被替换之后的写入属于新账户
+                JournalEntry::StorageChanged { address, slot, prev } => {
+                    let pre = changed.entry(*address).or_default();
+                    if pre.replaced.is_none() {
+                        pre.slots.entry(*slot).or_insert(*prev);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut diff = StateDiff::default();
+        let mut db_codes = HashMap::new();
+        for (address, changes) in changed {
+            let post = self.accounts.get(&address);
+            let (mut pre, from_db) = match changes.replaced {
+                Some((Some(account), from_db, _)) => (Some(account), from_db),
+                // 快照时已确认不存在
+                Some((None, _, true)) => (None, false),
+                Some((None, _, false)) => match self.db.basic(address)? {
+                    Some(info) => {
+                        if let Some(code) = info.code {
+                            db_codes.insert(info.code_hash, code);
+                        }
+                        let account = AccountState {
+                            nonce: info.nonce,
+                            balance: info.balance,
+                            code_hash: info.code_hash,
+                            ..Default::default()
+                        };
+                        (Some(account), true)
+                    }
+                    None => (None, false),
+                },
+                // 账户未被替换 只有字段和存储槽改变
+                None => {
+                    let mut account = post.cloned().unwrap_or_default();
+                    if let Some((nonce, balance, code_hash)) = changes.info {
+                        account.nonce = nonce;
+                        account.balance = balance;
+                        account.code_hash = code_hash;
+                    }
+                    for (slot, prev) in changes.slots {
+                        match prev {
+                            Some(value) => account.storage.insert(slot, value),
+                            None => account.storage.remove(&slot),
+                        };
+                    }
+                    (Some(account), self.db_accounts.contains(&address))
+                }
+            };
+
+            // 快照时未缓存的槽 原值在数据库中
+            if let (Some(pre), Some(post), true) = (&mut pre, post, from_db) {
+                for slot in post.storage.keys() {
+                    if !pre.storage.contains_key(slot) {
+                        pre.storage.insert(*slot, self.db.storage(address, *slot)?);
+                    }
+                }
+            }
+
+            let pre_code = match &pre {
+                Some(account) if account.has_code() => match self.codes.get(&account.code_hash) {
+                    Some(code) => code.clone(),
+                    None => match db_codes.get(&account.code_hash) {
+                        Some(code) => code.clone(),
+                        None => self.db.code_by_hash(account.code_hash)?,
+                    },
+                },
+                _ => Vec::new(),
+            };
+            let pre = pre.as_ref().map(|account| (account, pre_code.as_slice()));
+            let post = post.map(|account| (account, code(self, account)));
+            diff.insert(address, pre, post);
+        }
+        Ok(Some(diff))
+    }
+}
+
+//日志中记录的快照时的账户
+#[derive(Default)]
+struct PreState {
+    //账户被替换前的值 是否来自数据库 是否已载入
+    replaced: Option<(Option<AccountState>, bool, bool)>,
+    info: Option<(u64, [u8; 32], [u8; 32])>,
+    slots: HashMap<[u8; 32], Option<[u8; 32]>>,
+}
+
+fn code<'a>(state: &'a EvmState, account: &AccountState) -> &'a [u8] {
+    if !account.has_code() {
+        return &[];
+    }
+    state.codes.get(&account.code_hash).map_or(&[], Vec::as_slice)
+}
+
+fn change<T: PartialEq>(from: T, to: T) -> Option<Change<T>> {
+    (from != to).then_some(Change { from, to })
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    JSON                                    */
+/* -------------------------------------------------------------------------- */
+impl StateDiff {
+    /// Accounts by address, each with its status and the `from`/`to` of
+    /// every changed field and slot.
+    pub fn to_json(&self) -> Value {
+        let accounts: Map<String, Value> = self
+            .accounts
+            .iter()
+            .map(|(address, account)| (hex_string(address), account.to_json()))
+            .collect();
+        Value::Object(accounts)
+    }
+}
+
+impl AccountDiff {
+    pub fn to_json(&self) -> Value {
+        let status = match self.status {
+            AccountStatus::Created => "created",
+            AccountStatus::Destroyed => "destroyed",
+            AccountStatus::Modified => "modified",
+        };
+        let mut account = Map::new();
+        account.insert("status".to_string(), json!(status));
+        if let Some(balance) = &self.balance {
+            account.insert(
+                "balance".to_string(),
+                from_to(quantity(&balance.from), quantity(&balance.to)),
+            );
+        }
+        if let Some(nonce) = &self.nonce {
+            account.insert(
+                "nonce".to_string(),
+                from_to(format!("{:#x}", nonce.from), format!("{:#x}", nonce.to)),
+            );
+        }
+        if let Some(code) = &self.code {
+            account.insert("code".to_string(), from_to(hex_string(&code.from), hex_string(&code.to)));
+        }
+        let storage: Map<String, Value> = self
+            .storage
+            .iter()
+            .map(|(slot, value)| (hex_string(slot), from_to(hex_string(&value.from), hex_string(&value.to))))
+            .collect();
+        account.insert("storage".to_string(), Value::Object(storage));
+        Value::Object(account)
+    }
+}
+
+fn from_to(from: String, to: String) -> Value {
+    json!({ "from": from, "to": to })
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+//数值按JSON-RPC规则去掉前导0
+fn quantity(word: &[u8; 32]) -> String {
+    format!("{:#x}", U256::from_big_endian(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::db::memory::MemoryDb;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const BOB: [u8; 20] = [0xb0; 20];
+    const TOKEN: [u8; 20] = [0x70; 20];
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    fn db() -> MemoryDb {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                nonce: 1,
+                balance: value(9),
+                ..Default::default()
+            },
+        );
+        db.insert_code(TOKEN, vec![0x60, 0x00, 0x54, 0x00]);
+        db.insert_storage(TOKEN, value(1), value(7));
+        db.insert_storage(TOKEN, value(2), value(3));
+        db
+    }
+
+    #[test]
+    fn between_states() {
+        let mut pre = EvmState::with_database(db());
+        pre.load_account(ALICE).unwrap();
+        pre.sload(TOKEN, value(1)).unwrap();
+
+        let mut post = EvmState::with_database(db());
+        post.transfer(ALICE, BOB, value(4)).unwrap();
+        post.sstore(TOKEN, value(1), value(8)).unwrap();
+        post.sload(TOKEN, value(2)).unwrap();
+
+        let diff = StateDiff::between(&pre, &post);
+        assert_eq!(diff.created(), vec![BOB]);
+        assert!(diff.destroyed().is_empty());
+        assert_eq!(diff.accounts[&ALICE].balance, Some(Change { from: value(9), to: value(5) }));
+        assert_eq!(diff.accounts[&ALICE].nonce, None);
+        assert_eq!(diff.accounts[&BOB].balance, Some(Change { from: [0u8; 32], to: value(4) }));
+
+        // 只缓存在一侧的槽按0比较
+        let token = &diff.accounts[&TOKEN];
+        assert_eq!(token.status, AccountStatus::Modified);
+        assert_eq!(token.code, None);
+        assert_eq!(token.storage.len(), 2);
+        assert_eq!(token.storage[&value(1)], Change { from: value(7), to: value(8) });
+        assert_eq!(token.storage[&value(2)], Change { from: [0u8; 32], to: value(3) });
+
+        assert!(StateDiff::between(&post, &post).is_empty());
+        assert_eq!(StateDiff::between(&post, &pre).destroyed(), vec![BOB]);
+    }
+
+    #[test]
+    fn diff_since_snapshot() {
+        let mut state = EvmState::with_database(db());
+        state.sstore(TOKEN, value(2), value(4)).unwrap();
+        let id = state.snapshot();
+
+        // 快照之后才从数据库读取的账户和槽
+        state.transfer(ALICE, BOB, value(4)).unwrap();
+        state.sstore(TOKEN, value(1), value(8)).unwrap();
+        state.sstore(TOKEN, value(2), value(5)).unwrap();
+        state.put_code_at(BOB, vec![0x00]).unwrap();
+
+        let diff = state.diff_since(id).unwrap().unwrap();
+        assert_eq!(diff.created(), vec![BOB]);
+        assert_eq!(diff.accounts[&ALICE].balance, Some(Change { from: value(9), to: value(5) }));
+        assert_eq!(diff.accounts[&BOB].code, Some(Change { from: vec![], to: vec![0x00] }));
+        let token = &diff.accounts[&TOKEN];
+        assert_eq!(token.storage[&value(1)], Change { from: value(7), to: value(8) });
+        // 快照时的值而不是数据库中的值
+        assert_eq!(token.storage[&value(2)], Change { from: value(4), to: value(5) });
+
+        // 快照仍可使用
+        assert_eq!(state.diff_since(id).unwrap(), Some(diff));
+        assert!(state.revert_to(id));
+        assert_eq!(state.diff_since(id).unwrap(), None);
+    }
+
+    #[test]
+    fn unchanged_accounts_are_left_out() {
+        let mut state = EvmState::with_database(db());
+        let id = state.snapshot();
+        state.sstore(TOKEN, value(1), value(8)).unwrap();
+        state.sstore(TOKEN, value(1), value(7)).unwrap();
+        state.transfer(ALICE, ALICE, value(4)).unwrap();
+        assert!(state.diff_since(id).unwrap().unwrap().is_empty());
+    }
+
+    #[test]
+    fn json() {
+        let mut state = EvmState::with_database(db());
+        let id = state.snapshot();
+        state.transfer(ALICE, BOB, value(4)).unwrap();
+        state.sstore(TOKEN, value(1), value(8)).unwrap();
+
+        let json = state.diff_since(id).unwrap().unwrap().to_json();
+        let alice = &json[hex_string(&ALICE)];
+        assert_eq!(alice["status"], "modified");
+        assert_eq!(alice["balance"], json!({ "from": "0x9", "to": "0x5" }));
+        assert!(alice.get("nonce").is_none());
+        assert_eq!(json[hex_string(&BOB)]["status"], "created");
+        assert_eq!(
+            json[hex_string(&TOKEN)]["storage"][hex_string(&value(1))],
+            json!({ "from": hex_string(&value(7)), "to": hex_string(&value(8)) })
+        );
+    }
+}
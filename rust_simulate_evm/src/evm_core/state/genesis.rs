@@ -0,0 +1,285 @@
+/*
+geth格式的状态导入导出
+alloc: genesis.json中的alloc 每个地址的余额 nonce 代码和存储 导入时整体替换账户
+dump: geth dump的输出 带状态根 账户按校验和地址索引 余额为十进制
+只导出已缓存的账户 分叉状态中未读取过的账户不会出现
+*/
+use ethers::types::{Address, U256};
+use ethers::utils::{keccak256, to_checksum};
+use serde_json::{json, Map, Value};
+
+use crate::evm_core::storage::{AccountState, EvmState, KECCAK_EMPTY};
+use crate::evm_core::utils::byte_operate::{hex_to_address, hex_to_bytes, hex_to_u64, hex_to_word};
+use crate::evm_core::utils::error::RunnerError;
+
+impl EvmState {
+    /// An in-memory state holding the `alloc` of a geth genesis file.
+    ///
+    /// A bare `alloc` object is accepted as well.
+    pub fn from_genesis(json: &str) -> Result<Self, RunnerError> {
+        let genesis: Value =
+            serde_json::from_str(json).map_err(|e| RunnerError::InvalidGenesis(e.to_string()))?;
+        let mut state = EvmState::default();
+        state.load_alloc(genesis.get("alloc").unwrap_or(&genesis))?;
+        Ok(state)
+    }
+
+    /// Set each account of a genesis `alloc` object, replacing any account
+    /// already at the address together with its storage.
+    ///
+    /// Quantities may be hex or decimal strings, addresses may omit `0x`.
+    pub fn load_alloc(&mut self, alloc: &Value) -> Result<(), RunnerError> {
+        let alloc = alloc
+            .as_object()
+            .ok_or_else(|| invalid_genesis("alloc is not an object"))?;
+        for (address, entry) in alloc {
+            let address = hex_to_address(address)?;
+            let mut account = AccountState {
+                balance: optional(entry, "balance", quantity_to_word)?.unwrap_or_default(),
+                nonce: optional(entry, "nonce", quantity_to_u64)?.unwrap_or_default(),
+                ..Default::default()
+            };
+            if let Some(code) = optional(entry, "code", hex_value_to_bytes)? {
+                if !code.is_empty() {
+                    account.code_hash = keccak256(&code);
+                    self.codes.insert(account.code_hash, code);
+                }
+            }
+            if let Some(storage) = entry.get("storage").filter(|storage| !storage.is_null()) {
+                let storage = storage
+                    .as_object()
+                    .ok_or_else(|| invalid_genesis("storage is not an object"))?;
+                for (slot, value) in storage {
+                    let value = hex_value_to_word(value)?;
+                    if value != [0u8; 32] {
+                        account.storage.insert(hex_to_word(slot)?, value);
+                    }
+                }
+            }
+            self.replace_account(address, account);
+        }
+        Ok(())
+    }
+
+    /// The accounts as a genesis `alloc` object, in the shape geth writes.
+    pub fn to_alloc(&self) -> Value {
+        let mut alloc = Map::new();
+        for (address, account) in &self.accounts {
+            let mut entry = Map::new();
+            entry.insert("balance".to_string(), json!(quantity(&account.balance)));
+            if account.nonce != 0 {
+                entry.insert("nonce".to_string(), json!(format!("{:#x}", account.nonce)));
+            }
+            if let Some(code) = self.get_code_at(*address) {
+                entry.insert("code".to_string(), json!(hex_string(code)));
+            }
+            let storage = sorted_storage(account);
+            if !storage.is_empty() {
+                let storage: Map<String, Value> = storage
+                    .into_iter()
+                    .map(|(slot, value)| (hex_string(slot), json!(hex_string(value))))
+                    .collect();
+                entry.insert("storage".to_string(), Value::Object(storage));
+            }
+            // geth写出的alloc地址不带0x
+            alloc.insert(hex::encode(address), Value::Object(entry));
+        }
+        Value::Object(alloc)
+    }
+
+    /// The state in the format of `geth dump`, with the state root and each
+    /// account's storage root.
+    pub fn dump(&self) -> Value {
+        let mut accounts = Map::new();
+        for (address, account) in &self.accounts {
+            let mut entry = Map::new();
+            entry.insert(
+                "balance".to_string(),
+                json!(U256::from_big_endian(&account.balance).to_string()),
+            );
+            entry.insert("nonce".to_string(), json!(account.nonce));
+            entry.insert("root".to_string(), json!(hex_string(&account.storage_root())));
+            let code_hash = if account.has_code() {
+                account.code_hash
+            } else {
+                KECCAK_EMPTY
+            };
+            entry.insert("codeHash".to_string(), json!(hex_string(&code_hash)));
+            if let Some(code) = self.get_code_at(*address) {
+                entry.insert("code".to_string(), json!(hex_string(code)));
+            }
+            // 存储值为rlp解码后的字节 去掉前导0且不带0x
+            let storage: Map<String, Value> = sorted_storage(account)
+                .into_iter()
+                .map(|(slot, value)| {
+                    let start = value.iter().position(|byte| *byte != 0).unwrap_or(32);
+                    (hex_string(slot), json!(hex::encode(&value[start..])))
+                })
+                .collect();
+            if !storage.is_empty() {
+                entry.insert("storage".to_string(), Value::Object(storage));
+            }
+            entry.insert("address".to_string(), json!(hex_string(address)));
+            entry.insert("key".to_string(), json!(hex_string(&keccak256(address))));
+            accounts.insert(to_checksum(&Address::from(*address), None), Value::Object(entry));
+        }
+        json!({
+            "root": hex_string(&self.cached_state_root()),
+            "accounts": accounts,
+        })
+    }
+}
+
+//值为0的槽不属于状态 不导出
+fn sorted_storage(account: &AccountState) -> Vec<(&[u8; 32], &[u8; 32])> {
+    let mut storage: Vec<_> = account
+        .storage
+        .iter()
+        .filter(|(_, value)| **value != [0u8; 32])
+        .collect();
+    storage.sort();
+    storage
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                   Parsing                                  */
+/* -------------------------------------------------------------------------- */
+fn optional<T>(
+    value: &Value,
+    key: &str,
+    parse: fn(&Value) -> Result<T, RunnerError>,
+) -> Result<Option<T>, RunnerError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(field) => parse(field).map(Some),
+    }
+}
+
+//geth的HexOrDecimal 0x开头为十六进制 否则为十进制 也接受JSON数字
+fn quantity_to_word(value: &Value) -> Result<[u8; 32], RunnerError> {
+    let word = match value {
+        Value::String(text) if text.starts_with("0x") || text.starts_with("0X") => {
+            return hex_to_word(text)
+        }
+        Value::String(text) => U256::from_dec_str(text),
+        Value::Number(number) => U256::from_dec_str(&number.to_string()),
+        _ => return Err(invalid_genesis("quantity is not a string")),
+    }
+    .map_err(|_| invalid_genesis(&format!("invalid quantity {}", value)))?;
+    let mut bytes = [0u8; 32];
+    word.to_big_endian(&mut bytes);
+    Ok(bytes)
+}
+
+fn quantity_to_u64(value: &Value) -> Result<u64, RunnerError> {
+    match value {
+        Value::String(text) if text.starts_with("0x") || text.starts_with("0X") => hex_to_u64(text),
+        Value::String(text) => text
+            .parse()
+            .map_err(|_| invalid_genesis(&format!("invalid quantity {}", text))),
+        Value::Number(number) => number
+            .as_u64()
+            .ok_or_else(|| invalid_genesis(&format!("invalid quantity {}", number))),
+        _ => Err(invalid_genesis("quantity is not a string")),
+    }
+}
+
+fn hex_value_to_bytes(value: &Value) -> Result<Vec<u8>, RunnerError> {
+    hex_to_bytes(value.as_str().ok_or_else(|| invalid_genesis("code is not a string"))?)
+}
+
+fn hex_value_to_word(value: &Value) -> Result<[u8; 32], RunnerError> {
+    hex_to_word(value.as_str().ok_or_else(|| invalid_genesis("storage value is not a string"))?)
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+//数值按JSON-RPC规则去掉前导0
+fn quantity(word: &[u8; 32]) -> String {
+    format!("{:#x}", U256::from_big_endian(word))
+}
+
+fn invalid_genesis(reason: &str) -> RunnerError {
+    RunnerError::InvalidGenesis(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENESIS: &str = r#"{
+        "config": { "chainId": 1337 },
+        "alloc": {
+            "a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1": { "balance": "1000000000000000000" },
+            "0x7070707070707070707070707070707070707070": {
+                "balance": "0x0",
+                "nonce": "1",
+                "code": "0x6000546000",
+                "storage": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000000": "0x0000000000000000000000000000000000000000000000000000000000000007",
+                    "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000000"
+                }
+            }
+        }
+    }"#;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const TOKEN: [u8; 20] = [0x70; 20];
+
+    #[test]
+    fn load_genesis() {
+        let state = EvmState::from_genesis(GENESIS).unwrap();
+        let alice = &state.accounts[&ALICE];
+        assert_eq!(U256::from_big_endian(&alice.balance), U256::exp10(18));
+        assert_eq!(alice.nonce, 0);
+
+        let token = &state.accounts[&TOKEN];
+        assert_eq!(token.nonce, 1);
+        assert_eq!(state.get_code_at(TOKEN), Some(&vec![0x60, 0x00, 0x54, 0x60, 0x00]));
+        // 值为0的槽不导入
+        assert_eq!(token.storage.len(), 1);
+        assert_eq!(token.storage[&[0u8; 32]][31], 7);
+    }
+
+    #[test]
+    fn alloc_round_trip() {
+        let state = EvmState::from_genesis(GENESIS).unwrap();
+        let alloc = state.to_alloc();
+        assert_eq!(alloc[hex::encode(ALICE)]["balance"], "0xde0b6b3a7640000");
+        assert_eq!(alloc[hex::encode(TOKEN)]["nonce"], "0x1");
+
+        // 裸alloc对象也可以导入
+        let reloaded = EvmState::from_genesis(&alloc.to_string()).unwrap();
+        assert_eq!(reloaded.cached_state_root(), state.cached_state_root());
+        assert_eq!(reloaded.to_alloc(), alloc);
+    }
+
+    #[test]
+    fn dump_matches_state() {
+        let state = EvmState::from_genesis(GENESIS).unwrap();
+        let dump = state.dump();
+        assert_eq!(dump["root"], hex_string(&state.cached_state_root()));
+
+        let alice = &dump["accounts"][to_checksum(&Address::from(ALICE), None)];
+        assert_eq!(alice["balance"], "1000000000000000000");
+        assert_eq!(alice["codeHash"], hex_string(&KECCAK_EMPTY));
+        assert_eq!(alice["key"], hex_string(&keccak256(ALICE)));
+
+        let token = &dump["accounts"][to_checksum(&Address::from(TOKEN), None)];
+        assert_eq!(token["nonce"], 1);
+        assert_eq!(token["storage"][hex_string(&[0u8; 32])], "07");
+    }
+
+    #[test]
+    fn invalid_genesis_is_rejected() {
+        assert!(matches!(EvmState::from_genesis("{"), Err(RunnerError::InvalidGenesis(_))));
+        assert!(matches!(EvmState::from_genesis("[]"), Err(RunnerError::InvalidGenesis(_))));
+        let bad_balance = json!({ "alloc": { hex::encode(ALICE): { "balance": "ten" } } });
+        assert!(matches!(
+            EvmState::from_genesis(&bad_balance.to_string()),
+            Err(RunnerError::InvalidGenesis(_))
+        ));
+    }
+}
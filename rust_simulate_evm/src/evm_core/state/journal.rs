@@ -0,0 +1,252 @@
+/*
+状态修改日志
+存在快照时 每次修改状态都记录修改前的值 快照只保存当时的日志长度
+回滚时倒序撤销快照之后的记录 代价与快照之后的修改次数成正比 与状态大小无关
+从数据库载入账户和存储槽只是填充缓存 不记录 回滚后保留缓存的值
+没有快照时不记录 最后一个快照被丢弃时清空日志
+*/
+use std::collections::HashSet;
+
+use crate::evm_core::storage::{AccountState, EvmState, SnapshotId};
+
+/// One change to the state, holding what it replaced.
+#[derive(Debug)]
+pub(crate) enum JournalEntry {
+    /// Balance, nonce or code hash of an existing account changed.
+    AccountChanged {
+        address: [u8; 20],
+        nonce: u64,
+        balance: [u8; 32],
+        code_hash: [u8; 32],
+    },
+    /// The account was created, replaced or removed. `prev` is the account
+    /// before, with the database and loaded flags it had then.
+    AccountReplaced {
+        address: [u8; 20],
+        prev: Option<AccountState>,
+        from_db: bool,
+        loaded: bool,
+    },
+    /// A storage slot was written. `prev` is `None` if it was not cached.
+    StorageChanged {
+        address: [u8; 20],
+        slot: [u8; 32],
+        prev: Option<[u8; 32]>,
+    },
+    /// A log was appended at this index.
+    LogPushed(usize),
+    Touched([u8; 20]),
+    Created([u8; 20]),
+    SelfDestructed([u8; 20]),
+    /// The per-transaction sets were cleared at the end of a transaction.
+    TransactionCleared {
+        created_accounts: HashSet<[u8; 20]>,
+        selfdestructs: HashSet<[u8; 20]>,
+        touched: HashSet<[u8; 20]>,
+    },
+}
+
+//快照对应的日志位置
+#[derive(Debug)]
+pub(crate) struct Snapshot {
+    pub(crate) id: SnapshotId,
+    pub(crate) journal_len: usize,
+}
+
+impl EvmState {
+    /// Mark the current state, like `evm_snapshot`. Constant time: from now
+    /// on changes are journaled until the snapshot is reverted or discarded.
+    pub fn snapshot(&mut self) -> SnapshotId {
+        let id = SnapshotId(self.next_snapshot_id);
+        self.next_snapshot_id += 1;
+        self.snapshots.push(Snapshot {
+            id,
+            journal_len: self.journal.len(),
+        });
+        id
+    }
+
+    /// Undo every change since snapshot `id`, like `evm_revert`, returning
+    /// `false` if there is no such snapshot.
+    ///
+    /// The snapshot is used up, and so are those taken after it. Take a new
+    /// one to revert to the same point again.
+    pub fn revert_to(&mut self, id: SnapshotId) -> bool {
+        let Some(index) = self.snapshots.iter().position(|snapshot| snapshot.id == id) else {
+            return false;
+        };
+        let journal_len = self.snapshots[index].journal_len;
+        self.snapshots.truncate(index);
+        while self.journal.len() > journal_len {
+            if let Some(entry) = self.journal.pop() {
+                self.undo(entry);
+            }
+        }
+        true
+    }
+
+    /// Keep the changes since snapshot `id` and forget the snapshot and
+    /// those taken after it, returning `false` if there is no such snapshot.
+    pub fn discard_snapshot(&mut self, id: SnapshotId) -> bool {
+        let Some(index) = self.snapshots.iter().position(|snapshot| snapshot.id == id) else {
+            return false;
+        };
+        self.snapshots.truncate(index);
+        if self.snapshots.is_empty() {
+            self.journal.clear();
+        }
+        true
+    }
+
+    //只在有快照时记录
+    pub(crate) fn record(&mut self, entry: JournalEntry) {
+        if !self.snapshots.is_empty() {
+            self.journal.push(entry);
+        }
+    }
+
+    fn undo(&mut self, entry: JournalEntry) {
+        match entry {
+            JournalEntry::AccountChanged {
+                address,
+                nonce,
+                balance,
+                code_hash,
+            } => {
+                if let Some(account) = self.accounts.get_mut(&address) {
+                    account.nonce = nonce;
+                    account.balance = balance;
+                    account.code_hash = code_hash;
+                }
+            }
+            JournalEntry::AccountReplaced {
+                address,
+                prev,
+                from_db,
+                loaded,
+            } => {
+                match prev {
+                    Some(account) => self.accounts.insert(address, account),
+                    None => self.accounts.remove(&address),
+                };
+                match from_db {
+                    true => self.db_accounts.insert(address),
+                    false => self.db_accounts.remove(&address),
+                };
+                // 之前未载入的账户需要时重新从数据库读取
+                if !loaded {
+                    self.loaded.remove(&address);
+                }
+            }
+            JournalEntry::StorageChanged { address, slot, prev } => {
+                if let Some(account) = self.accounts.get_mut(&address) {
+                    match prev {
+                        Some(value) => account.storage.insert(slot, value),
+                        None => account.storage.remove(&slot),
+                    };
+                }
+            }
+            JournalEntry::LogPushed(index) => self.logs.truncate(index),
+            JournalEntry::Touched(address) => {
+                self.touched.remove(&address);
+            }
+            JournalEntry::Created(address) => {
+                self.created_accounts.remove(&address);
+            }
+            JournalEntry::SelfDestructed(address) => {
+                self.selfdestructs.remove(&address);
+            }
+            JournalEntry::TransactionCleared {
+                created_accounts,
+                selfdestructs,
+                touched,
+            } => {
+                self.created_accounts = created_accounts;
+                self.selfdestructs = selfdestructs;
+                self.touched = touched;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::context::spec::SpecId;
+    use crate::evm_core::db::memory::MemoryDb;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const BOB: [u8; 20] = [0xb0; 20];
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    fn state() -> EvmState {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: value(9),
+                ..Default::default()
+            },
+        );
+        db.insert_storage(ALICE, value(1), value(7));
+        EvmState::with_database(db)
+    }
+
+    #[test]
+    fn revert_undoes_changes() {
+        let mut state = state();
+        // 缓存填充不记录 先读入 回滚前后的状态根才可比较
+        state.sload(ALICE, value(1)).unwrap();
+        let root = state.state_root().unwrap();
+
+        let id = state.snapshot();
+        state.transfer(ALICE, BOB, value(4)).unwrap();
+        state.sstore(ALICE, value(1), value(8)).unwrap();
+        state.put_code_at(ALICE, vec![0x00]).unwrap();
+        state.finalize_transaction(true, SpecId::Cancun);
+        assert_ne!(state.state_root().unwrap(), root);
+
+        assert!(state.revert_to(id));
+        assert!(!state.account_exists(BOB));
+        assert_eq!(state.accounts[&ALICE].balance, value(9));
+        assert_eq!(state.sload(ALICE, value(1)).unwrap(), value(7));
+        assert!(state.touched.is_empty());
+        assert_eq!(state.state_root().unwrap(), root);
+        // 快照已用完
+        assert!(!state.revert_to(id));
+    }
+
+    #[test]
+    fn nested_snapshots() {
+        let mut state = state();
+        let outer = state.snapshot();
+        state.sstore(ALICE, value(1), value(8)).unwrap();
+        let inner = state.snapshot();
+        state.sstore(ALICE, value(1), value(9)).unwrap();
+
+        assert!(state.revert_to(inner));
+        assert_eq!(state.sload(ALICE, value(1)).unwrap(), value(8));
+        assert!(state.revert_to(outer));
+        assert_eq!(state.sload(ALICE, value(1)).unwrap(), value(7));
+        assert!(state.journal.is_empty());
+    }
+
+    #[test]
+    fn discard_keeps_changes() {
+        let mut state = state();
+        let outer = state.snapshot();
+        let inner = state.snapshot();
+        state.sstore(ALICE, value(1), value(8)).unwrap();
+
+        // 丢弃外层快照时内层快照也被丢弃
+        assert!(state.discard_snapshot(outer));
+        assert!(!state.revert_to(inner));
+        assert!(state.journal.is_empty());
+        assert_eq!(state.sload(ALICE, value(1)).unwrap(), value(8));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod genesis;
+
+pub mod diff;
+
+pub mod overlay;
+
+pub mod journal;
@@ -0,0 +1,436 @@
+/*
+基于共享基础状态的写时复制覆盖层 用于eth_call式的假设模拟
+覆盖层是一个普通的EvmState 其数据库从基础状态读取 写入只进入覆盖层自己的缓存
+先读基础状态的缓存 再读基础状态的数据库 读到的值只缓存在覆盖层 基础状态不被修改
+基础状态只在每次读取时加锁 多个覆盖层可以在不同线程中同时运行 创建覆盖层不复制基础状态
+账户覆盖(余额 nonce 代码 state/stateDiff)在创建时写入覆盖层缓存
+*/
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use alloy_primitives::B256;
+use ethers::types::U256;
+use ethers::utils::keccak256;
+
+use crate::evm_core::context::evm_context::EvmContext;
+use crate::evm_core::db::database::{AccountInfo, Database, StateKeys};
+use crate::evm_core::storage::{EvmState, KECCAK_EMPTY};
+use crate::evm_core::utils::byte_operate::u64_to_u256_array;
+use crate::evm_core::utils::error::RunnerError;
+
+/// Overrides for one account, as in the `eth_call` state override set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountOverride {
+    pub balance: Option<[u8; 32]>,
+    pub nonce: Option<u64>,
+    pub code: Option<Vec<u8>>,
+    /// Replaces the whole storage: slots not listed read as zero.
+    pub state: Option<HashMap<[u8; 32], [u8; 32]>>,
+    /// Patches single slots on top of the base storage.
+    pub state_diff: Option<HashMap<[u8; 32], [u8; 32]>>,
+}
+
+/// Account overrides by address.
+pub type StateOverrides = HashMap<[u8; 20], AccountOverride>;
+
+/// Overrides of the block a call runs in, as in `eth_call`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockOverrides {
+    pub number: Option<u64>,
+    pub time: Option<u64>,
+    pub gas_limit: Option<u64>,
+    pub coinbase: Option<[u8; 20]>,
+    pub random: Option<[u8; 32]>,
+    pub base_fee: Option<u128>,
+    /// Hashes returned by BLOCKHASH, by block number.
+    pub block_hash: HashMap<u64, [u8; 32]>,
+}
+
+impl BlockOverrides {
+    /// A copy of `context` with the overridden fields replaced.
+    pub fn apply(&self, context: &EvmContext) -> EvmContext {
+        let mut context = context.clone();
+        if let Some(number) = self.number {
+            context.block_number = Some(u64_to_u256_array(number));
+        }
+        if let Some(time) = self.time {
+            context.timestamp = Some(u64_to_u256_array(time));
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            context.gas_limit = Some(u64_to_u256_array(gas_limit));
+        }
+        if let Some(coinbase) = self.coinbase {
+            context.coinbase = Some(coinbase);
+        }
+        if let Some(random) = self.random {
+            context.prevrandao = Some(B256::from(random));
+        }
+        if let Some(base_fee) = self.base_fee {
+            let mut basefee = [0u8; 32];
+            U256::from(base_fee).to_big_endian(&mut basefee);
+            context.basefee = Some(basefee);
+        }
+        context.block_hashes.extend(&self.block_hash);
+        context
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                 Overlay db                                 */
+/* -------------------------------------------------------------------------- */
+/// A [`Database`] reading through to a base state shared by many overlays.
+///
+/// Reads look at what the base has cached, then at the base's database. The
+/// base's cache is never filled: what an overlay loads stays in the overlay.
+/// The base is locked only for each read, so overlays may run on several
+/// threads at once.
+#[derive(Debug)]
+pub struct OverlayDb {
+    base: Arc<Mutex<EvmState>>,
+}
+
+impl OverlayDb {
+    pub fn new(base: Arc<Mutex<EvmState>>) -> Self {
+        Self { base }
+    }
+
+    fn base(&self) -> MutexGuard<'_, EvmState> {
+        self.base.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Database for OverlayDb {
+    fn basic(&mut self, address: [u8; 20]) -> Result<Option<AccountInfo>, RunnerError> {
+        let mut base = self.base();
+        if let Some(account) = base.accounts.get(&address) {
+            return Ok(Some(AccountInfo {
+                balance: account.balance,
+                nonce: account.nonce,
+                code_hash: account.code_hash,
+                code: base.codes.get(&account.code_hash).cloned(),
+            }));
+        }
+        // 基础状态中不存在或已删除的账户
+        if base.loaded.contains(&address) {
+            return Ok(None);
+        }
+        base.db.basic(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: [u8; 32]) -> Result<Vec<u8>, RunnerError> {
+        let mut base = self.base();
+        match base.codes.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => base.db.code_by_hash(code_hash),
+        }
+    }
+
+    fn storage(&mut self, address: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError> {
+        let mut base = self.base();
+        match base.accounts.get(&address) {
+            Some(account) => {
+                if let Some(value) = account.storage.get(&slot) {
+                    return Ok(*value);
+                }
+                // 基础状态本地创建的账户没有数据库中的存储
+                if !base.db_accounts.contains(&address) {
+                    return Ok([0u8; 32]);
+                }
+            }
+            None if base.loaded.contains(&address) => return Ok([0u8; 32]),
+            None => {}
+        }
+        base.db.storage(address, slot)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<[u8; 32], RunnerError> {
+        self.base().db.block_hash(number)
+    }
+
+    fn prefetch(&mut self, accounts: &[[u8; 20]], storage: &[([u8; 20], [u8; 32])]) {
+        self.base().prefetch(accounts, storage)
+    }
+
+    // 基础数据库的键加上基础状态缓存的账户和槽 已删除的账户读取时返回None
+    fn state_keys(&mut self) -> Result<Option<StateKeys>, RunnerError> {
+        let mut base = self.base();
+        let Some(mut keys) = base.db.state_keys()? else {
+            return Ok(None);
+        };
+        for (address, account) in &base.accounts {
+            let slots = keys.entry(*address).or_default();
+            slots.extend(account.storage.keys());
+            slots.sort();
+            slots.dedup();
+        }
+        Ok(Some(keys))
+    }
+}
+
+impl EvmState {
+    /// A copy-on-write state on top of `base` with `overrides` applied.
+    ///
+    /// Nothing is copied up front: accounts and slots are read from the base
+    /// on first use and writes stay in the overlay. Take a snapshot of the
+    /// overlay to list its writes later with [`EvmState::diff_since`].
+    pub fn overlay(
+        base: &Arc<Mutex<EvmState>>,
+        overrides: &StateOverrides,
+    ) -> Result<EvmState, RunnerError> {
+        let mut state = EvmState::with_database(OverlayDb::new(base.clone()));
+        state.apply_overrides(overrides)?;
+        Ok(state)
+    }
+
+    /// Apply `eth_call` style account overrides to this state.
+    pub fn apply_overrides(&mut self, overrides: &StateOverrides) -> Result<(), RunnerError> {
+        for (address, account_override) in overrides {
+            if account_override.state.is_some() && account_override.state_diff.is_some() {
+                return Err(RunnerError::InvalidOverride(format!(
+                    "{} has both state and stateDiff",
+                    hex::encode(address)
+                )));
+            }
+            self.load_account(*address)?;
+            let mut account = self.accounts.get(address).cloned().unwrap_or_default();
+            if let Some(balance) = account_override.balance {
+                account.balance = balance;
+            }
+            if let Some(nonce) = account_override.nonce {
+                account.nonce = nonce;
+            }
+            if let Some(code) = &account_override.code {
+                account.code_hash = KECCAK_EMPTY;
+                if !code.is_empty() {
+                    account.code_hash = keccak256(code);
+                    self.codes.insert(account.code_hash, code.clone());
+                }
+            }
+
+            match &account_override.state {
+                // 整体替换存储 未列出的槽不再读取基础状态
+                Some(storage) => {
+                    account.storage = storage.clone();
+                    self.replace_account(*address, account);
+                }
+                None => {
+                    if let Some(state_diff) = &account_override.state_diff {
+                        account.storage.extend(state_diff);
+                    }
+                    let from_db = self.db_accounts.contains(address);
+                    self.put_account(*address, Some(account), from_db);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::AccountState;
+    use crate::evm_core::transaction::transact::transact;
+    use crate::evm_core::transaction::types::{Transaction, TxLegacy};
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const BOB: [u8; 20] = [0xb0; 20];
+    const TOKEN: [u8; 20] = [0x70; 20];
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    // TOKEN的槽1为7 槽2为8
+    fn base() -> Arc<Mutex<EvmState>> {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: u64_to_u256_array(1_000_000),
+                ..Default::default()
+            },
+        );
+        db.insert_account(TOKEN, AccountState::default());
+        db.insert_storage(TOKEN, value(1), value(7));
+        db.insert_storage(TOKEN, value(2), value(8));
+        Arc::new(Mutex::new(EvmState::with_database(db)))
+    }
+
+    fn send(state: &mut EvmState, to: [u8; 20], amount: u64) {
+        let tx = Transaction::Legacy(TxLegacy {
+            from: ALICE,
+            gas_limit: 21_000,
+            to: Some(to),
+            value: u64_to_u256_array(amount),
+            ..Default::default()
+        });
+        assert!(transact(state, &EvmContext::new(), tx).unwrap().success);
+    }
+
+    fn storage_override(
+        state: Option<HashMap<[u8; 32], [u8; 32]>>,
+        state_diff: Option<HashMap<[u8; 32], [u8; 32]>>,
+    ) -> StateOverrides {
+        HashMap::from([(
+            TOKEN,
+            AccountOverride {
+                state,
+                state_diff,
+                ..Default::default()
+            },
+        )])
+    }
+
+    #[test]
+    fn writes_stay_in_overlay() {
+        let base = base();
+        let mut overlay = EvmState::overlay(&base, &StateOverrides::new()).unwrap();
+        send(&mut overlay, BOB, 5);
+        overlay.load_account(TOKEN).unwrap();
+        overlay.sstore(TOKEN, value(1), value(9)).unwrap();
+        assert_eq!(overlay.accounts[&BOB].balance, u64_to_u256_array(5));
+
+        // 基础状态既没有被修改 也没有缓存覆盖层读取的值
+        let mut base = base.lock().unwrap();
+        assert!(base.accounts.is_empty());
+        assert!(!base.account_exists(BOB));
+        assert_eq!(base.sload(TOKEN, value(1)).unwrap(), value(7));
+        base.load_account(ALICE).unwrap();
+        assert_eq!(base.accounts[&ALICE].balance, u64_to_u256_array(1_000_000));
+    }
+
+    #[test]
+    fn overlay_reads_base_cache() {
+        let base = base();
+        {
+            let mut base = base.lock().unwrap();
+            base.load_account(TOKEN).unwrap();
+            base.sstore(TOKEN, value(1), value(3)).unwrap();
+            base.put_account(BOB, None, true);
+        }
+        let mut overlay = EvmState::overlay(&base, &StateOverrides::new()).unwrap();
+        assert_eq!(overlay.sload(TOKEN, value(1)).unwrap(), value(3));
+        assert_eq!(overlay.sload(TOKEN, value(2)).unwrap(), value(8));
+        assert!(!overlay.account_exists(BOB));
+    }
+
+    #[test]
+    fn account_overrides() {
+        let base = base();
+        let overrides = HashMap::from([(
+            ALICE,
+            AccountOverride {
+                balance: Some(value(4)),
+                nonce: Some(6),
+                code: Some(vec![0x00]),
+                ..Default::default()
+            },
+        )]);
+        let overlay = EvmState::overlay(&base, &overrides).unwrap();
+        assert_eq!(overlay.accounts[&ALICE].balance, value(4));
+        assert_eq!(overlay.accounts[&ALICE].nonce, 6);
+        assert_eq!(overlay.get_code_at(ALICE), Some(&vec![0x00]));
+    }
+
+    #[test]
+    fn state_replaces_storage() {
+        let base = base();
+        let overrides = storage_override(Some(HashMap::from([(value(1), value(9))])), None);
+        let mut overlay = EvmState::overlay(&base, &overrides).unwrap();
+        assert_eq!(overlay.sload(TOKEN, value(1)).unwrap(), value(9));
+        // 未列出的槽为0
+        assert_eq!(overlay.sload(TOKEN, value(2)).unwrap(), [0u8; 32]);
+    }
+
+    #[test]
+    fn state_diff_patches_storage() {
+        let base = base();
+        let overrides = storage_override(None, Some(HashMap::from([(value(1), value(9))])));
+        let mut overlay = EvmState::overlay(&base, &overrides).unwrap();
+        assert_eq!(overlay.sload(TOKEN, value(1)).unwrap(), value(9));
+        assert_eq!(overlay.sload(TOKEN, value(2)).unwrap(), value(8));
+    }
+
+    #[test]
+    fn state_and_state_diff_conflict() {
+        let storage = HashMap::from([(value(1), value(9))]);
+        let overrides = storage_override(Some(storage.clone()), Some(storage));
+        assert!(matches!(
+            EvmState::overlay(&base(), &overrides),
+            Err(RunnerError::InvalidOverride(_))
+        ));
+    }
+
+    #[test]
+    fn block_overrides() {
+        let mut context = EvmContext::new();
+        context.block_number = Some(u64_to_u256_array(1));
+        context.timestamp = Some(u64_to_u256_array(2));
+        let overrides = BlockOverrides {
+            number: Some(10),
+            base_fee: Some(7),
+            block_hash: HashMap::from([(9, value(5))]),
+            ..Default::default()
+        };
+        let context = overrides.apply(&context);
+        assert_eq!(context.block_number, Some(u64_to_u256_array(10)));
+        assert_eq!(context.timestamp, Some(u64_to_u256_array(2)));
+        assert_eq!(context.basefee, Some(u64_to_u256_array(7)));
+        assert_eq!(context.block_hashes[&9], value(5));
+    }
+
+    #[test]
+    fn state_root_covers_base() {
+        let base = base();
+        let root = base.lock().unwrap().state_root().unwrap();
+        let mut overlay = EvmState::overlay(&base, &StateOverrides::new()).unwrap();
+        assert_eq!(overlay.state_root().unwrap(), root);
+
+        // 与直接修改完整状态的结果相同
+        let overrides = storage_override(None, Some(HashMap::from([(value(1), value(9))])));
+        let mut overlay = EvmState::overlay(&base, &overrides).unwrap();
+        send(&mut overlay, BOB, 5);
+        let mut expected = EvmState::with_database(MemoryDb::new());
+        expected.accounts = base.lock().unwrap().accounts.clone();
+        expected.apply_overrides(&overrides).unwrap();
+        send(&mut expected, BOB, 5);
+        assert_eq!(overlay.state_root().unwrap(), expected.state_root().unwrap());
+    }
+
+    #[test]
+    fn concurrent_overlays() {
+        let base = base();
+        let handles: Vec<_> = (1..=4u8)
+            .map(|index| {
+                let base = base.clone();
+                thread::spawn(move || {
+                    let overrides =
+                        storage_override(None, Some(HashMap::from([(value(2), value(index))])));
+                    let mut overlay = EvmState::overlay(&base, &overrides).unwrap();
+                    send(&mut overlay, BOB, index as u64);
+                    (
+                        overlay.sload(TOKEN, value(1)).unwrap(),
+                        overlay.sload(TOKEN, value(2)).unwrap(),
+                        overlay.accounts[&BOB].balance,
+                    )
+                })
+            })
+            .collect();
+        for (index, handle) in (1..=4u8).zip(handles) {
+            assert_eq!(
+                handle.join().unwrap(),
+                (value(7), value(index), u64_to_u256_array(index as u64))
+            );
+        }
+
+        let mut base = base.lock().unwrap();
+        assert!(base.accounts.is_empty());
+        assert_eq!(base.sload(TOKEN, value(2)).unwrap(), value(8));
+    }
+}
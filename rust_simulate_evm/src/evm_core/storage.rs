@@ -1,34 +1,92 @@
 use super::utils::error::RunnerError;
 use ethers::utils::keccak256;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::path::{Path, PathBuf};
 use colored::Colorize;
-use ethers::addressbook::Address;
-use ethers::prelude::{H256, U256};
-use ethers::prelude::*;
-
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+
+use super::db::cache::CacheDb;
+use super::db::database::Database;
+use super::db::prefetch::{predict_accounts, predict_storage_keys};
+use super::db::memory::MemoryDb;
+use super::db::provider::ProviderDb;
 use super::log::Log;
+use super::state::journal::{JournalEntry, Snapshot};
 
 use crate::evm_core::utils::debug;
+use crate::evm_core::utils::serde_hex;
+use crate::evm_core::context::spec::SpecId;
+
+/// keccak256 of empty bytes, the code hash of an account without code.
+pub const KECCAK_EMPTY: [u8; 32] = [
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+];
+
+/// EIP-7702 delegation designator prefix, followed by the 20-byte target.
+pub const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// Code written to an account delegating to `address`: `0xef0100 || address`.
+pub fn delegation_designator(address: [u8; 20]) -> Vec<u8> {
+    [DELEGATION_PREFIX.as_slice(), address.as_slice()].concat()
+}
+
+/// Target of a delegation designator, or `None` for ordinary code.
+pub fn delegated_address(code: &[u8]) -> Option<[u8; 20]> {
+    match code.strip_prefix(DELEGATION_PREFIX.as_slice()) {
+        Some(address) if address.len() == 20 => address.try_into().ok(),
+        _ => None,
+    }
+}
 
 /* -------------------------------------------------------------------------- */
 /*                             AccountState struct                            */
 /* -------------------------------------------------------------------------- */
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AccountState {
     pub nonce: u64,
+    #[serde(with = "serde_hex::bytes")]
     pub balance: [u8; 32],
+    #[serde(with = "serde_hex::map")]
     pub storage: HashMap<[u8; 32], [u8; 32]>,    //<slot, value>
+    #[serde(with = "serde_hex::bytes")]
     pub code_hash: [u8; 32],
 }
 
+impl Default for AccountState {
+    fn default() -> Self {
+        Self {
+            nonce: 0,
+            balance: [0u8; 32],
+            storage: HashMap::new(),
+            code_hash: KECCAK_EMPTY,
+        }
+    }
+}
+
+impl AccountState {
+    /// `KECCAK_EMPTY` marks an account without code. All-zero is accepted as
+    /// well for states built by hand.
+    pub fn has_code(&self) -> bool {
+        self.code_hash != KECCAK_EMPTY && self.code_hash != [0u8; 32]
+    }
+
+    /// EIP-161: an account is empty when it has no code, zero nonce and zero balance.
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balance == [0u8; 32] && !self.has_code()
+    }
+}
+
 //Debug Display Traits
 impl fmt::Debug for AccountState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut code_hash: String = debug::to_hex_string(self.code_hash);
         //check空哈希
-        if self.code_hash == [0u8; 32] {
+        if !self.has_code() {
             code_hash = format!("{}", "Empty code".red()).to_string()
         }
         //打印nonce
@@ -66,30 +124,314 @@ impl fmt::Debug for AccountState {
 /* -------------------------------------------------------------------------- */
 /*                              EVM state struct                              */
 /* -------------------------------------------------------------------------- */
-#[derive(Debug)]
+/// Serializes everything but the database, snapshots and their journal. A deserialized
+/// state reads from an empty [`MemoryDb`] until
+/// [`EvmState::set_database`] attaches the database it was built on.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EvmState {
+    #[serde(with = "serde_hex::key_map")]
     pub accounts: HashMap<[u8; 20], AccountState>,
+    #[serde(with = "serde_hex::map")]
     pub codes: HashMap<[u8; 32], Vec<u8>>,
     pub logs: Vec<Log>,
     pub static_mode: bool,     //pure view
-    pub provider: Option<Provider<Http>>, //节点 缺少本地状态时从链上拉取数据
+    #[serde(skip, default = "empty_database")]
+    pub db: Box<dyn Database>, //缓存中没有的账户 代码和存储从数据库读取
+    #[serde(with = "serde_hex::set")]
+    pub(crate) loaded: HashSet<[u8; 20]>, //已向数据库查询过的账户
+    #[serde(with = "serde_hex::set")]
+    pub(crate) db_accounts: HashSet<[u8; 20]>, //来自数据库的账户 未缓存的存储槽需从数据库读取
+    #[serde(with = "serde_hex::set")]
+    pub created_accounts: HashSet<[u8; 20]>, //当前交易中创建的合约
+    #[serde(with = "serde_hex::set")]
+    pub selfdestructs: HashSet<[u8; 20]>,    //交易结束时待删除的账户
+    #[serde(with = "serde_hex::set")]
+    pub touched: HashSet<[u8; 20]>,          //当前交易中被触及的账户
+    #[serde(skip)]
+    pub(crate) snapshots: Vec<Snapshot>,     //按id递增
+    #[serde(skip)]
+    pub(crate) next_snapshot_id: u64,
+    #[serde(skip)]
+    pub(crate) journal: Vec<JournalEntry>,   //最早的快照之后的修改
 }
 
+fn empty_database() -> Box<dyn Database> {
+    Box::new(MemoryDb::new())
+}
+
+//空的内存状态
+impl Default for EvmState {
+    fn default() -> Self {
+        Self::with_database(MemoryDb::new())
+    }
+}
+
+/// Id of a state snapshot, see [`EvmState::snapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SnapshotId(pub u64);
+
 impl EvmState {
-    pub fn new(fork_url: Option<String>) -> Self {
+    /// An empty in-memory state, or one reading a node at `fork_url`.
+    ///
+    /// Fails if `fork_url` is not a valid URL.
+    pub fn new(fork_url: Option<String>) -> Result<Self, RunnerError> {
+        match fork_url {
+            Some(url) => Ok(Self::with_database(ProviderDb::new(&url)?)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Lazily fork the chain behind `url` at `block_number`, or at its
+    /// current block when `None`.
+    pub fn fork(url: &str, block_number: Option<u64>) -> Result<Self, RunnerError> {
+        Ok(Self::with_database(ProviderDb::pinned(url, block_number)?))
+    }
+
+    /// Like [`EvmState::fork`], recording every remote read to the JSON file
+    /// at `cache_path`, see [`CacheDb::record`].
+    pub fn fork_recorded(
+        url: &str,
+        block_number: Option<u64>,
+        cache_path: impl Into<PathBuf>,
+    ) -> Result<Self, RunnerError> {
+        let provider = ProviderDb::pinned(url, block_number)?;
+        let chain_id = provider.chain_id()?;
+        let block_number = provider.block_number.unwrap_or_default();
+        Ok(Self::with_database(CacheDb::record(
+            provider,
+            chain_id,
+            block_number,
+            cache_path,
+        )?))
+    }
+
+    /// Replay a fork recorded by [`EvmState::fork_recorded`] without a node.
+    pub fn fork_replayed(cache_path: impl AsRef<Path>) -> Result<Self, RunnerError> {
+        Ok(Self::with_database(CacheDb::replay_file(cache_path)?))
+    }
+
+    /// A state that reads what it has not cached yet from `db`.
+    pub fn with_database(db: impl Database + 'static) -> Self {
         Self {
             accounts: HashMap::new(),
             codes: HashMap::new(),
             logs: Vec::new(),
             static_mode: false,
-            provider: if fork_url.is_some() {
-                Some(Provider::<Http>::try_from(fork_url.unwrap()).unwrap())
-            } else {
-                None
+            db: Box::new(db),
+            loaded: HashSet::new(),
+            db_accounts: HashSet::new(),
+            created_accounts: HashSet::new(),
+            selfdestructs: HashSet::new(),
+            touched: HashSet::new(),
+            snapshots: Vec::new(),
+            next_snapshot_id: 0,
+            journal: Vec::new(),
+        }
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                  Database                                  */
+    /* -------------------------------------------------------------------------- */
+    /// Bring `address` into the cache from the database, returning whether
+    /// the account exists.
+    ///
+    /// Each address is looked up once. Reads through `&self`, such as
+    /// [`EvmState::get_code_at`], only see accounts loaded before.
+    pub fn load_account(&mut self, address: [u8; 20]) -> Result<bool, RunnerError> {
+        if self.accounts.contains_key(&address) {
+            return Ok(true);
+        }
+        if !self.loaded.insert(address) {
+            return Ok(false);
+        }
+
+        let info = match self.db.basic(address) {
+            Ok(Some(info)) => info,
+            Ok(None) => return Ok(false),
+            Err(e) => {
+                self.loaded.remove(&address);
+                return Err(e);
+            }
+        };
+        let has_code = info.code_hash != KECCAK_EMPTY && info.code_hash != [0u8; 32];
+        if has_code && !self.codes.contains_key(&info.code_hash) {
+            let code = match info.code {
+                Some(code) => code,
+                None => self.db.code_by_hash(info.code_hash)?,
+            };
+            self.codes.insert(info.code_hash, code);
+        }
+        self.accounts.insert(
+            address,
+            AccountState {
+                nonce: info.nonce,
+                balance: info.balance,
+                storage: HashMap::new(),
+                code_hash: info.code_hash,
             },
+        );
+        self.db_accounts.insert(address);
+
+        // 代码中常量存储槽和地址很可能马上被读取 提前一起获取
+        if has_code {
+            let code = &self.codes[&info.code_hash];
+            let storage: Vec<_> = predict_storage_keys(code)
+                .into_iter()
+                .map(|slot| (address, slot))
+                .collect();
+            let accounts = predict_accounts(code);
+            self.prefetch(&accounts, &storage);
+        }
+        Ok(true)
+    }
+
+    /// Ask the database to fetch `accounts` and `storage` slots together,
+    /// leaving out what is already cached. See [`Database::prefetch`].
+    pub fn prefetch(&mut self, accounts: &[[u8; 20]], storage: &[([u8; 20], [u8; 32])]) {
+        let accounts: Vec<_> = accounts
+            .iter()
+            .filter(|address| !self.accounts.contains_key(*address) && !self.loaded.contains(*address))
+            .copied()
+            .collect();
+        // 本地账户和已缓存的槽不会再读数据库
+        let storage: Vec<_> = storage
+            .iter()
+            .filter(|(address, slot)| match self.accounts.get(address) {
+                Some(account) => {
+                    self.db_accounts.contains(address) && !account.storage.contains_key(slot)
+                }
+                None => !self.loaded.contains(address),
+            })
+            .copied()
+            .collect();
+        if !accounts.is_empty() || !storage.is_empty() {
+            self.db.prefetch(&accounts, &storage);
+        }
+    }
+
+    /// Read what is not cached from `db` from now on, e.g. to reattach the
+    /// fork a deserialized state was taken from.
+    pub fn set_database(&mut self, db: impl Database + 'static) {
+        self.db = Box::new(db);
+    }
+
+    /// Put `account` at `address` as is. What the database holds for the
+    /// address, storage included, is no longer read.
+    pub fn replace_account(&mut self, address: [u8; 20], account: AccountState) {
+        self.put_account(address, Some(account), false);
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                              Journaled writes                              */
+    /* -------------------------------------------------------------------------- */
+    //以下修改在有快照时记入日志 直接修改accounts等字段不会被回滚
+
+    /// Put `account` at `address`, or remove it when `None`. With `from_db`
+    /// slots it does not cache are still read from the database.
+    pub(crate) fn put_account(&mut self, address: [u8; 20], account: Option<AccountState>, from_db: bool) {
+        let prev_from_db = self.db_accounts.contains(&address);
+        let loaded = self.loaded.contains(&address);
+        let prev = match account {
+            Some(account) => self.accounts.insert(address, account),
+            None => self.accounts.remove(&address),
+        };
+        self.loaded.insert(address);
+        match from_db {
+            true => self.db_accounts.insert(address),
+            false => self.db_accounts.remove(&address),
+        };
+        self.record(JournalEntry::AccountReplaced {
+            address,
+            prev,
+            from_db: prev_from_db,
+            loaded,
+        });
+    }
+
+    /// The account at `address` to change its balance, nonce or code hash.
+    /// Storage must be written with [`EvmState::sstore`] instead.
+    pub(crate) fn account_mut(&mut self, address: [u8; 20]) -> Option<&mut AccountState> {
+        let account = self.accounts.get(&address)?;
+        let entry = JournalEntry::AccountChanged {
+            address,
+            nonce: account.nonce,
+            balance: account.balance,
+            code_hash: account.code_hash,
+        };
+        self.record(entry);
+        self.accounts.get_mut(&address)
+    }
+
+    //账户不存在时返回false
+    fn set_storage(&mut self, address: [u8; 20], slot: [u8; 32], value: [u8; 32]) -> bool {
+        let Some(account) = self.accounts.get_mut(&address) else {
+            return false;
+        };
+        let prev = account.storage.insert(slot, value);
+        self.record(JournalEntry::StorageChanged { address, slot, prev });
+        true
+    }
+
+    pub fn push_log(&mut self, log: Log) {
+        self.record(JournalEntry::LogPushed(self.logs.len()));
+        self.logs.push(log);
+    }
+
+    //当前交易中创建的合约
+    pub fn mark_created(&mut self, address: [u8; 20]) {
+        if self.created_accounts.insert(address) {
+            self.record(JournalEntry::Created(address));
+        }
+    }
+
+    //交易成功结束时删除
+    pub fn mark_selfdestruct(&mut self, address: [u8; 20]) {
+        if self.selfdestructs.insert(address) {
+            self.record(JournalEntry::SelfDestructed(address));
+        }
+    }
+
+    /// Hash of block `number` according to the database.
+    pub fn block_hash(&mut self, number: u64) -> Result<[u8; 32], RunnerError> {
+        self.db.block_hash(number)
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                              Account lifecycle                             */
+    /* -------------------------------------------------------------------------- */
+    //账户是否存在于状态中
+    pub fn account_exists(&self, address: [u8; 20]) -> bool {
+        self.accounts.contains_key(&address)
+    }
+
+    /// EIP-161: an account is dead when it does not exist or is empty.
+    pub fn is_dead(&self, address: [u8; 20]) -> bool {
+        self.accounts
+            .get(&address)
+            .is_none_or(|account| account.is_empty())
+    }
+
+    //账户不存在时创建空账户 数据库读取失败时按不存在处理
+    pub fn init_account(&mut self, address: [u8; 20]) {
+        let _ = self.load_account(address);
+        self.touch(address);
+        if !self.accounts.contains_key(&address) {
+            self.put_account(address, Some(AccountState::default()), false);
         }
     }
 
+    //标记账户被触及 交易结束时空账户会被清理
+    pub fn touch(&mut self, address: [u8; 20]) {
+        if self.touched.insert(address) {
+            self.record(JournalEntry::Touched(address));
+        }
+    }
+
+    /// Move `value` from `from` to `to`.
+    ///
+    /// The balance is checked before anything changes. A missing sender
+    /// has zero balance and a missing recipient is created.
     pub fn transfer(
         &mut self,
         from: [u8; 20],
@@ -101,22 +443,16 @@ impl EvmState {
             return Err(RunnerError::StaticCallStateChanged);
         }
 
+        self.load_account(from)?;
+        self.load_account(to)?;
+
         let value_u256 = U256::from_big_endian(&value);
         // Check account
         let from_balance = U256::from_big_endian(
             &self
                 .accounts
                 .get(&from)
-                .ok_or(RunnerError::AccountNotFound)?
-                .balance,
-        );
-
-        let to_balance = U256::from_big_endian(
-            &self
-                .accounts
-                .get(&to)
-                .ok_or(RunnerError::AccountNotFound)?
-                .balance,
+                .map_or([0u8; 32], |account| account.balance),
         );
 
         // Check if the balance is sufficient
@@ -124,17 +460,19 @@ impl EvmState {
             return Err(RunnerError::InsufficientBalance);
         }
 
+        self.touch(from);
+        self.init_account(to);
+
         // Transfer the value
         let new_from_balance = from_balance - value_u256;
-        let new_to_balance = to_balance + value_u256;
-
-        // update the balance of the from_account and to_account
-        if let Some(from_account) = self.accounts.get_mut(&from) {
+        if let Some(from_account) = self.account_mut(from) {
             let mut result_bytes = [0u8; 32];
             new_from_balance.to_big_endian(&mut result_bytes);
             from_account.balance = result_bytes;
         }
-        if let Some(to_account) = self.accounts.get_mut(&to) {
+        // 读取最新余额 from和to可能是同一个账户
+        if let Some(to_account) = self.account_mut(to) {
+            let new_to_balance = U256::from_big_endian(&to_account.balance) + value_u256;
             let mut result_bytes = [0u8; 32];
             new_to_balance.to_big_endian(&mut result_bytes);
             to_account.balance = result_bytes;
@@ -143,58 +481,58 @@ impl EvmState {
         Ok(())
     }
 
-    // 从指定的slot读取存储值
-    pub fn sload(&mut self, account: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError> {
-        //本地账户状态读取
-        match self.accounts.get(&account) {
-            Some(account_state) => match account_state.storage.get(&slot) {
-                Some(value) => Ok(*value),
-                // 存储槽为空
-                None => Ok([0u8; 32]),
-            },
-            //不在则去链上获取
-            None => {
-                let provider = self.provider.as_ref();
-                if provider.is_none() {
-                    return Ok([0u8; 32]);
+    //增加余额 账户不存在时创建
+    pub fn add_balance(&mut self, address: [u8; 20], value: [u8; 32]) {
+        self.init_account(address);
+        if let Some(account) = self.account_mut(address) {
+            let new_balance = U256::from_big_endian(&account.balance)
+                .saturating_add(U256::from_big_endian(&value));
+            new_balance.to_big_endian(&mut account.balance);
+        }
+    }
+
+    //扣除余额 余额不足时不做修改
+    pub fn sub_balance(&mut self, address: [u8; 20], value: [u8; 32]) -> Result<(), RunnerError> {
+        self.load_account(address)?;
+        let value = U256::from_big_endian(&value);
+        match self.accounts.get(&address) {
+            Some(account) => {
+                let balance = U256::from_big_endian(&account.balance);
+                if balance < value {
+                    return Err(RunnerError::InsufficientBalance);
                 }
-                //更新本地状态
-                let contract_address = Address::from(account);
-                let future =
-                    provider
-                        .unwrap()
-                        .get_storage_at(contract_address, H256::from(&slot), None);
-
-                // Block on the future and get the result
-                let storage_result = tokio::runtime::Runtime::new()
-                    .expect("Could not create a Runtime")
-                    //获取远程节点上的存储数据
-                    .block_on(future);
-
-                match storage_result {
-                    Ok(storage) => {
-                        let storage_bytes = storage.to_fixed_bytes();
-
-                        // 如果获取到的存储值是全0，存储槽为空
-                        if storage_bytes == [0u8; 32] {
-                            Ok([0u8; 32]) // 存储槽为空
-                        } else {
-                            // 保存从远程获取的存储数据到本地账户状态
-                            if let Some(account_state) = self.accounts.get_mut(&account) {
-                                account_state.storage.insert(slot, storage_bytes);
-                            }
-
-                            Ok(storage_bytes) // 成功获取到非空存储槽值
-                        }
-                    }
-                    Err(_) => {
-                        // 链上数据获取失败
-                        Err(RunnerError::StorageRetrievalFailed)
-                    }
+                if let Some(account) = self.account_mut(address) {
+                    (balance - value).to_big_endian(&mut account.balance);
                 }
+                self.touch(address);
+                Ok(())
             }
+            None if value.is_zero() => Ok(()),
+            None => Err(RunnerError::InsufficientBalance),
         }
     }
+
+    // 从指定的slot读取存储值 缓存中没有时从数据库读取
+    pub fn sload(&mut self, account: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError> {
+        self.load_account(account)?;
+        if let Some(value) = self
+            .accounts
+            .get(&account)
+            .and_then(|account_state| account_state.storage.get(&slot))
+        {
+            return Ok(*value);
+        }
+        // 本地创建的账户没有数据库中的存储
+        if !self.db_accounts.contains(&account) {
+            return Ok([0u8; 32]);
+        }
+
+        let value = self.db.storage(account, slot)?;
+        if let Some(account_state) = self.accounts.get_mut(&account) {
+            account_state.storage.insert(slot, value);
+        }
+        Ok(value)
+    }
     // 更新存储值到指定账户的slot
     pub fn sstore(
         &mut self,
@@ -206,13 +544,12 @@ impl EvmState {
         if self.static_mode {
             return Err(RunnerError::StaticCallStateChanged);
         }
+        // 先读入原值 之后的读取不再访问数据库
+        self.sload(account, slot)?;
 
-        match self.accounts.get_mut(&account) {
-            Some(account_state) => {
-                account_state.storage.insert(slot, value);
-                Ok(())
-            }
-            None => Err(RunnerError::AccountNotFound),
+        match self.set_storage(account, slot, value) {
+            true => Ok(()),
+            false => Err(RunnerError::AccountNotFound),
         }
     }
 
@@ -229,6 +566,11 @@ impl EvmState {
         };
         code
     }
+    /// Delegation target of `address` if its code is a designator.
+    pub fn get_delegation(&self, address: [u8; 20]) -> Option<[u8; 20]> {
+        self.get_code_at(address).and_then(|code| delegated_address(code))
+    }
+
     //根据hash=>code的Vec<u8>
     fn get_code(&self, code_hash: [u8; 32]) -> Option<&Vec<u8>> {
         self.codes.get(&code_hash)
@@ -236,9 +578,10 @@ impl EvmState {
 
     //将合约代码存储在特定的账户地址
     pub fn put_code_at(&mut self, address: [u8; 20], code: Vec<u8>) -> Result<(), RunnerError> {
+        self.load_account(address)?;
         let code_hash = self.put_code(code)?;
 
-        match self.accounts.get_mut(&address) {
+        match self.account_mut(address) {
             Some(account_state) => {
                 account_state.code_hash = code_hash.to_owned();
                 Ok(())
@@ -262,6 +605,38 @@ impl EvmState {
         Ok(code_hash)
     }
 
+    /// Apply the deferred end-of-transaction work.
+    ///
+    /// Accounts that executed SELFDESTRUCT are only removed here, and only
+    /// if the transaction succeeded. From Spurious Dragon on, touched
+    /// accounts that ended up empty are removed as well (EIP-161).
+    pub fn finalize_transaction(&mut self, success: bool, spec_id: SpecId) {
+        let created_accounts = std::mem::take(&mut self.created_accounts);
+        let selfdestructs = std::mem::take(&mut self.selfdestructs);
+        let touched = std::mem::take(&mut self.touched);
+
+        let mut removed = Vec::new();
+        if success {
+            removed.extend(selfdestructs.iter().copied());
+        }
+        if success && spec_id.is_enabled_in(SpecId::SpuriousDragon) {
+            for address in &touched {
+                if self.accounts.get(address).is_some_and(|account| account.is_empty()) {
+                    removed.push(*address);
+                }
+            }
+        }
+        // 删除的账户不再从数据库恢复
+        for address in removed {
+            self.put_account(address, None, false);
+        }
+        self.record(JournalEntry::TransactionCleared {
+            created_accounts,
+            selfdestructs,
+            touched,
+        });
+    }
+
     //打印EVM当前状态
     pub fn debug_state(&mut self) {
         let border_line =
@@ -301,7 +676,7 @@ impl EvmState {
 
             // Print Code Hash
             let code_hash = debug::to_hex_string(account_state.code_hash);
-            let code_status = if account_state.code_hash == [0u8; 32] {
+            let code_status = if !account_state.has_code() {
                 "Empty code".red().to_string()
             } else {
                 code_hash.yellow().to_string()
@@ -322,8 +697,7 @@ impl EvmState {
             }
 
             // If code exists, print the code
-            if account_state.code_hash != [0u8; 32] {
-                let code = self.get_code_at(address.to_owned()).unwrap();
+            if let Some(code) = self.get_code_at(address.to_owned()) {
                 let code_hex = debug::vec_to_hex_string(code.to_owned());
                 println!("║ {}: {:<100} ║", "Code".magenta(), code_hex);
             }
@@ -336,4 +710,58 @@ impl EvmState {
         println!("{}", footer_line.green());
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const BOB: [u8; 20] = [0xb0; 20];
+    const CAROL: [u8; 20] = [0xca; 20];
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    // BOB已存在但为空账户 CAROL不存在
+    fn state() -> EvmState {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: value(9),
+                ..Default::default()
+            },
+        );
+        db.insert_account(BOB, AccountState::default());
+        EvmState::with_database(db)
+    }
+
+    fn touch_empty(spec_id: SpecId) -> EvmState {
+        let mut state = state();
+        state.transfer(ALICE, BOB, value(0)).unwrap();
+        state.transfer(ALICE, CAROL, value(0)).unwrap();
+        state.finalize_transaction(true, spec_id);
+        state
+    }
+
+    #[test]
+    fn touched_empty_account_is_deleted() {
+        let state = touch_empty(SpecId::SpuriousDragon);
+        assert!(!state.account_exists(BOB));
+        assert!(!state.account_exists(CAROL));
+        assert!(state.account_exists(ALICE));
+    }
+
+    #[test]
+    fn touched_empty_account_is_kept_before_spurious_dragon() {
+        let state = touch_empty(SpecId::Tangerine);
+        assert!(state.account_exists(BOB));
+        // 零值转账也会创建接收账户
+        assert!(state.account_exists(CAROL));
+        assert!(state.accounts[&CAROL].is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+/*
+EIP-7702 授权列表
+每个授权由authority签名 内容为 keccak256(0x05 || rlp([chain_id, address, nonce]))
+合法的授权把 0xef0100 || address 写入authority的代码并递增其nonce
+不合法的授权直接跳过 不影响交易本身
+*/
+use alloy_rlp::{Encodable, Header};
+use ethers::types::U256;
+use ethers::utils::keccak256;
+
+use crate::evm_core::context::evm_context::EvmContext;
+use crate::evm_core::gas::constant::{PER_AUTH_BASE_COST, PER_EMPTY_ACCOUNT_COST};
+use crate::evm_core::storage::{delegated_address, delegation_designator, EvmState, KECCAK_EMPTY};
+use crate::evm_core::utils::error::RunnerError;
+
+use super::decode::recover_signer;
+use super::types::Authorization;
+
+//授权签名的类型前缀
+const SET_CODE_MAGIC: u8 = 0x05;
+
+impl Authorization {
+    /// Hash signed by the authority.
+    pub fn signing_hash(&self) -> [u8; 32] {
+        let mut payload = Vec::new();
+        self.chain_id.encode(&mut payload);
+        self.address.encode(&mut payload);
+        self.nonce.encode(&mut payload);
+
+        let mut preimage = vec![SET_CODE_MAGIC];
+        Header {
+            list: true,
+            payload_length: payload.len(),
+        }
+        .encode(&mut preimage);
+        preimage.extend(payload);
+        keccak256(preimage)
+    }
+
+    /// Recover the account that signed this authorization.
+    pub fn authority(&self) -> Result<[u8; 20], RunnerError> {
+        if self.y_parity > 1 {
+            return Err(RunnerError::InvalidSignature);
+        }
+        recover_signer(self.signing_hash(), self.y_parity as u64, self.r, self.s)
+    }
+}
+
+/// Apply `authorization_list` in order, skipping invalid entries.
+///
+/// Returns the gas refund owed for authorities that already existed.
+pub fn apply_authorizations(
+    state: &mut EvmState,
+    evm_context: &EvmContext,
+    authorization_list: &[Authorization],
+) -> u64 {
+    let chain_id = evm_context.chain_id.map(|chain_id| U256::from_big_endian(&chain_id));
+    let mut refund = 0;
+    for authorization in authorization_list {
+        // chain_id为0的授权在任意链上有效
+        if authorization.chain_id != 0
+            && chain_id.is_some_and(|chain_id| chain_id != U256::from(authorization.chain_id))
+        {
+            continue;
+        }
+        if authorization.nonce == u64::MAX {
+            continue;
+        }
+        let Ok(authority) = authorization.authority() else {
+            continue;
+        };
+
+        // authority只能是EOA或已委托的账户
+        if state.load_account(authority).is_err() {
+            continue;
+        }
+        if let Some(code) = state.get_code_at(authority) {
+            if delegated_address(code).is_none() {
+                continue;
+            }
+        }
+        let account = state.accounts.get(&authority);
+        if account.map_or(0, |account| account.nonce) != authorization.nonce {
+            continue;
+        }
+        if account.is_some_and(|account| !account.is_empty()) {
+            refund += PER_EMPTY_ACCOUNT_COST - PER_AUTH_BASE_COST;
+        }
+
+        state.init_account(authority);
+        // 委托给零地址表示清除委托
+        if authorization.address == [0u8; 20] {
+            if let Some(account) = state.account_mut(authority) {
+                account.code_hash = KECCAK_EMPTY;
+            }
+        } else {
+            // 委托代码非空 put_code_at不会失败
+            let _ = state.put_code_at(authority, delegation_designator(authorization.address));
+        }
+        if let Some(account) = state.account_mut(authority) {
+            account.nonce += 1;
+        }
+        state.touch(authority);
+    }
+    refund
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::context::spec::SpecId;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::AccountState;
+    use crate::evm_core::transaction::transact::transact;
+    use crate::evm_core::transaction::types::{Transaction, TxLegacy};
+    use crate::evm_core::utils::byte_operate::u64_to_u256_array;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::H256;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const TARGET: [u8; 20] = [0x70; 20];
+
+    fn wallet(byte: u8) -> LocalWallet {
+        LocalWallet::from_bytes(&[byte; 32]).unwrap()
+    }
+
+    fn sign(wallet: &LocalWallet, chain_id: u64, nonce: u64) -> Authorization {
+        let mut authorization = Authorization {
+            chain_id,
+            address: TARGET,
+            nonce,
+            ..Default::default()
+        };
+        let signature = wallet.sign_hash(H256::from(authorization.signing_hash())).unwrap();
+        authorization.y_parity = (signature.v - 27) as u8;
+        signature.r.to_big_endian(&mut authorization.r);
+        signature.s.to_big_endian(&mut authorization.s);
+        authorization
+    }
+
+    fn context() -> EvmContext {
+        EvmContext {
+            chain_id: Some(u64_to_u256_array(1)),
+            spec_id: SpecId::Prague,
+            ..Default::default()
+        }
+    }
+
+    fn delegated_to(state: &EvmState, authority: [u8; 20]) -> Option<[u8; 20]> {
+        state.get_code_at(authority).and_then(|code| delegated_address(code))
+    }
+
+    #[test]
+    fn recovers_authority() {
+        let wallet = wallet(1);
+        let authorization = sign(&wallet, 1, 0);
+        assert_eq!(authorization.authority(), Ok(wallet.address().0));
+    }
+
+    #[test]
+    fn checks_chain_id() {
+        let mut state = EvmState::default();
+        let other_chain = wallet(1);
+        let any_chain = wallet(2);
+        let authorizations = [sign(&other_chain, 5, 0), sign(&any_chain, 0, 0)];
+        apply_authorizations(&mut state, &context(), &authorizations);
+
+        // 其他链的授权被跳过 chain_id为0的授权有效
+        assert_eq!(delegated_to(&state, other_chain.address().0), None);
+        assert_eq!(delegated_to(&state, any_chain.address().0), Some(TARGET));
+        assert_eq!(state.accounts[&any_chain.address().0].nonce, 1);
+    }
+
+    #[test]
+    fn checks_nonce() {
+        let mut state = EvmState::default();
+        let wallet = wallet(1);
+        let authority = wallet.address().0;
+        apply_authorizations(&mut state, &context(), &[sign(&wallet, 1, 1)]);
+        assert_eq!(delegated_to(&state, authority), None);
+
+        // 同一列表中后一个授权看到前一个授权递增后的nonce
+        let authorizations = [sign(&wallet, 1, 0), sign(&wallet, 1, 0), sign(&wallet, 1, 1)];
+        apply_authorizations(&mut state, &context(), &authorizations);
+        assert_eq!(delegated_to(&state, authority), Some(TARGET));
+        assert_eq!(state.accounts[&authority].nonce, 2);
+    }
+
+    #[test]
+    fn refunds_existing_authorities() {
+        let new_wallet = wallet(1);
+        let existing_wallet = wallet(2);
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            existing_wallet.address().0,
+            AccountState {
+                balance: u64_to_u256_array(1),
+                ..Default::default()
+            },
+        );
+        let mut state = EvmState::with_database(db);
+
+        let refund = apply_authorizations(&mut state, &context(), &[sign(&new_wallet, 1, 0)]);
+        assert_eq!(refund, 0);
+        let refund = apply_authorizations(&mut state, &context(), &[sign(&existing_wallet, 1, 0)]);
+        assert_eq!(refund, PER_EMPTY_ACCOUNT_COST - PER_AUTH_BASE_COST);
+    }
+
+    #[test]
+    fn delegation_follows_one_hop() {
+        const FIRST: [u8; 20] = [0xf1; 20];
+        const SECOND: [u8; 20] = [0xf2; 20];
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: u64_to_u256_array(1_000_000),
+                ..Default::default()
+            },
+        );
+        // SSTORE(0, 1)
+        db.insert_code(TARGET, vec![0x60, 0x01, 0x60, 0x00, 0x55]);
+        db.insert_code(SECOND, delegation_designator(TARGET));
+        db.insert_code(FIRST, delegation_designator(SECOND));
+        let mut state = EvmState::with_database(db);
+
+        let call = |state: &mut EvmState, to: [u8; 20], nonce: u64| {
+            let tx = Transaction::Legacy(TxLegacy {
+                from: ALICE,
+                nonce,
+                gas_limit: 100_000,
+                to: Some(to),
+                ..Default::default()
+            });
+            transact(state, &context(), tx).unwrap()
+        };
+
+        // 目标代码在委托账户的上下文中执行
+        assert!(call(&mut state, SECOND, 0).success);
+        assert_eq!(state.sload(SECOND, [0u8; 32]).unwrap(), u64_to_u256_array(1));
+        assert_eq!(state.sload(TARGET, [0u8; 32]).unwrap(), [0u8; 32]);
+
+        // 只跟随一次 第二个委托标记以0xef开头 执行失败
+        let result = call(&mut state, FIRST, 1);
+        assert!(!result.success);
+        assert_eq!(state.sload(FIRST, [0u8; 32]).unwrap(), [0u8; 32]);
+    }
+}
@@ -0,0 +1,485 @@
+/*
+签名交易解码
+legacy交易为rlp列表 类型化交易为 type || rlp列表 (EIP-2718)
+签名覆盖的是去掉 v r s 后的字段 发送者由签名恢复
+*/
+use alloy_rlp::{Decodable, Encodable, Header};
+use ethers::types::{RecoveryMessage, Signature, H256, U256};
+use ethers::utils::keccak256;
+
+use crate::evm_core::context::evm_context::EvmContext;
+use crate::evm_core::storage::EvmState;
+use crate::evm_core::utils::byte_operate::pad_left;
+use crate::evm_core::utils::error::RunnerError;
+
+use super::transact::{transact, TransactionResult};
+use super::types::{
+    AccessListItem, Authorization, Transaction, TxEip1559, TxEip2930, TxEip4844, TxEip7702,
+    TxLegacy,
+};
+
+/* -------------------------------------------------------------------------- */
+/*                                   Decode                                   */
+/* -------------------------------------------------------------------------- */
+/// Decode a raw signed transaction and recover its sender.
+///
+/// Accepts legacy RLP and typed envelopes 0x01 to 0x04. Blob transactions may
+/// be in either the canonical or the network (with sidecar) form.
+pub fn decode_raw_transaction(raw: &[u8]) -> Result<Transaction, RunnerError> {
+    let (tx_type, mut buf) = match raw.first() {
+        None => return Err(decode_error("empty transaction")),
+        // 0xc0以上为rlp列表 即legacy交易
+        Some(&first) if first >= 0xc0 => (0x00, raw),
+        Some(&first) if first <= 0x04 => (first, &raw[1..]),
+        Some(&first) => return Err(RunnerError::TransactionTypeNotSupported(first)),
+    };
+
+    // 网络格式的blob交易为 [tx_payload, blobs, commitments, proofs]
+    if tx_type == 0x03 {
+        let mut rest = buf;
+        let outer = list_payload(&mut rest)?;
+        // 外层列表的首个元素仍是列表 说明带有sidecar 只取交易部分
+        if outer.first().is_some_and(|&byte| byte >= 0xc0) {
+            buf = raw_list(outer)?;
+        }
+    }
+
+    let payload = list_payload(&mut buf)?;
+    if !buf.is_empty() {
+        return Err(decode_error("trailing bytes after transaction"));
+    }
+
+    let mut fields = payload;
+    let mut tx = match tx_type {
+        0x00 => Transaction::Legacy(decode_legacy(&mut fields)?),
+        0x01 => Transaction::Eip2930(decode_eip2930(&mut fields)?),
+        0x02 => Transaction::Eip1559(decode_eip1559(&mut fields)?),
+        0x03 => Transaction::Eip4844(decode_eip4844(&mut fields)?),
+        _ => Transaction::Eip7702(decode_eip7702(&mut fields)?),
+    };
+    // 签名前的字段
+    let unsigned = &payload[..payload.len() - fields.len()];
+
+    let v = u64::decode(&mut fields).map_err(rlp_error)?;
+    let r = decode_word(&mut fields)?;
+    let s = decode_word(&mut fields)?;
+    if !fields.is_empty() {
+        return Err(decode_error("unexpected fields after signature"));
+    }
+
+    let signing_hash = match &mut tx {
+        Transaction::Legacy(legacy) => {
+            // EIP-155: v = chain_id * 2 + 35 + y_parity
+            legacy.chain_id = match v {
+                27 | 28 => None,
+                v if v >= 35 => Some((v - 35) / 2),
+                _ => return Err(RunnerError::InvalidSignature),
+            };
+            let mut body = unsigned.to_vec();
+            if let Some(chain_id) = legacy.chain_id {
+                chain_id.encode(&mut body);
+                0u8.encode(&mut body);
+                0u8.encode(&mut body);
+            }
+            keccak256(wrap_list(&body))
+        }
+        _ => {
+            if v > 1 {
+                return Err(RunnerError::InvalidSignature);
+            }
+            let mut preimage = vec![tx_type];
+            preimage.extend(wrap_list(unsigned));
+            keccak256(preimage)
+        }
+    };
+
+    let from = recover_signer(signing_hash, v, r, s)?;
+    tx.set_caller(from);
+    Ok(tx)
+}
+
+/// Decode a `0x`-prefixed or bare hex string, as found in RPC dumps and bug reports.
+pub fn decode_raw_transaction_hex(raw: &str) -> Result<Transaction, RunnerError> {
+    let raw = raw.strip_prefix("0x").unwrap_or(raw);
+    let bytes = hex::decode(raw).map_err(|e| decode_error(&e.to_string()))?;
+    decode_raw_transaction(&bytes)
+}
+
+/// Decode a raw signed transaction and execute it against `state`.
+pub fn transact_raw(
+    state: &mut EvmState,
+    evm_context: &EvmContext,
+    raw: &[u8],
+) -> Result<TransactionResult, RunnerError> {
+    let tx = decode_raw_transaction(raw)?;
+    transact(state, evm_context, tx)
+}
+
+/* -------------------------------------------------------------------------- */
+/*                              Envelope payloads                             */
+/* -------------------------------------------------------------------------- */
+// [nonce, gasPrice, gasLimit, to, value, data, v, r, s]
+fn decode_legacy(buf: &mut &[u8]) -> Result<TxLegacy, RunnerError> {
+    Ok(TxLegacy {
+        from: [0u8; 20],
+        chain_id: None,
+        nonce: u64::decode(buf).map_err(rlp_error)?,
+        gas_price: u128::decode(buf).map_err(rlp_error)?,
+        gas_limit: u64::decode(buf).map_err(rlp_error)?,
+        to: decode_to(buf)?,
+        value: decode_word(buf)?,
+        input: decode_bytes(buf)?,
+    })
+}
+
+// [chainId, nonce, gasPrice, gasLimit, to, value, data, accessList, ...]
+fn decode_eip2930(buf: &mut &[u8]) -> Result<TxEip2930, RunnerError> {
+    Ok(TxEip2930 {
+        from: [0u8; 20],
+        chain_id: u64::decode(buf).map_err(rlp_error)?,
+        nonce: u64::decode(buf).map_err(rlp_error)?,
+        gas_price: u128::decode(buf).map_err(rlp_error)?,
+        gas_limit: u64::decode(buf).map_err(rlp_error)?,
+        to: decode_to(buf)?,
+        value: decode_word(buf)?,
+        input: decode_bytes(buf)?,
+        access_list: decode_access_list(buf)?,
+    })
+}
+
+// [chainId, nonce, maxPriorityFee, maxFee, gasLimit, to, value, data, accessList, ...]
+fn decode_eip1559(buf: &mut &[u8]) -> Result<TxEip1559, RunnerError> {
+    Ok(TxEip1559 {
+        from: [0u8; 20],
+        chain_id: u64::decode(buf).map_err(rlp_error)?,
+        nonce: u64::decode(buf).map_err(rlp_error)?,
+        max_priority_fee_per_gas: u128::decode(buf).map_err(rlp_error)?,
+        max_fee_per_gas: u128::decode(buf).map_err(rlp_error)?,
+        gas_limit: u64::decode(buf).map_err(rlp_error)?,
+        to: decode_to(buf)?,
+        value: decode_word(buf)?,
+        input: decode_bytes(buf)?,
+        access_list: decode_access_list(buf)?,
+    })
+}
+
+// eip1559字段 + [maxFeePerBlobGas, blobVersionedHashes] to不可为空
+fn decode_eip4844(buf: &mut &[u8]) -> Result<TxEip4844, RunnerError> {
+    Ok(TxEip4844 {
+        from: [0u8; 20],
+        chain_id: u64::decode(buf).map_err(rlp_error)?,
+        nonce: u64::decode(buf).map_err(rlp_error)?,
+        max_priority_fee_per_gas: u128::decode(buf).map_err(rlp_error)?,
+        max_fee_per_gas: u128::decode(buf).map_err(rlp_error)?,
+        gas_limit: u64::decode(buf).map_err(rlp_error)?,
+        to: <[u8; 20]>::decode(buf).map_err(rlp_error)?,
+        value: decode_word(buf)?,
+        input: decode_bytes(buf)?,
+        access_list: decode_access_list(buf)?,
+        max_fee_per_blob_gas: u128::decode(buf).map_err(rlp_error)?,
+        blob_versioned_hashes: Vec::<[u8; 32]>::decode(buf).map_err(rlp_error)?,
+    })
+}
+
+// eip1559字段 + [authorizationList] to不可为空
+fn decode_eip7702(buf: &mut &[u8]) -> Result<TxEip7702, RunnerError> {
+    Ok(TxEip7702 {
+        from: [0u8; 20],
+        chain_id: u64::decode(buf).map_err(rlp_error)?,
+        nonce: u64::decode(buf).map_err(rlp_error)?,
+        max_priority_fee_per_gas: u128::decode(buf).map_err(rlp_error)?,
+        max_fee_per_gas: u128::decode(buf).map_err(rlp_error)?,
+        gas_limit: u64::decode(buf).map_err(rlp_error)?,
+        to: <[u8; 20]>::decode(buf).map_err(rlp_error)?,
+        value: decode_word(buf)?,
+        input: decode_bytes(buf)?,
+        access_list: decode_access_list(buf)?,
+        authorization_list: decode_authorization_list(buf)?,
+    })
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                   Fields                                   */
+/* -------------------------------------------------------------------------- */
+// [[address, [storageKey, ...]], ...]
+fn decode_access_list(buf: &mut &[u8]) -> Result<Vec<AccessListItem>, RunnerError> {
+    let mut items = list_payload(buf)?;
+    let mut access_list = Vec::new();
+    while !items.is_empty() {
+        let mut item = list_payload(&mut items)?;
+        access_list.push(AccessListItem {
+            address: <[u8; 20]>::decode(&mut item).map_err(rlp_error)?,
+            storage_keys: Vec::<[u8; 32]>::decode(&mut item).map_err(rlp_error)?,
+        });
+    }
+    Ok(access_list)
+}
+
+// [[chainId, address, nonce, yParity, r, s], ...]
+fn decode_authorization_list(buf: &mut &[u8]) -> Result<Vec<Authorization>, RunnerError> {
+    let mut items = list_payload(buf)?;
+    let mut authorization_list = Vec::new();
+    while !items.is_empty() {
+        let mut item = list_payload(&mut items)?;
+        authorization_list.push(Authorization {
+            chain_id: u64::decode(&mut item).map_err(rlp_error)?,
+            address: <[u8; 20]>::decode(&mut item).map_err(rlp_error)?,
+            nonce: u64::decode(&mut item).map_err(rlp_error)?,
+            y_parity: u8::decode(&mut item).map_err(rlp_error)?,
+            r: decode_word(&mut item)?,
+            s: decode_word(&mut item)?,
+        });
+    }
+    Ok(authorization_list)
+}
+
+//空字符串表示创建合约
+fn decode_to(buf: &mut &[u8]) -> Result<Option<[u8; 20]>, RunnerError> {
+    let bytes = Header::decode_bytes(buf, false).map_err(rlp_error)?;
+    match bytes.len() {
+        0 => Ok(None),
+        20 => Ok(Some(bytes.try_into().unwrap())),
+        _ => Err(decode_error("invalid to address length")),
+    }
+}
+
+//最多32字节的大端整数 左侧补0
+fn decode_word(buf: &mut &[u8]) -> Result<[u8; 32], RunnerError> {
+    let bytes = Header::decode_bytes(buf, false).map_err(rlp_error)?;
+    if bytes.len() > 32 {
+        return Err(decode_error("integer longer than 32 bytes"));
+    }
+    Ok(pad_left(bytes))
+}
+
+fn decode_bytes(buf: &mut &[u8]) -> Result<Vec<u8>, RunnerError> {
+    Ok(Header::decode_bytes(buf, false).map_err(rlp_error)?.to_vec())
+}
+
+//读取一个rlp列表 返回其内容
+fn list_payload<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], RunnerError> {
+    Header::decode_bytes(buf, true).map_err(rlp_error)
+}
+
+//取出buf开头的完整rlp列表(包含头部)
+fn raw_list(buf: &[u8]) -> Result<&[u8], RunnerError> {
+    let mut rest = buf;
+    list_payload(&mut rest)?;
+    Ok(&buf[..buf.len() - rest.len()])
+}
+
+fn wrap_list(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 9);
+    Header {
+        list: true,
+        payload_length: payload.len(),
+    }
+    .encode(&mut out);
+    out.extend_from_slice(payload);
+    out
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Signature                                 */
+/* -------------------------------------------------------------------------- */
+//secp256k1n / 2 更大的s值不被接受 (EIP-2)
+const SECP256K1N_HALF: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+pub(super) fn recover_signer(
+    signing_hash: [u8; 32],
+    v: u64,
+    r: [u8; 32],
+    s: [u8; 32],
+) -> Result<[u8; 20], RunnerError> {
+    if U256::from_big_endian(&s) > U256::from(SECP256K1N_HALF) {
+        return Err(RunnerError::InvalidSignature);
+    }
+    let signature = Signature {
+        r: U256::from_big_endian(&r),
+        s: U256::from_big_endian(&s),
+        v,
+    };
+    signature
+        .recover(RecoveryMessage::Hash(H256::from(signing_hash)))
+        .map(|address| address.0)
+        .map_err(|_| RunnerError::InvalidSignature)
+}
+
+fn rlp_error(error: alloy_rlp::Error) -> RunnerError {
+    RunnerError::TransactionDecodeFailed(error.to_string())
+}
+
+fn decode_error(message: &str) -> RunnerError {
+    RunnerError::TransactionDecodeFailed(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(hex: &str) -> [u8; 20] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    // ethers自己的解码器恢复的发送者 作为对照
+    fn ethers_sender(raw: &[u8]) -> [u8; 20] {
+        let tx: ethers::types::Transaction = ethers::utils::rlp::decode(raw).unwrap();
+        tx.recover_from().unwrap().0
+    }
+
+    #[test]
+    fn eip155_example() {
+        // EIP-155中的例子 私钥为0x4646...46
+        let raw = hex::decode(
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+             8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f\
+             761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        )
+        .unwrap();
+        let tx = decode_raw_transaction(&raw).unwrap();
+        let Transaction::Legacy(legacy) = &tx else {
+            panic!("expected a legacy transaction");
+        };
+        assert_eq!(legacy.chain_id, Some(1));
+        assert_eq!(legacy.nonce, 9);
+        assert_eq!(legacy.gas_price, 20_000_000_000);
+        assert_eq!(legacy.gas_limit, 21000);
+        assert_eq!(legacy.to, Some([0x35u8; 20]));
+        assert_eq!(U256::from_big_endian(&legacy.value), U256::exp10(18));
+        assert_eq!(tx.caller(), address("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"));
+    }
+
+    #[test]
+    fn mainnet_legacy_transactions() {
+        // USDT transfer 0x929ff27a...
+        let raw = hex::decode(
+            "f8aa808512ec276caf83010e2b94dac17f958d2ee523a2206206994597c13d831ec780b844a9059cbb\
+             000000000000000000000000fdae129ecc2c27d166a3131098bc05d143fa258e0000000000000000000000\
+             000000000000000000000000000000000002faf08025a0c81e70f9e49e0d3b854720143e86d172fecc9e76\
+             ef8a8666f2fdc017017c5141a01dd3410180f6a6ca3e25ad3058789cd0df3321ed76b5b4dbe0a2bb2dc28a\
+             e274",
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(keccak256(&raw)),
+            "929ff27a5c7833953df23103c4eb55ebdfb698678139d751c51932163877fada"
+        );
+        let tx = decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx.chain_id(), Some(1));
+        assert_eq!(tx.to(), Some(address("dac17f958d2ee523a2206206994597c13d831ec7")));
+        assert_eq!(tx.input()[..4], [0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(tx.caller(), address("c26ad91f4e7a0cad84c4b9315f420ca9217e315d"));
+
+        let raw = hex::decode(
+            "f865028504a817c80083015f9094dca8ce283150ab773bcbeb8d38289bdb5661de1e808025a019f2694e\
+             b9113656dbea0b925e2e7ceb43df83e601c4116aee9c0dd99130be88a073e5764b324a4f7679d890a198\
+             ba658ba1c8cd36983ff9797e10b1b89dbb448e",
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(keccak256(&raw)),
+            "c3c5f700243de37ae986082fd2af88d2a7c2752a0c0f7b9d6ac47c729d45e067"
+        );
+        let tx = decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx.caller(), address("fdcedc3bfca10ecb0890337fbdd1977aba84807a"));
+    }
+
+    #[test]
+    fn eip1559_transaction() {
+        let raw = hex::decode(
+            "02f874018201bb8405f5e10085096a1d45b782520894d696a5c568160bbbf5a1356f8ac56ee81a190588\
+             871550f7dca7000080c080a07df2299b0181d6d5b817795a7d2eff5897d0d3914ff5f602e17d5b75d32e\
+             c25fa051833973e8a8c222e682d2dcea02ad7bf3ec5bc3a86bfbcdbbaa3b853e52ad08",
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(keccak256(&raw)),
+            "938913ef1df8cd17e0893a85586ade463014559fb1bd2d536ac282f3b1bdea53"
+        );
+        let tx = decode_raw_transaction(&raw).unwrap();
+        let Transaction::Eip1559(eip1559) = &tx else {
+            panic!("expected an EIP-1559 transaction");
+        };
+        assert_eq!(eip1559.chain_id, 1);
+        assert_eq!(eip1559.nonce, 0x1bb);
+        assert_eq!(eip1559.max_priority_fee_per_gas, 100_000_000);
+        assert_eq!(eip1559.max_fee_per_gas, 0x096a1d45b7);
+        assert_eq!(eip1559.gas_limit, 21000);
+        assert!(eip1559.access_list.is_empty());
+        assert_eq!(tx.caller(), ethers_sender(&raw));
+    }
+
+    #[test]
+    fn eip2930_transaction() {
+        let raw = hex::decode(
+            "01f8ee0182034c853d9f1b88158307a120940087bb802d9c0e343f00510000729031ce00bf2780b8841e\
+             1326a300000000000000000000000088e6a0c2ddd26feeb64f039a2c41296fcb3f5640000000000000000\
+             0000000000000000000000000000000000000001d3b3e7300000000000000000000000000000000000000\
+             00000000000596b93e536967400000000000000000000000000000000000000000000000000000000000\
+             00000001c001a0bbfd754ed51b34d0a8577f69b4c42ce6b47fee6ecf49114bb135e7e8eadbb336a04336\
+             92134eb7e7686e9aefafa9f69c601aa977c00cc85c827782f5fb1f1cff0f",
+        )
+        .unwrap();
+        let tx = decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx.tx_type(), 0x01);
+        assert_eq!(tx.chain_id(), Some(1));
+        assert_eq!(tx.nonce(), 0x034c);
+        assert_eq!(tx.caller(), ethers_sender(&raw));
+    }
+
+    #[test]
+    fn hex_input() {
+        let raw = "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+        assert_eq!(
+            decode_raw_transaction_hex(raw).unwrap().caller(),
+            address("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f")
+        );
+    }
+
+    #[test]
+    fn malformed_input() {
+        assert!(decode_raw_transaction(&[]).is_err());
+        assert_eq!(
+            decode_raw_transaction(&[0x05, 0xc0]),
+            Err(RunnerError::TransactionTypeNotSupported(0x05))
+        );
+
+        let mut raw = hex::decode(
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+             8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f\
+             761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        )
+        .unwrap();
+        raw.push(0x00);
+        assert!(decode_raw_transaction(&raw).is_err());
+
+        // v既不是27/28也不符合EIP-155
+        raw.pop();
+        raw[43] = 0x01;
+        assert_eq!(decode_raw_transaction(&raw), Err(RunnerError::InvalidSignature));
+    }
+
+    #[test]
+    fn high_s_is_rejected() {
+        let mut raw = hex::decode(
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+             8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f\
+             761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        )
+        .unwrap();
+        // 同一签名的另一种形式 (r, n - s) 并翻转奇偶位
+        let n = U256::from_str_radix(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            16,
+        )
+        .unwrap();
+        let s_offset = raw.len() - 32;
+        let high_s = n - U256::from_big_endian(&raw[s_offset..]);
+        high_s.to_big_endian(&mut raw[s_offset..]);
+        raw[43] = 0x26;
+        assert_eq!(decode_raw_transaction(&raw), Err(RunnerError::InvalidSignature));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod types;
+
+pub mod transact;
+
+pub mod decode;
+
+pub mod authorization;
@@ -0,0 +1,472 @@
+use ethers::types::{U256, U512};
+use revm_primitives::Address;
+
+use crate::evm_core::context::evm_context::EvmContext;
+use crate::evm_core::context::spec::SpecId;
+use crate::evm_core::execute::Execute;
+use crate::evm_core::gas::blob::max_blob_gas_per_block;
+use crate::evm_core::gas::constant::{
+    ACCESS_LIST_ADDRESS, ACCESS_LIST_STORAGE_KEY, INITCODE_WORD, PER_EMPTY_ACCOUNT_COST, TX_BASE,
+    TX_CREATE, TX_DATA_NON_ZERO, TX_DATA_NON_ZERO_FRONTIER, TX_DATA_ZERO,
+    VERSIONED_HASH_VERSION_KZG,
+};
+use crate::evm_core::log::Log;
+use crate::evm_core::storage::{delegated_address, EvmState};
+use crate::evm_core::utils::error::RunnerError;
+
+use super::authorization::apply_authorizations;
+use super::types::Transaction;
+
+//未指定区块gas上限时使用30M
+const DEFAULT_BLOCK_GAS_LIMIT: u64 = 30_000_000;
+
+/// Outcome of an executed transaction, shaped like a receipt.
+#[derive(Debug)]
+pub struct TransactionResult {
+    pub success: bool,
+    /// Gas charged. Opcodes are not metered, so this is the intrinsic gas,
+    /// or the whole limit after an exceptional halt.
+    pub gas_used: u64,
+    /// Whether `gas_used` is what a real chain would charge: no code ran, or
+    /// the transaction halted and paid its whole limit.
+    pub gas_exact: bool,
+    pub effective_gas_price: u128,
+    /// RETURN data of a call, or revert data of a failed call or create.
+    pub output: Vec<u8>,
+    pub logs: Vec<Log>,
+    pub contract_address: Option<[u8; 20]>,
+    /// Blob gas of a type 0x03 transaction, paid in full whatever the outcome.
+    pub blob_gas_used: u64,
+    pub blob_gas_price: u128,
+    /// Why execution failed, if it did.
+    pub error: Option<RunnerError>,
+}
+
+/// Gas charged before any code runs: base cost, calldata, create, access list
+/// and authorizations.
+pub fn intrinsic_gas(tx: &Transaction, spec_id: SpecId) -> u64 {
+    let mut gas = TX_BASE;
+
+    let non_zero_cost = if spec_id.is_enabled_in(SpecId::Istanbul) {
+        TX_DATA_NON_ZERO
+    } else {
+        TX_DATA_NON_ZERO_FRONTIER
+    };
+    for byte in tx.input() {
+        gas += if *byte == 0 { TX_DATA_ZERO } else { non_zero_cost };
+    }
+
+    if tx.to().is_none() {
+        if spec_id.is_enabled_in(SpecId::Homestead) {
+            gas += TX_CREATE;
+        }
+        // EIP-3860: 按init code字数收费
+        if spec_id.is_enabled_in(SpecId::Shanghai) {
+            gas += INITCODE_WORD * (tx.input().len() as u64).div_ceil(32);
+        }
+    }
+
+    for item in tx.access_list() {
+        gas += ACCESS_LIST_ADDRESS + ACCESS_LIST_STORAGE_KEY * item.storage_keys.len() as u64;
+    }
+
+    // EIP-7702: 每个授权按新账户收费 已存在的账户在执行时退还差额
+    if let Transaction::Eip7702(tx) = tx {
+        gas += PER_EMPTY_ACCOUNT_COST * tx.authorization_list.len() as u64;
+    }
+
+    gas
+}
+
+/// Validate `tx` against the block and run it against `state`.
+///
+/// An `Err` means the transaction is invalid and left `state` untouched. A
+/// transaction that reverts or halts is still valid: it returns `Ok` with
+/// `success == false`, pays for its gas and bumps the sender nonce.
+pub fn transact(
+    state: &mut EvmState,
+    evm_context: &EvmContext,
+    tx: Transaction,
+) -> Result<TransactionResult, RunnerError> {
+    let spec_id = evm_context.spec_id;
+    let caller = tx.caller();
+    let gas_limit = tx.gas_limit();
+    let basefee = if spec_id.is_enabled_in(SpecId::London) {
+        evm_context.basefee.map_or(0, |basefee| U256::from_big_endian(&basefee).low_u128())
+    } else {
+        0
+    };
+    // 发送者 接收者和访问列表中的账户与存储槽一起从数据库获取
+    let mut accounts = vec![caller];
+    accounts.extend(tx.to());
+    accounts.extend(evm_context.coinbase);
+    let mut storage = Vec::new();
+    for item in tx.access_list() {
+        accounts.push(item.address);
+        storage.extend(item.storage_keys.iter().map(|key| (item.address, *key)));
+    }
+    state.prefetch(&accounts, &storage);
+    // 校验只读取缓存 先从数据库载入发送者
+    state.load_account(caller)?;
+    let intrinsic = validate_transaction(state, evm_context, &tx, basefee)?;
+
+    // 预先扣除全部gas费用 执行结束后退还未使用部分
+    let gas_price = tx.effective_gas_price(basefee);
+    state.sub_balance(caller, to_bytes(U256::from(gas_limit) * U256::from(gas_price)))?;
+    // blob费用按blob base fee全额扣除 不退还
+    let blob_gas_used = tx.blob_gas();
+    let blob_gas_price = if blob_gas_used > 0 {
+        evm_context.blob_base_fee()
+    } else {
+        0
+    };
+    state.sub_balance(caller, to_bytes(U256::from(blob_gas_used) * U256::from(blob_gas_price)))?;
+
+    // 区块环境中的GASPRICE为交易实际价格
+    let mut tx_context = evm_context.clone();
+    tx_context.gas_price = Some(to_bytes(U256::from(gas_price)));
+    if tx_context.basefee.is_none() {
+        tx_context.basefee = Some([0u8; 32]);
+    }
+    tx_context.blob_hashes = tx.blob_versioned_hashes().to_vec();
+
+    // 发送者nonce先递增 创建交易的合约地址由递增前的nonce派生
+    state.init_account(caller);
+    if let Some(account) = state.account_mut(caller) {
+        account.nonce += 1;
+    }
+    // 授权在发送者nonce递增之后 调用之前生效 调用失败也不回滚
+    let mut authorization_refund = 0;
+    if let Transaction::Eip7702(set_code_tx) = &tx {
+        authorization_refund =
+            apply_authorizations(state, evm_context, &set_code_tx.authorization_list);
+    }
+
+    // 交易检查点 gas费用 nonce和授权之后的修改在执行失败时全部回滚
+    let checkpoint = state.snapshot();
+    let log_start = state.logs.len();
+    let mut execute = Execute::new(
+        caller,
+        Some(caller),
+        Some(caller),
+        Some(tx.value()),
+        None,
+        Some(std::mem::take(state)),
+        Some(tx_context),
+    );
+    execute.gas = gas_limit - intrinsic;
+
+    let (result, contract_address) = match tx.to() {
+        Some(to) => {
+            let result = execute.call(to, tx.value(), tx.input().to_vec(), execute.gas, false);
+            (result, None)
+        }
+        None => {
+            let address = *Address::from_slice(&caller).create(tx.nonce()).0;
+            let result = execute.create_frame(address, tx.value(), tx.input().to_vec());
+            (result, Some(address))
+        }
+    };
+    let output = execute.returndata.heap.clone();
+    let code_executed = execute.op_count > 0;
+    *state = execute.state;
+
+    let success = result.is_ok();
+    if success {
+        state.discard_snapshot(checkpoint);
+    } else {
+        state.revert_to(checkpoint);
+    }
+
+    // 不按操作码计量gas 正常结束和revert只收取intrinsic gas 异常终止消耗全部gas
+    let halted = matches!(
+        &result,
+        Err(err) if !matches!(err, RunnerError::Revert(_) | RunnerError::RevertWithoutData)
+    );
+    let mut gas_used = if halted { gas_limit } else { intrinsic };
+    let gas_exact = halted || !code_executed;
+    // EIP-3529: 退款上限为已用gas的1/5
+    gas_used -= authorization_refund.min(gas_used / 5);
+
+    // 退还剩余gas 矿工获得小费
+    let refund = U256::from(gas_limit - gas_used) * U256::from(gas_price);
+    state.add_balance(caller, to_bytes(refund));
+    let tip = U256::from(gas_used) * U256::from(gas_price.saturating_sub(basefee));
+    let coinbase = evm_context.coinbase.unwrap_or([0xc0u8; 20]);
+    state.add_balance(coinbase, to_bytes(tip));
+
+    // 失败的交易不保留日志
+    let logs = if success {
+        let block_number = evm_context
+            .block_number
+            .map_or(0, |number| U256::from_big_endian(&number).low_u64());
+        for log in state.logs[log_start..].iter_mut() {
+            log.block_number = block_number;
+        }
+        state.logs[log_start..].to_vec()
+    } else {
+        Vec::new()
+    };
+    state.finalize_transaction(success, spec_id);
+
+    Ok(TransactionResult {
+        success,
+        gas_used,
+        gas_exact,
+        effective_gas_price: gas_price,
+        output,
+        logs,
+        contract_address: contract_address.filter(|_| success),
+        blob_gas_used,
+        blob_gas_price,
+        error: result.err(),
+    })
+}
+
+//交易合法性检查 返回intrinsic gas
+fn validate_transaction(
+    state: &EvmState,
+    evm_context: &EvmContext,
+    tx: &Transaction,
+    basefee: u128,
+) -> Result<u64, RunnerError> {
+    let spec_id = evm_context.spec_id;
+
+    let required_spec = match tx {
+        Transaction::Legacy(_) => SpecId::Frontier,
+        Transaction::Eip2930(_) => SpecId::Berlin,
+        Transaction::Eip1559(_) => SpecId::London,
+        Transaction::Eip4844(_) => SpecId::Cancun,
+        Transaction::Eip7702(_) => SpecId::Prague,
+    };
+    if !spec_id.is_enabled_in(required_spec) {
+        return Err(RunnerError::TransactionTypeNotSupported(tx.tx_type()));
+    }
+
+    // EIP-155 链id检查
+    if let (Some(tx_chain_id), Some(chain_id)) = (tx.chain_id(), evm_context.chain_id) {
+        if U256::from(tx_chain_id) != U256::from_big_endian(&chain_id) {
+            return Err(RunnerError::InvalidChainId);
+        }
+    }
+
+    let block_gas_limit = evm_context
+        .gas_limit
+        .map_or(DEFAULT_BLOCK_GAS_LIMIT, |gas_limit| U256::from_big_endian(&gas_limit).low_u64());
+    if tx.gas_limit() > block_gas_limit {
+        return Err(RunnerError::TxGasLimitExceedsBlock);
+    }
+    let intrinsic = intrinsic_gas(tx, spec_id);
+    if tx.gas_limit() < intrinsic {
+        return Err(RunnerError::IntrinsicGasTooLow);
+    }
+
+    if tx.max_priority_fee_per_gas() > tx.max_fee_per_gas() {
+        return Err(RunnerError::TipAboveFeeCap);
+    }
+    if tx.max_fee_per_gas() < basefee {
+        return Err(RunnerError::FeeCapTooLow);
+    }
+
+    // EIP-4844: 至少一个blob 哈希版本为KZG 总量不超过区块上限
+    if let Transaction::Eip4844(blob_tx) = tx {
+        if blob_tx.blob_versioned_hashes.is_empty() {
+            return Err(RunnerError::EmptyBlobs);
+        }
+        if blob_tx
+            .blob_versioned_hashes
+            .iter()
+            .any(|hash| hash[0] != VERSIONED_HASH_VERSION_KZG)
+        {
+            return Err(RunnerError::InvalidBlobVersionedHash);
+        }
+        if tx.blob_gas() > max_blob_gas_per_block(spec_id) {
+            return Err(RunnerError::TooManyBlobs);
+        }
+        if blob_tx.max_fee_per_blob_gas < evm_context.blob_base_fee() {
+            return Err(RunnerError::BlobFeeCapTooLow);
+        }
+    }
+
+    if let Transaction::Eip7702(set_code_tx) = tx {
+        if set_code_tx.authorization_list.is_empty() {
+            return Err(RunnerError::EmptyAuthorizationList);
+        }
+    }
+
+    let caller = tx.caller();
+    // EIP-3607: 有代码的账户不能发送交易 Prague起委托给合约的EOA除外
+    if let Some(code) = state.get_code_at(caller) {
+        if !spec_id.is_enabled_in(SpecId::Prague) || delegated_address(code).is_none() {
+            return Err(RunnerError::SenderNotEoa);
+        }
+    }
+
+    let account = state.accounts.get(&caller);
+    let nonce = account.map_or(0, |account| account.nonce);
+    if tx.nonce() != nonce {
+        return Err(RunnerError::NonceMismatch {
+            expected: nonce,
+            got: tx.nonce(),
+        });
+    }
+
+    // 余额需覆盖gas上限费用 blob费用上限和转账金额
+    let balance = U256::from_big_endian(&account.map_or([0u8; 32], |account| account.balance));
+    let max_cost = U256::from(tx.gas_limit()).full_mul(U256::from(tx.max_fee_per_gas()))
+        + U256::from(tx.blob_gas()).full_mul(U256::from(tx.max_fee_per_blob_gas()))
+        + U512::from(U256::from_big_endian(&tx.value()));
+    if max_cost > U512::from(balance) {
+        return Err(RunnerError::InsufficientBalance);
+    }
+
+    Ok(intrinsic)
+}
+
+fn to_bytes(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::storage::AccountState;
+    use crate::evm_core::transaction::types::{TxEip1559, TxLegacy};
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const BOB: [u8; 20] = [0xb0; 20];
+    const CONTRACT: [u8; 20] = [0xcc; 20];
+    const COINBASE: [u8; 20] = [0xc0; 20];
+
+    // SSTORE(0, 1) 后 REVERT(0, 0)
+    const STORE_THEN_REVERT: [u8; 9] = [0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x80, 0xfd];
+
+    fn wei(amount: u64) -> [u8; 32] {
+        to_bytes(U256::from(amount))
+    }
+
+    fn state() -> EvmState {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: wei(1_000_000),
+                ..Default::default()
+            },
+        );
+        db.insert_account(CONTRACT, AccountState::default());
+        db.insert_code(CONTRACT, STORE_THEN_REVERT.to_vec());
+        EvmState::with_database(db)
+    }
+
+    fn context(basefee: u64) -> EvmContext {
+        EvmContext {
+            basefee: Some(wei(basefee)),
+            coinbase: Some(COINBASE),
+            spec_id: SpecId::Cancun,
+            ..Default::default()
+        }
+    }
+
+    fn dynamic_fee(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> TxEip1559 {
+        TxEip1559 {
+            from: ALICE,
+            chain_id: 1,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            gas_limit: 21_000,
+            to: Some(BOB),
+            value: wei(3),
+            ..Default::default()
+        }
+    }
+
+    // 无效交易不修改状态
+    fn assert_rejected(tx: Transaction, basefee: u64, expected: RunnerError) {
+        let mut state = state();
+        let err = transact(&mut state, &context(basefee), tx).unwrap_err();
+        assert_eq!(err, expected);
+        assert_eq!(state.accounts[&ALICE].balance, wei(1_000_000));
+        assert_eq!(state.accounts[&ALICE].nonce, 0);
+        assert!(!state.account_exists(BOB));
+    }
+
+    #[test]
+    fn rejects_wrong_nonce() {
+        let tx = TxEip1559 {
+            nonce: 1,
+            ..dynamic_fee(10, 2)
+        };
+        assert_rejected(
+            Transaction::Eip1559(tx),
+            7,
+            RunnerError::NonceMismatch {
+                expected: 0,
+                got: 1,
+            },
+        );
+    }
+
+    #[test]
+    fn rejects_insufficient_balance() {
+        // 21000 * 48 + 3 超过余额
+        let tx = dynamic_fee(48, 2);
+        assert_rejected(Transaction::Eip1559(tx), 7, RunnerError::InsufficientBalance);
+    }
+
+    #[test]
+    fn rejects_bad_fee_caps() {
+        let tx = dynamic_fee(6, 2);
+        assert_rejected(Transaction::Eip1559(tx), 7, RunnerError::FeeCapTooLow);
+
+        let tx = dynamic_fee(10, 11);
+        assert_rejected(Transaction::Eip1559(tx), 7, RunnerError::TipAboveFeeCap);
+    }
+
+    #[test]
+    fn pays_fees_to_sender_and_coinbase() {
+        let mut state = state();
+        let tx = Transaction::Eip1559(dynamic_fee(10, 2));
+        let result = transact(&mut state, &context(7), tx).unwrap();
+
+        // 实际价格为 min(10, 7 + 2) = 9 小费为2
+        assert!(result.success);
+        assert!(result.gas_exact);
+        assert_eq!(result.gas_used, 21_000);
+        assert_eq!(result.effective_gas_price, 9);
+        assert_eq!(state.accounts[&ALICE].balance, wei(1_000_000 - 21_000 * 9 - 3));
+        assert_eq!(state.accounts[&ALICE].nonce, 1);
+        assert_eq!(state.accounts[&BOB].balance, wei(3));
+        assert_eq!(state.accounts[&COINBASE].balance, wei(21_000 * 2));
+    }
+
+    #[test]
+    fn revert_rolls_back_execution() {
+        let mut state = state();
+        let tx = Transaction::Legacy(TxLegacy {
+            from: ALICE,
+            gas_price: 7,
+            gas_limit: 30_000,
+            to: Some(CONTRACT),
+            value: wei(5),
+            ..Default::default()
+        });
+        let result = transact(&mut state, &context(7), tx).unwrap();
+
+        // 存储和转账回滚 nonce和gas费用保留
+        assert!(!result.success);
+        assert_eq!(result.error, Some(RunnerError::RevertWithoutData));
+        // 执行了代码 只收取intrinsic gas 与真实链不一致
+        assert!(!result.gas_exact);
+        assert_eq!(state.sload(CONTRACT, [0u8; 32]).unwrap(), [0u8; 32]);
+        assert_eq!(state.accounts[&CONTRACT].balance, wei(0));
+        assert_eq!(state.accounts[&ALICE].nonce, 1);
+        assert_eq!(
+            state.accounts[&ALICE].balance,
+            wei(1_000_000 - result.gas_used * 7)
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use crate::evm_core::gas::constant::GAS_PER_BLOB;
+
+/* -------------------------------------------------------------------------- */
+/*                              Transaction types                             */
+/* -------------------------------------------------------------------------- */
+/// An address and the storage slots it pre-warms (EIP-2930).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessListItem {
+    pub address: [u8; 20],
+    pub storage_keys: Vec<[u8; 32]>,
+}
+
+/// A signed authorization to delegate an EOA's code (EIP-7702).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Authorization {
+    pub chain_id: u64,
+    pub address: [u8; 20],
+    pub nonce: u64,
+    pub y_parity: u8,
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+}
+
+/// Legacy transaction, optionally replay-protected with EIP-155.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxLegacy {
+    pub from: [u8; 20],
+    pub chain_id: Option<u64>,
+    pub nonce: u64,
+    pub gas_price: u128,
+    pub gas_limit: u64,
+    /// `None` creates a contract.
+    pub to: Option<[u8; 20]>,
+    pub value: [u8; 32],
+    pub input: Vec<u8>,
+}
+
+/// Type 0x01 transaction with an access list (EIP-2930).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxEip2930 {
+    pub from: [u8; 20],
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_price: u128,
+    pub gas_limit: u64,
+    pub to: Option<[u8; 20]>,
+    pub value: [u8; 32],
+    pub input: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+}
+
+/// Type 0x02 dynamic-fee transaction (EIP-1559).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxEip1559 {
+    pub from: [u8; 20],
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: u128,
+    pub max_fee_per_gas: u128,
+    pub gas_limit: u64,
+    pub to: Option<[u8; 20]>,
+    pub value: [u8; 32],
+    pub input: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+}
+
+/// Type 0x03 blob-carrying transaction (EIP-4844). Cannot create contracts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxEip4844 {
+    pub from: [u8; 20],
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: u128,
+    pub max_fee_per_gas: u128,
+    pub gas_limit: u64,
+    pub to: [u8; 20],
+    pub value: [u8; 32],
+    pub input: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+    pub max_fee_per_blob_gas: u128,
+    pub blob_versioned_hashes: Vec<[u8; 32]>,
+}
+
+/// Type 0x04 set-code transaction (EIP-7702). Cannot create contracts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxEip7702 {
+    pub from: [u8; 20],
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: u128,
+    pub max_fee_per_gas: u128,
+    pub gas_limit: u64,
+    pub to: [u8; 20],
+    pub value: [u8; 32],
+    pub input: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+    pub authorization_list: Vec<Authorization>,
+}
+
+/// A transaction of any supported envelope type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transaction {
+    Legacy(TxLegacy),
+    Eip2930(TxEip2930),
+    Eip1559(TxEip1559),
+    Eip4844(TxEip4844),
+    Eip7702(TxEip7702),
+}
+
+impl Transaction {
+    /// EIP-2718 type byte.
+    pub fn tx_type(&self) -> u8 {
+        match self {
+            Transaction::Legacy(_) => 0x00,
+            Transaction::Eip2930(_) => 0x01,
+            Transaction::Eip1559(_) => 0x02,
+            Transaction::Eip4844(_) => 0x03,
+            Transaction::Eip7702(_) => 0x04,
+        }
+    }
+
+    //交易发送者
+    pub fn caller(&self) -> [u8; 20] {
+        match self {
+            Transaction::Legacy(tx) => tx.from,
+            Transaction::Eip2930(tx) => tx.from,
+            Transaction::Eip1559(tx) => tx.from,
+            Transaction::Eip4844(tx) => tx.from,
+            Transaction::Eip7702(tx) => tx.from,
+        }
+    }
+
+    pub fn set_caller(&mut self, from: [u8; 20]) {
+        match self {
+            Transaction::Legacy(tx) => tx.from = from,
+            Transaction::Eip2930(tx) => tx.from = from,
+            Transaction::Eip1559(tx) => tx.from = from,
+            Transaction::Eip4844(tx) => tx.from = from,
+            Transaction::Eip7702(tx) => tx.from = from,
+        }
+    }
+
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            Transaction::Legacy(tx) => tx.chain_id,
+            Transaction::Eip2930(tx) => Some(tx.chain_id),
+            Transaction::Eip1559(tx) => Some(tx.chain_id),
+            Transaction::Eip4844(tx) => Some(tx.chain_id),
+            Transaction::Eip7702(tx) => Some(tx.chain_id),
+        }
+    }
+
+    pub fn nonce(&self) -> u64 {
+        match self {
+            Transaction::Legacy(tx) => tx.nonce,
+            Transaction::Eip2930(tx) => tx.nonce,
+            Transaction::Eip1559(tx) => tx.nonce,
+            Transaction::Eip4844(tx) => tx.nonce,
+            Transaction::Eip7702(tx) => tx.nonce,
+        }
+    }
+
+    pub fn gas_limit(&self) -> u64 {
+        match self {
+            Transaction::Legacy(tx) => tx.gas_limit,
+            Transaction::Eip2930(tx) => tx.gas_limit,
+            Transaction::Eip1559(tx) => tx.gas_limit,
+            Transaction::Eip4844(tx) => tx.gas_limit,
+            Transaction::Eip7702(tx) => tx.gas_limit,
+        }
+    }
+
+    //None表示创建合约
+    pub fn to(&self) -> Option<[u8; 20]> {
+        match self {
+            Transaction::Legacy(tx) => tx.to,
+            Transaction::Eip2930(tx) => tx.to,
+            Transaction::Eip1559(tx) => tx.to,
+            Transaction::Eip4844(tx) => Some(tx.to),
+            Transaction::Eip7702(tx) => Some(tx.to),
+        }
+    }
+
+    pub fn value(&self) -> [u8; 32] {
+        match self {
+            Transaction::Legacy(tx) => tx.value,
+            Transaction::Eip2930(tx) => tx.value,
+            Transaction::Eip1559(tx) => tx.value,
+            Transaction::Eip4844(tx) => tx.value,
+            Transaction::Eip7702(tx) => tx.value,
+        }
+    }
+
+    pub fn input(&self) -> &[u8] {
+        match self {
+            Transaction::Legacy(tx) => &tx.input,
+            Transaction::Eip2930(tx) => &tx.input,
+            Transaction::Eip1559(tx) => &tx.input,
+            Transaction::Eip4844(tx) => &tx.input,
+            Transaction::Eip7702(tx) => &tx.input,
+        }
+    }
+
+    pub fn access_list(&self) -> &[AccessListItem] {
+        match self {
+            Transaction::Legacy(_) => &[],
+            Transaction::Eip2930(tx) => &tx.access_list,
+            Transaction::Eip1559(tx) => &tx.access_list,
+            Transaction::Eip4844(tx) => &tx.access_list,
+            Transaction::Eip7702(tx) => &tx.access_list,
+        }
+    }
+
+    /// The most the sender is willing to pay per gas.
+    pub fn max_fee_per_gas(&self) -> u128 {
+        match self {
+            Transaction::Legacy(tx) => tx.gas_price,
+            Transaction::Eip2930(tx) => tx.gas_price,
+            Transaction::Eip1559(tx) => tx.max_fee_per_gas,
+            Transaction::Eip4844(tx) => tx.max_fee_per_gas,
+            Transaction::Eip7702(tx) => tx.max_fee_per_gas,
+        }
+    }
+
+    /// The tip cap per gas. Legacy transactions tip everything above the base fee.
+    pub fn max_priority_fee_per_gas(&self) -> u128 {
+        match self {
+            Transaction::Legacy(tx) => tx.gas_price,
+            Transaction::Eip2930(tx) => tx.gas_price,
+            Transaction::Eip1559(tx) => tx.max_priority_fee_per_gas,
+            Transaction::Eip4844(tx) => tx.max_priority_fee_per_gas,
+            Transaction::Eip7702(tx) => tx.max_priority_fee_per_gas,
+        }
+    }
+
+    /// Versioned hashes of the blobs carried by a type 0x03 transaction.
+    pub fn blob_versioned_hashes(&self) -> &[[u8; 32]] {
+        match self {
+            Transaction::Eip4844(tx) => &tx.blob_versioned_hashes,
+            _ => &[],
+        }
+    }
+
+    pub fn max_fee_per_blob_gas(&self) -> u128 {
+        match self {
+            Transaction::Eip4844(tx) => tx.max_fee_per_blob_gas,
+            _ => 0,
+        }
+    }
+
+    /// Blob gas consumed by the transaction's blobs.
+    pub fn blob_gas(&self) -> u64 {
+        GAS_PER_BLOB * self.blob_versioned_hashes().len() as u64
+    }
+
+    /// Price per gas actually paid under the given base fee.
+    pub fn effective_gas_price(&self, basefee: u128) -> u128 {
+        match self {
+            Transaction::Legacy(_) | Transaction::Eip2930(_) => self.max_fee_per_gas(),
+            _ => self
+                .max_fee_per_gas()
+                .min(basefee.saturating_add(self.max_priority_fee_per_gas())),
+        }
+    }
+}
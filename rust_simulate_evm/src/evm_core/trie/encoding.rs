@@ -0,0 +1,142 @@
+/*
+Merkle Patricia Trie 的编码规则
+key按半字节(nibble)展开 路径用hex-prefix编码 节点用rlp编码
+节点rlp不足32字节时直接内嵌在父节点中 否则父节点保存其keccak哈希
+*/
+use alloy_rlp::{Encodable, Header};
+use ethers::utils::keccak256;
+
+/// keccak256 of the RLP empty string, the root of an empty trie.
+pub const EMPTY_ROOT: [u8; 32] = [
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+];
+
+//每个字节拆成高低两个半字节
+pub fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+/// Hex-prefix encode a nibble path; the flag tells leaves from extensions.
+pub fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut out = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        out.push(((flag + 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        out.push(flag << 4);
+        nibbles
+    };
+    for pair in rest.chunks(2) {
+        out.push((pair[0] << 4) | pair[1]);
+    }
+    out
+}
+
+//hex_prefix的逆运算 返回(路径, 是否叶子)
+pub fn decode_hex_prefix(encoded: &[u8]) -> (Vec<u8>, bool) {
+    let Some(first) = encoded.first() else {
+        return (Vec::new(), false);
+    };
+    let is_leaf = first >> 4 >= 2;
+    let mut nibbles = Vec::with_capacity(encoded.len() * 2);
+    if (first >> 4) % 2 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(to_nibbles(&encoded[1..]));
+    (nibbles, is_leaf)
+}
+
+/// RLP-encode a byte string.
+pub fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len() + 5);
+    bytes.encode(&mut out);
+    out
+}
+
+/// Wrap already RLP-encoded items in a list header.
+pub fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload_length = items.iter().map(Vec::len).sum();
+    let mut out = Vec::with_capacity(payload_length + 9);
+    Header {
+        list: true,
+        payload_length,
+    }
+    .encode(&mut out);
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
+
+/// How a parent refers to a child node: inline below 32 bytes, else by hash.
+pub fn node_reference(encoded: &[u8]) -> Vec<u8> {
+    if encoded.len() < 32 {
+        encoded.to_vec()
+    } else {
+        rlp_bytes(&keccak256(encoded))
+    }
+}
+
+/// RLP-encode a big-endian integer, dropping leading zero bytes.
+pub fn rlp_uint(word: &[u8; 32]) -> Vec<u8> {
+    let start = word.iter().position(|byte| *byte != 0).unwrap_or(32);
+    rlp_bytes(&word[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_root_is_hash_of_empty_string() {
+        assert_eq!(keccak256([0x80]), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn rlp_vectors() {
+        assert_eq!(rlp_bytes(b""), vec![0x80]);
+        assert_eq!(rlp_bytes(b"\x0f"), vec![0x0f]);
+        assert_eq!(rlp_bytes(b"dog"), hex::decode("83646f67").unwrap());
+        assert_eq!(
+            rlp_list(&[rlp_bytes(b"cat"), rlp_bytes(b"dog")]),
+            hex::decode("c88363617483646f67").unwrap()
+        );
+        assert_eq!(rlp_list(&[]), vec![0xc0]);
+
+        // 56字节开始使用长度前缀
+        let lorem = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        assert_eq!(rlp_bytes(lorem)[..2], [0xb8, 0x38]);
+    }
+
+    #[test]
+    fn rlp_uint_drops_leading_zeros() {
+        let mut word = [0u8; 32];
+        assert_eq!(rlp_uint(&word), vec![0x80]);
+        word[31] = 0x0f;
+        assert_eq!(rlp_uint(&word), vec![0x0f]);
+        word[30] = 0x04;
+        word[31] = 0x00;
+        assert_eq!(rlp_uint(&word), vec![0x82, 0x04, 0x00]);
+    }
+
+    #[test]
+    fn hex_prefix_vectors() {
+        // Yellow Paper 附录C的例子
+        assert_eq!(hex_prefix(&[1, 2, 3, 4, 5], false), vec![0x11, 0x23, 0x45]);
+        assert_eq!(hex_prefix(&[0, 1, 2, 3, 4, 5], false), vec![0x00, 0x01, 0x23, 0x45]);
+        assert_eq!(hex_prefix(&[0, 0xf, 1, 0xc, 0xb, 8], true), vec![0x20, 0x0f, 0x1c, 0xb8]);
+        assert_eq!(hex_prefix(&[0xf, 1, 0xc, 0xb, 8], true), vec![0x3f, 0x1c, 0xb8]);
+
+        assert_eq!(decode_hex_prefix(&[0x3f, 0x1c, 0xb8]), (vec![0xf, 1, 0xc, 0xb, 8], true));
+        assert_eq!(decode_hex_prefix(&[0x00, 0x01, 0x23, 0x45]), (vec![0, 1, 2, 3, 4, 5], false));
+    }
+
+    #[test]
+    fn small_nodes_are_inlined() {
+        assert_eq!(node_reference(&[0xc2, 0x01, 0x02]), vec![0xc2, 0x01, 0x02]);
+        let large = vec![0u8; 32];
+        assert_eq!(node_reference(&large), rlp_bytes(&keccak256(&large)));
+    }
+}
@@ -0,0 +1,357 @@
+use ethers::utils::keccak256;
+
+use super::encoding::{hex_prefix, node_reference, rlp_bytes, rlp_list, to_nibbles, EMPTY_ROOT};
+
+/* -------------------------------------------------------------------------- */
+/*                                    Node                                    */
+/* -------------------------------------------------------------------------- */
+/// A node of a hexary Merkle Patricia Trie. Paths are in nibbles.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Node {
+    #[default]
+    Empty,
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Box<Node>,
+    },
+    Branch {
+        children: Box<[Node; 16]>,
+        value: Option<Vec<u8>>,
+    },
+}
+
+impl Node {
+    /// RLP encoding of the node, as hashed into its parent.
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Node::Empty => rlp_bytes(&[]),
+            Node::Leaf { path, value } => {
+                rlp_list(&[rlp_bytes(&hex_prefix(path, true)), rlp_bytes(value)])
+            }
+            Node::Extension { path, child } => rlp_list(&[
+                rlp_bytes(&hex_prefix(path, false)),
+                node_reference(&child.encode()),
+            ]),
+            Node::Branch { children, value } => {
+                let mut items: Vec<Vec<u8>> = children
+                    .iter()
+                    .map(|child| match child {
+                        Node::Empty => rlp_bytes(&[]),
+                        child => node_reference(&child.encode()),
+                    })
+                    .collect();
+                items.push(rlp_bytes(value.as_deref().unwrap_or_default()));
+                rlp_list(&items)
+            }
+        }
+    }
+
+    fn empty_branch() -> Node {
+        Node::Branch {
+            children: Box::default(),
+            value: None,
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                 MerkleTrie                                 */
+/* -------------------------------------------------------------------------- */
+/// An in-memory Merkle Patricia Trie keyed by raw bytes.
+///
+/// Hashes are computed on demand by [`MerkleTrie::root_hash`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MerkleTrie {
+    pub root: Node,
+}
+
+impl MerkleTrie {
+    pub fn new() -> Self {
+        Self { root: Node::Empty }
+    }
+
+    /// Insert or overwrite `key`. An empty value removes the key.
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) {
+        if value.is_empty() {
+            self.remove(key);
+            return;
+        }
+        let root = std::mem::take(&mut self.root);
+        self.root = insert(root, &to_nibbles(key), value);
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        let root = std::mem::take(&mut self.root);
+        self.root = remove(root, &to_nibbles(key));
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        let path = to_nibbles(key);
+        let mut node = &self.root;
+        let mut path = path.as_slice();
+        loop {
+            match node {
+                Node::Empty => return None,
+                Node::Leaf { path: leaf, value } => {
+                    return (leaf.as_slice() == path).then_some(value.as_slice());
+                }
+                Node::Extension { path: prefix, child } => {
+                    path = path.strip_prefix(prefix.as_slice())?;
+                    node = child;
+                }
+                Node::Branch { children, value } => match path.split_first() {
+                    None => return value.as_deref(),
+                    Some((nibble, rest)) => {
+                        node = &children[*nibble as usize];
+                        path = rest;
+                    }
+                },
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root == Node::Empty
+    }
+
+    pub fn root_hash(&self) -> [u8; 32] {
+        match self.root {
+            Node::Empty => EMPTY_ROOT,
+            // 根节点总是取哈希 即使编码不足32字节
+            ref root => keccak256(root.encode()),
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                Insert/remove                               */
+/* -------------------------------------------------------------------------- */
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn insert(node: Node, path: &[u8], value: Vec<u8>) -> Node {
+    match node {
+        Node::Empty => Node::Leaf {
+            path: path.to_vec(),
+            value,
+        },
+        Node::Leaf {
+            path: leaf_path,
+            value: leaf_value,
+        } => {
+            if leaf_path == path {
+                return Node::Leaf {
+                    path: leaf_path,
+                    value,
+                };
+            }
+            // 从分叉处拆成分支节点
+            let shared = common_prefix(&leaf_path, path);
+            let branch = insert(Node::empty_branch(), &leaf_path[shared..], leaf_value);
+            let branch = insert(branch, &path[shared..], value);
+            with_prefix(&path[..shared], branch)
+        }
+        Node::Extension {
+            path: prefix,
+            child,
+        } => {
+            let shared = common_prefix(&prefix, path);
+            if shared == prefix.len() {
+                return Node::Extension {
+                    child: Box::new(insert(*child, &path[shared..], value)),
+                    path: prefix,
+                };
+            }
+            // 扩展节点在分叉处断开 剩余部分挂到新分支下
+            let mut children: Box<[Node; 16]> = Box::default();
+            children[prefix[shared] as usize] = with_prefix(&prefix[shared + 1..], *child);
+            let branch = Node::Branch {
+                children,
+                value: None,
+            };
+            let branch = insert(branch, &path[shared..], value);
+            with_prefix(&path[..shared], branch)
+        }
+        Node::Branch {
+            mut children,
+            value: branch_value,
+        } => match path.split_first() {
+            None => Node::Branch {
+                children,
+                value: Some(value),
+            },
+            Some((nibble, rest)) => {
+                let child = std::mem::take(&mut children[*nibble as usize]);
+                children[*nibble as usize] = insert(child, rest, value);
+                Node::Branch {
+                    children,
+                    value: branch_value,
+                }
+            }
+        },
+    }
+}
+
+fn remove(node: Node, path: &[u8]) -> Node {
+    match node {
+        Node::Empty => Node::Empty,
+        Node::Leaf {
+            path: leaf_path,
+            value,
+        } => {
+            if leaf_path == path {
+                Node::Empty
+            } else {
+                Node::Leaf {
+                    path: leaf_path,
+                    value,
+                }
+            }
+        }
+        Node::Extension {
+            path: prefix,
+            child,
+        } => match path.strip_prefix(prefix.as_slice()) {
+            Some(rest) => with_prefix(&prefix, remove(*child, rest)),
+            None => Node::Extension {
+                path: prefix,
+                child,
+            },
+        },
+        Node::Branch {
+            mut children,
+            mut value,
+        } => {
+            match path.split_first() {
+                None => value = None,
+                Some((nibble, rest)) => {
+                    let child = std::mem::take(&mut children[*nibble as usize]);
+                    children[*nibble as usize] = remove(child, rest);
+                }
+            }
+            collapse_branch(children, value)
+        }
+    }
+}
+
+//分支只剩一项时退化为叶子或扩展节点
+fn collapse_branch(mut children: Box<[Node; 16]>, value: Option<Vec<u8>>) -> Node {
+    let mut remaining = children
+        .iter()
+        .enumerate()
+        .filter(|(_, child)| **child != Node::Empty)
+        .map(|(nibble, _)| nibble);
+    let first = remaining.next();
+    let more = remaining.next().is_some();
+
+    match (first, more, value) {
+        (None, _, None) => Node::Empty,
+        (None, _, Some(value)) => Node::Leaf {
+            path: Vec::new(),
+            value,
+        },
+        (Some(nibble), false, None) => {
+            let child = std::mem::take(&mut children[nibble]);
+            with_prefix(&[nibble as u8], child)
+        }
+        (_, _, value) => Node::Branch { children, value },
+    }
+}
+
+//在节点前拼接路径 合并相邻的路径节点
+fn with_prefix(prefix: &[u8], node: Node) -> Node {
+    if prefix.is_empty() {
+        return node;
+    }
+    match node {
+        Node::Empty => Node::Empty,
+        Node::Leaf { path, value } => Node::Leaf {
+            path: [prefix, &path].concat(),
+            value,
+        },
+        Node::Extension { path, child } => Node::Extension {
+            path: [prefix, &path].concat(),
+            child,
+        },
+        branch => Node::Extension {
+            path: prefix.to_vec(),
+            child: Box::new(branch),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root_of(entries: &[(&str, &str)]) -> String {
+        let mut trie = MerkleTrie::new();
+        for (key, value) in entries {
+            trie.insert(key.as_bytes(), value.as_bytes().to_vec());
+        }
+        hex::encode(trie.root_hash())
+    }
+
+    #[test]
+    fn empty_trie() {
+        assert_eq!(MerkleTrie::new().root_hash(), EMPTY_ROOT);
+        assert!(MerkleTrie::new().is_empty());
+    }
+
+    // ethereum/tests TrieTests/trieanyorder.json
+    #[test]
+    fn known_roots() {
+        assert_eq!(
+            root_of(&[("doe", "reindeer"), ("dog", "puppy"), ("dogglesworth", "cat")]),
+            "8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3"
+        );
+        assert_eq!(
+            root_of(&[("do", "verb"), ("horse", "stallion"), ("doge", "coin"), ("dog", "puppy")]),
+            "5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"
+        );
+        assert_eq!(
+            root_of(&[("foo", "bar"), ("food", "bass")]),
+            "17beaa1648bafa633cda809c90c04af50fc8aed3cb40d16efbddee6fdf63c4c3"
+        );
+        assert_eq!(
+            root_of(&[("be", "e"), ("dog", "puppy"), ("bed", "d")]),
+            "3f67c7a47520f79faa29255d2d3c084a7a6df0453116ed7232ff10277a8be68b"
+        );
+        assert_eq!(
+            root_of(&[("test", "test"), ("te", "testy")]),
+            "8452568af70d8d140f58d941338542f645fcca50094b20f3c3d8c3df49337928"
+        );
+    }
+
+    #[test]
+    fn insertion_order_does_not_matter() {
+        let forward = root_of(&[("do", "verb"), ("dog", "puppy"), ("doge", "coin"), ("horse", "stallion")]);
+        let backward = root_of(&[("horse", "stallion"), ("doge", "coin"), ("dog", "puppy"), ("do", "verb")]);
+        assert_eq!(forward, backward);
+    }
+
+    #[test]
+    fn remove_restores_previous_root() {
+        let mut trie = MerkleTrie::new();
+        trie.insert(b"doe", b"reindeer".to_vec());
+        trie.insert(b"dog", b"puppy".to_vec());
+        let root = trie.root_hash();
+
+        trie.insert(b"dogglesworth", b"cat".to_vec());
+        assert_eq!(trie.get(b"dogglesworth"), Some(&b"cat"[..]));
+        trie.remove(b"dogglesworth");
+        assert_eq!(trie.root_hash(), root);
+        assert_eq!(trie.get(b"dogglesworth"), None);
+
+        // 空值等同于删除
+        trie.insert(b"dog", Vec::new());
+        trie.insert(b"doe", Vec::new());
+        assert!(trie.is_empty());
+        assert_eq!(trie.root_hash(), EMPTY_ROOT);
+    }
+}
@@ -0,0 +1,9 @@
+pub mod encoding;
+
+pub mod merkle;
+
+pub mod root;
+
+pub mod state;
+
+pub mod proof;
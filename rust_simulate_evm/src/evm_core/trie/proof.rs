@@ -0,0 +1,484 @@
+/*
+Merkle证明 即从根节点到目标key路径上的所有节点的rlp编码
+内嵌在父节点中的小节点(不足32字节)不单独列出 根节点总是列出
+*/
+use std::collections::HashMap;
+
+use alloy_rlp::Header;
+use ethers::types::U256;
+use ethers::utils::keccak256;
+use serde_json::{json, Value};
+
+use crate::evm_core::storage::{AccountState, EvmState, KECCAK_EMPTY};
+use crate::evm_core::utils::byte_operate::{hex_to_address, hex_to_bytes, hex_to_u64, hex_to_word};
+use crate::evm_core::utils::error::RunnerError;
+
+use super::encoding::{decode_hex_prefix, rlp_uint, to_nibbles, EMPTY_ROOT};
+use super::merkle::{MerkleTrie, Node};
+
+/* -------------------------------------------------------------------------- */
+/*                                 Proof types                                */
+/* -------------------------------------------------------------------------- */
+/// Proof of one storage slot, as in the `storageProof` entries of `eth_getProof`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageProof {
+    pub key: [u8; 32],
+    pub value: [u8; 32],
+    pub proof: Vec<Vec<u8>>,
+}
+
+/// An account and its storage proofs, shaped like an `eth_getProof` response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountProof {
+    pub address: [u8; 20],
+    pub balance: [u8; 32],
+    pub code_hash: [u8; 32],
+    pub nonce: u64,
+    pub storage_hash: [u8; 32],
+    pub account_proof: Vec<Vec<u8>>,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                 Generation                                 */
+/* -------------------------------------------------------------------------- */
+impl MerkleTrie {
+    /// Nodes on the path to `key`. Also proves absence when `key` is missing.
+    pub fn proof(&self, key: &[u8]) -> Vec<Vec<u8>> {
+        let path = to_nibbles(key);
+        let mut path = path.as_slice();
+        let mut node = &self.root;
+        let mut proof = vec![node.encode()];
+        loop {
+            let child = match node {
+                Node::Empty | Node::Leaf { .. } => break,
+                Node::Extension { path: prefix, child } => match path.strip_prefix(prefix.as_slice()) {
+                    Some(rest) => {
+                        path = rest;
+                        child.as_ref()
+                    }
+                    None => break,
+                },
+                Node::Branch { children, .. } => match path.split_first() {
+                    Some((nibble, rest)) => {
+                        path = rest;
+                        &children[*nibble as usize]
+                    }
+                    None => break,
+                },
+            };
+            if *child == Node::Empty {
+                break;
+            }
+            let encoded = child.encode();
+            // 内嵌节点已包含在父节点中
+            if encoded.len() >= 32 {
+                proof.push(encoded);
+            }
+            node = child;
+        }
+        proof
+    }
+}
+
+impl EvmState {
+    /// Account and storage proofs against [`EvmState::state_root`], like `eth_getProof`.
+    ///
+    /// Loads the whole database first, so it fails with
+    /// [`RunnerError::IncompleteState`] on a fork.
+    pub fn get_proof(
+        &mut self,
+        address: [u8; 20],
+        slots: &[[u8; 32]],
+    ) -> Result<AccountProof, RunnerError> {
+        self.load_all()?;
+        let account_proof = self.state_trie().proof(&keccak256(address));
+        let Some(account) = self.accounts.get(&address) else {
+            return Ok(AccountProof {
+                address,
+                code_hash: KECCAK_EMPTY,
+                storage_hash: EMPTY_ROOT,
+                account_proof,
+                storage_proof: slots
+                    .iter()
+                    .map(|slot| StorageProof {
+                        key: *slot,
+                        proof: Vec::new(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            });
+        };
+
+        let storage_trie = account.storage_trie();
+        let storage_proof = slots
+            .iter()
+            .map(|slot| StorageProof {
+                key: *slot,
+                value: account.storage.get(slot).copied().unwrap_or_default(),
+                proof: storage_trie.proof(&keccak256(slot)),
+            })
+            .collect();
+
+        Ok(AccountProof {
+            address,
+            balance: account.balance,
+            code_hash: if account.has_code() {
+                account.code_hash
+            } else {
+                KECCAK_EMPTY
+            },
+            nonce: account.nonce,
+            storage_hash: storage_trie.root_hash(),
+            account_proof,
+            storage_proof,
+        })
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                Verification                                */
+/* -------------------------------------------------------------------------- */
+/// Walk `proof` from `root` along `key`.
+///
+/// Returns the stored value, `None` if the proof shows the key is absent, or
+/// an error if the proof does not connect to `root`.
+pub fn verify_proof(
+    root: [u8; 32],
+    key: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, RunnerError> {
+    let nodes: HashMap<[u8; 32], &[u8]> =
+        proof.iter().map(|node| (keccak256(node), node.as_slice())).collect();
+    if root == EMPTY_ROOT {
+        return Ok(None);
+    }
+
+    let path = to_nibbles(key);
+    let mut path = path.as_slice();
+    let mut node = *nodes
+        .get(&root)
+        .ok_or_else(|| invalid_proof("root node missing from proof"))?;
+    loop {
+        let items = decode_list(node)?;
+        let reference = match items.len() {
+            17 => match path.split_first() {
+                None => {
+                    let value = decode_string(items[16])?;
+                    return Ok((!value.is_empty()).then(|| value.to_vec()));
+                }
+                Some((nibble, rest)) => {
+                    path = rest;
+                    items[*nibble as usize]
+                }
+            },
+            2 => {
+                let (node_path, is_leaf) = decode_hex_prefix(decode_string(items[0])?);
+                if is_leaf {
+                    return if node_path == path {
+                        Ok(Some(decode_string(items[1])?.to_vec()))
+                    } else {
+                        Ok(None)
+                    };
+                }
+                match path.strip_prefix(node_path.as_slice()) {
+                    Some(rest) => {
+                        path = rest;
+                        items[1]
+                    }
+                    None => return Ok(None),
+                }
+            }
+            _ => return Err(invalid_proof("node is neither branch nor short node")),
+        };
+
+        // 列表为内嵌节点 32字节字符串为子节点哈希 空串表示不存在
+        node = if reference.first().is_some_and(|byte| *byte >= 0xc0) {
+            reference
+        } else {
+            let hash = decode_string(reference)?;
+            if hash.is_empty() {
+                return Ok(None);
+            }
+            let hash: [u8; 32] = hash
+                .try_into()
+                .map_err(|_| invalid_proof("child reference is not a hash"))?;
+            *nodes
+                .get(&hash)
+                .ok_or_else(|| invalid_proof("node missing from proof"))?
+        };
+    }
+}
+
+impl AccountProof {
+    /// Check the account proof against `state_root` and each storage proof
+    /// against the proven storage root.
+    pub fn verify(&self, state_root: [u8; 32]) -> Result<(), RunnerError> {
+        let account = verify_proof(state_root, &keccak256(self.address), &self.account_proof)?;
+        match account {
+            Some(encoded) if encoded != self.rlp_encode() => {
+                return Err(invalid_proof("account does not match proof"))
+            }
+            None if !self.is_empty() => return Err(invalid_proof("account missing from state")),
+            _ => {}
+        }
+
+        for storage in &self.storage_proof {
+            let value = verify_proof(self.storage_hash, &keccak256(storage.key), &storage.proof)?;
+            let expected = (storage.value != [0u8; 32]).then(|| rlp_uint(&storage.value));
+            if value != expected {
+                return Err(invalid_proof("storage value does not match proof"));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.nonce == 0
+            && self.balance == [0u8; 32]
+            && self.code_hash == KECCAK_EMPTY
+            && self.storage_hash == EMPTY_ROOT
+    }
+
+    fn rlp_encode(&self) -> Vec<u8> {
+        let account = AccountState {
+            nonce: self.nonce,
+            balance: self.balance,
+            storage: HashMap::new(),
+            code_hash: self.code_hash,
+        };
+        account.rlp_encode(self.storage_hash)
+    }
+}
+
+fn decode_list(node: &[u8]) -> Result<Vec<&[u8]>, RunnerError> {
+    let mut buf = node;
+    let mut payload = Header::decode_bytes(&mut buf, true).map_err(rlp_error)?;
+    let mut items = Vec::with_capacity(17);
+    while !payload.is_empty() {
+        let start = payload;
+        Header::decode(&mut payload).and_then(|header| {
+            if header.payload_length > payload.len() {
+                return Err(alloy_rlp::Error::InputTooShort);
+            }
+            payload = &payload[header.payload_length..];
+            Ok(())
+        })
+        .map_err(rlp_error)?;
+        items.push(&start[..start.len() - payload.len()]);
+    }
+    Ok(items)
+}
+
+fn decode_string(item: &[u8]) -> Result<&[u8], RunnerError> {
+    let mut buf = item;
+    Header::decode_bytes(&mut buf, false).map_err(rlp_error)
+}
+
+fn rlp_error(error: alloy_rlp::Error) -> RunnerError {
+    RunnerError::InvalidProof(error.to_string())
+}
+
+fn invalid_proof(message: &str) -> RunnerError {
+    RunnerError::InvalidProof(message.to_string())
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    JSON                                    */
+/* -------------------------------------------------------------------------- */
+impl AccountProof {
+    /// The `eth_getProof` JSON result.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "address": format!("0x{}", hex::encode(self.address)),
+            "accountProof": hex_list(&self.account_proof),
+            "balance": quantity(&self.balance),
+            "codeHash": format!("0x{}", hex::encode(self.code_hash)),
+            "nonce": format!("{:#x}", self.nonce),
+            "storageHash": format!("0x{}", hex::encode(self.storage_hash)),
+            "storageProof": self.storage_proof.iter().map(|storage| json!({
+                "key": format!("0x{}", hex::encode(storage.key)),
+                "value": quantity(&storage.value),
+                "proof": hex_list(&storage.proof),
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Parse an `eth_getProof` result, e.g. one fetched from a node.
+    pub fn from_json(value: &Value) -> Result<Self, RunnerError> {
+        let value = value.get("result").unwrap_or(value);
+        let storage_proof = value
+            .get("storageProof")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|storage| {
+                Ok(StorageProof {
+                    key: hex_to_word(field(storage, "key")?)?,
+                    value: hex_to_word(field(storage, "value")?)?,
+                    proof: proof_nodes(storage, "proof")?,
+                })
+            })
+            .collect::<Result<_, RunnerError>>()?;
+
+        Ok(AccountProof {
+            address: hex_to_address(field(value, "address")?)?,
+            balance: hex_to_word(field(value, "balance")?)?,
+            code_hash: hex_to_word(field(value, "codeHash")?)?,
+            nonce: hex_to_u64(field(value, "nonce")?)?,
+            storage_hash: hex_to_word(field(value, "storageHash")?)?,
+            account_proof: proof_nodes(value, "accountProof")?,
+            storage_proof,
+        })
+    }
+}
+
+//数值按JSON-RPC规则去掉前导0
+fn quantity(word: &[u8; 32]) -> String {
+    format!("{:#x}", U256::from_big_endian(word))
+}
+
+fn hex_list(nodes: &[Vec<u8>]) -> Vec<String> {
+    nodes.iter().map(|node| format!("0x{}", hex::encode(node))).collect()
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Result<&'a str, RunnerError> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid_proof(&format!("missing field {}", key)))
+}
+
+fn proof_nodes(value: &Value, key: &str) -> Result<Vec<Vec<u8>>, RunnerError> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .ok_or_else(|| invalid_proof(&format!("missing field {}", key)))?
+        .iter()
+        .map(|node| {
+            node.as_str()
+                .ok_or_else(|| invalid_proof("proof nodes must be hex strings"))
+                .and_then(hex_to_bytes)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::db::memory::MemoryDb;
+
+    fn word(byte: u8) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        word[31] = byte;
+        word
+    }
+
+    fn state() -> EvmState {
+        let mut state = EvmState::default();
+        for byte in 1..=20u8 {
+            let mut account = AccountState {
+                nonce: byte as u64,
+                balance: word(byte),
+                ..Default::default()
+            };
+            for slot in 0..byte {
+                account.storage.insert(word(slot), word(slot + 1));
+            }
+            state.accounts.insert([byte; 20], account);
+        }
+        state
+    }
+
+    #[test]
+    fn proof_against_known_root() {
+        let mut trie = MerkleTrie::new();
+        trie.insert(b"doe", b"reindeer".to_vec());
+        trie.insert(b"dog", b"puppy".to_vec());
+        trie.insert(b"dogglesworth", b"cat".to_vec());
+        let root: [u8; 32] =
+            hex::decode("8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3")
+                .unwrap()
+                .try_into()
+                .unwrap();
+
+        let proof = trie.proof(b"dog");
+        assert_eq!(verify_proof(root, b"dog", &proof).unwrap(), Some(b"puppy".to_vec()));
+        // 不存在的key由同一条路径证明
+        let proof = trie.proof(b"dot");
+        assert_eq!(verify_proof(root, b"dot", &proof).unwrap(), None);
+        // 缺少根节点
+        assert!(verify_proof(root, b"dog", &[]).is_err());
+    }
+
+    #[test]
+    fn account_and_storage_proofs() {
+        let mut state = state();
+        let root = state.state_root().unwrap();
+        let proof = state.get_proof([7u8; 20], &[word(0), word(6), word(100)]).unwrap();
+        assert_eq!(proof.nonce, 7);
+        assert_eq!(proof.storage_proof[1].value, word(7));
+        assert_eq!(proof.storage_proof[2].value, [0u8; 32]);
+        proof.verify(root).unwrap();
+
+        // 不存在的账户
+        let missing = state.get_proof([0xeeu8; 20], &[word(0)]).unwrap();
+        missing.verify(root).unwrap();
+    }
+
+    #[test]
+    fn tampered_proofs_fail() {
+        let mut state = state();
+        let root = state.state_root().unwrap();
+        let proof = state.get_proof([7u8; 20], &[word(3)]).unwrap();
+
+        let mut tampered = proof.clone();
+        tampered.balance = word(8);
+        assert!(tampered.verify(root).is_err());
+
+        let mut tampered = proof.clone();
+        tampered.storage_proof[0].value = word(9);
+        assert!(tampered.verify(root).is_err());
+
+        // 证明连不到其他根
+        assert!(proof.verify([0x11u8; 32]).is_err());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut state = state();
+        let proof = state.get_proof([3u8; 20], &[word(1), word(2)]).unwrap();
+        let json = proof.to_json();
+        assert_eq!(json["nonce"], "0x3");
+        assert_eq!(AccountProof::from_json(&json).unwrap(), proof);
+    }
+
+    #[test]
+    fn proofs_cover_unread_accounts() {
+        let mut db = MemoryDb::new();
+        let mut expected = EvmState::default();
+        for byte in 1..=20u8 {
+            db.insert_storage([byte; 20], word(1), word(byte));
+            let mut account = AccountState::default();
+            account.storage.insert(word(1), word(byte));
+            expected.accounts.insert([byte; 20], account);
+        }
+
+        // 数据库中的账户没有读入缓存 证明仍以完整的状态根为准
+        let mut state = EvmState::with_database(db);
+        let proof = state.get_proof([7u8; 20], &[word(1)]).unwrap();
+        assert_eq!(proof.storage_proof[0].value, word(7));
+        proof.verify(expected.cached_state_root()).unwrap();
+    }
+
+    #[test]
+    fn proofs_need_listable_database() {
+        let mut state = EvmState::new(Some("http://127.0.0.1:1".to_string())).unwrap();
+        assert_eq!(
+            state.get_proof([7u8; 20], &[word(1)]),
+            Err(RunnerError::IncompleteState)
+        );
+    }
+}
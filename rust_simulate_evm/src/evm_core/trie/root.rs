@@ -0,0 +1,64 @@
+use alloy_rlp::Encodable;
+
+use super::merkle::MerkleTrie;
+
+/// Root hash of the trie holding `entries`.
+///
+/// Later duplicates of a key overwrite earlier ones; empty values delete.
+pub fn trie_root(entries: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>) -> [u8; 32] {
+    let mut trie = MerkleTrie::new();
+    for (key, value) in entries {
+        trie.insert(&key, value);
+    }
+    trie.root_hash()
+}
+
+/// Root of a list trie keyed by `rlp(index)`, as used for transactions and receipts.
+pub fn ordered_trie_root(items: impl IntoIterator<Item = Vec<u8>>) -> [u8; 32] {
+    trie_root(items.into_iter().enumerate().map(|(index, item)| {
+        let mut key = Vec::new();
+        index.encode(&mut key);
+        (key, item)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::trie::encoding::EMPTY_ROOT;
+
+    #[test]
+    fn empty_list() {
+        assert_eq!(ordered_trie_root(Vec::new()), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn transactions_root() {
+        // ethers-core中的区块3 唯一一笔交易的原始rlp
+        let raw = hex::decode(
+            "f865028504a817c80083015f9094dca8ce283150ab773bcbeb8d38289bdb5661de1e808025\
+             a019f2694eb9113656dbea0b925e2e7ceb43df83e601c4116aee9c0dd99130be88\
+             a073e5764b324a4f7679d890a198ba658ba1c8cd36983ff9797e10b1b89dbb448e",
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(ordered_trie_root([raw])),
+            "7270c1c4440180f2bd5215809ee3d545df042b67329499e1ab97eb759d31610d"
+        );
+    }
+
+    #[test]
+    fn index_keys_are_rlp_encoded() {
+        // 下标0编码为0x80 128以上为多字节
+        let items: Vec<Vec<u8>> = (0..200u8).map(|index| vec![index + 1]).collect();
+        let mut expected = MerkleTrie::new();
+        for (index, item) in items.iter().enumerate() {
+            let mut key = Vec::new();
+            index.encode(&mut key);
+            expected.insert(&key, item.clone());
+        }
+        assert_eq!(expected.get(&[0x80]), Some(&[1u8][..]));
+        assert_eq!(expected.get(&[0x81, 0x80]), Some(&[129u8][..]));
+        assert_eq!(ordered_trie_root(items), expected.root_hash());
+    }
+}
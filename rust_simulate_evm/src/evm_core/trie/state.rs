@@ -0,0 +1,219 @@
+/*
+世界状态树 key为keccak(address) value为rlp([nonce, balance, storageRoot, codeHash])
+存储树 key为keccak(slot) value为rlp(去掉前导0的值) 值为0的槽不在树中
+*/
+use alloy_rlp::Encodable;
+use ethers::utils::keccak256;
+
+use crate::evm_core::db::database::Database;
+use crate::evm_core::storage::{AccountState, EvmState, KECCAK_EMPTY};
+use crate::evm_core::utils::error::RunnerError;
+
+use super::encoding::{rlp_bytes, rlp_list, rlp_uint};
+use super::merkle::MerkleTrie;
+
+impl AccountState {
+    /// The storage trie of the slots in `storage`. Slots of a database
+    /// account that were never read are missing, see [`EvmState::load_all`].
+    pub fn storage_trie(&self) -> MerkleTrie {
+        let mut trie = MerkleTrie::new();
+        for (slot, value) in &self.storage {
+            if *value != [0u8; 32] {
+                trie.insert(&keccak256(slot), rlp_uint(value));
+            }
+        }
+        trie
+    }
+
+    pub fn storage_root(&self) -> [u8; 32] {
+        self.storage_trie().root_hash()
+    }
+
+    /// Consensus encoding of the account as stored in the state trie.
+    pub fn rlp_encode(&self, storage_root: [u8; 32]) -> Vec<u8> {
+        let mut nonce = Vec::new();
+        self.nonce.encode(&mut nonce);
+        let code_hash = if self.has_code() {
+            self.code_hash
+        } else {
+            KECCAK_EMPTY
+        };
+        rlp_list(&[
+            nonce,
+            rlp_uint(&self.balance),
+            rlp_bytes(&storage_root),
+            rlp_bytes(&code_hash),
+        ])
+    }
+}
+
+impl EvmState {
+    /// Bring every account and storage slot of the database into the cache,
+    /// so that the cached accounts are the whole state.
+    ///
+    /// Fails with [`RunnerError::IncompleteState`] if the database cannot
+    /// list what it holds, see [`Database::state_keys`].
+    pub fn load_all(&mut self) -> Result<(), RunnerError> {
+        let keys = self.db.state_keys()?.ok_or(RunnerError::IncompleteState)?;
+        for (address, slots) in keys {
+            // 本地删除或替换的账户不再读取数据库
+            if self.load_account(address)? && self.db_accounts.contains(&address) {
+                for slot in slots {
+                    self.sload(address, slot)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The world state trie over the cached accounts.
+    pub fn state_trie(&self) -> MerkleTrie {
+        let mut trie = MerkleTrie::new();
+        for (address, account) in &self.accounts {
+            trie.insert(&keccak256(address), account.rlp_encode(account.storage_root()));
+        }
+        trie
+    }
+
+    /// The world state root, comparable with a block header's `stateRoot`.
+    ///
+    /// Loads the whole database first, so it fails with
+    /// [`RunnerError::IncompleteState`] on a fork.
+    pub fn state_root(&mut self) -> Result<[u8; 32], RunnerError> {
+        self.load_all()?;
+        Ok(self.cached_state_root())
+    }
+
+    /// Root of the cached accounts only, the state root once
+    /// [`EvmState::load_all`] has run.
+    pub fn cached_state_root(&self) -> [u8; 32] {
+        self.state_trie().root_hash()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_core::db::database::AccountInfo;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::trie::encoding::EMPTY_ROOT;
+    use std::collections::HashMap;
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const TOKEN: [u8; 20] = [0x70; 20];
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    fn db() -> MemoryDb {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                balance: value(9),
+                ..Default::default()
+            },
+        );
+        db.insert_code(TOKEN, vec![0x00]);
+        db.insert_storage(TOKEN, value(1), value(7));
+        db.insert_storage(TOKEN, value(2), value(8));
+        db
+    }
+
+    // 不能列出账户的后端 如远程节点
+    struct Remote(MemoryDb);
+
+    impl Database for Remote {
+        fn basic(&mut self, address: [u8; 20]) -> Result<Option<AccountInfo>, RunnerError> {
+            self.0.basic(address)
+        }
+
+        fn code_by_hash(&mut self, code_hash: [u8; 32]) -> Result<Vec<u8>, RunnerError> {
+            self.0.code_by_hash(code_hash)
+        }
+
+        fn storage(&mut self, address: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], RunnerError> {
+            self.0.storage(address, slot)
+        }
+
+        fn block_hash(&mut self, number: u64) -> Result<[u8; 32], RunnerError> {
+            self.0.block_hash(number)
+        }
+    }
+
+    #[test]
+    fn account_encoding() {
+        // rlp crate的测试向量 [5, 0x010efbef67941f79b2, 空存储根, 空代码哈希]
+        let mut balance = [0u8; 32];
+        balance[23..].copy_from_slice(&hex::decode("010efbef67941f79b2").unwrap());
+        let account = AccountState {
+            nonce: 5,
+            balance,
+            storage: HashMap::new(),
+            code_hash: KECCAK_EMPTY,
+        };
+        assert_eq!(
+            hex::encode(account.rlp_encode(account.storage_root())),
+            "f84d0589010efbef67941f79b2a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a0c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+    }
+
+    #[test]
+    fn zero_slots_are_not_stored() {
+        let mut account = AccountState::default();
+        account.storage.insert([1u8; 32], [0u8; 32]);
+        assert_eq!(account.storage_root(), EMPTY_ROOT);
+
+        account.storage.insert([2u8; 32], value(1));
+        let mut expected = MerkleTrie::new();
+        expected.insert(&keccak256([2u8; 32]), vec![0x01]);
+        assert_eq!(account.storage_root(), expected.root_hash());
+    }
+
+    #[test]
+    fn empty_state() {
+        assert_eq!(EvmState::default().state_root(), Ok(EMPTY_ROOT));
+    }
+
+    #[test]
+    fn state_root_covers_unread_accounts() {
+        // 全部读入缓存后的根作为对照
+        let mut loaded = EvmState::with_database(db());
+        for address in [ALICE, TOKEN] {
+            loaded.load_account(address).unwrap();
+        }
+        loaded.sload(TOKEN, value(1)).unwrap();
+        loaded.sload(TOKEN, value(2)).unwrap();
+        let expected = loaded.cached_state_root();
+
+        let mut state = EvmState::with_database(db());
+        state.sload(TOKEN, value(1)).unwrap();
+        assert_ne!(state.cached_state_root(), expected);
+        assert_eq!(state.state_root(), Ok(expected));
+    }
+
+    #[test]
+    fn state_root_keeps_local_changes() {
+        let mut state = EvmState::with_database(db());
+        state.sstore(TOKEN, value(1), value(0)).unwrap();
+        state.replace_account(ALICE, AccountState::default());
+        let root = state.state_root().unwrap();
+
+        // 修改后的值不被数据库中的旧值覆盖
+        assert_eq!(state.sload(TOKEN, value(1)).unwrap(), value(0));
+        assert_eq!(state.sload(TOKEN, value(2)).unwrap(), value(8));
+        assert_eq!(state.accounts[&ALICE].balance, value(0));
+        assert_eq!(state.state_root(), Ok(root));
+    }
+
+    #[test]
+    fn state_root_needs_listable_database() {
+        let mut state = EvmState::with_database(Remote(db()));
+        state.load_account(ALICE).unwrap();
+        assert_eq!(state.state_root(), Err(RunnerError::IncompleteState));
+        assert_ne!(state.cached_state_root(), EMPTY_ROOT);
+    }
+}
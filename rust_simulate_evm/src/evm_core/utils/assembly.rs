@@ -113,8 +113,10 @@ pub fn get_op_code(op_number: u8) -> &'static str {
         0x17 => "OR",
         0x18 => "XOR",
         0x19 => "NOT",
+        0x1a => "BYTE",
         0x1b => "SHL",
         0x1c => "SHR",
+        0x1d => "SAR",
         0x20 => "KECCAK256",
 
         /* ---------------------------- Environment OpCodes ------------------------- */
@@ -143,6 +145,8 @@ pub fn get_op_code(op_number: u8) -> &'static str {
         0x46 => "CHAINID",
         0x47 => "SELFBALANCE",
         0x48 => "BASEFEE",
+        0x49 => "BLOBHASH",
+        0x4a => "BLOBBASEFEE",
 
         /* ------------------------------ Flow OpCodes ------------------------------ */
         0x56 => "JUMP",
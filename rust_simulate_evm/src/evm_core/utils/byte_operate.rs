@@ -5,6 +5,8 @@ use std::str::FromStr;
 /*地址哈希 交易哈希 区块哈希*/
 use primitive_types::{H160, H256};
 
+use super::error::RunnerError;
+
 //为长度不固定的字节数组填充到32字节
 pub fn pad_left(bytes: &[u8]) -> [u8; 32] {
     let mut padded = [0u8; 32];
@@ -101,3 +103,46 @@ pub fn to_h160(str_address: &'static str) -> H160 {
 pub fn to_h256(str_address: &'static str) -> H256 {
     H256::from_str(str_address).unwrap()
 }
+
+/* -------------------------------------------------------------------------- */
+/*                                 Hex parsing                                */
+/* -------------------------------------------------------------------------- */
+//RPC返回的十六进制字符串 数值可省略前导0
+pub fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, RunnerError> {
+    let digits = hex.strip_prefix("0x").unwrap_or(hex);
+    if digits.len() % 2 == 1 {
+        return hex::decode(format!("0{}", digits))
+            .map_err(|_| RunnerError::InvalidHex(hex.to_string()));
+    }
+    hex::decode(digits).map_err(|_| RunnerError::InvalidHex(hex.to_string()))
+}
+
+pub fn hex_to_word(hex: &str) -> Result<[u8; 32], RunnerError> {
+    let bytes = hex_to_bytes(hex)?;
+    if bytes.len() > 32 {
+        return Err(RunnerError::InvalidHex(hex.to_string()));
+    }
+    Ok(pad_left(&bytes))
+}
+
+pub fn hex_to_address(hex: &str) -> Result<[u8; 20], RunnerError> {
+    hex_to_bytes(hex)?
+        .try_into()
+        .map_err(|_| RunnerError::InvalidHex(hex.to_string()))
+}
+
+pub fn hex_to_u64(hex: &str) -> Result<u64, RunnerError> {
+    let word = U256::from_big_endian(&hex_to_word(hex)?);
+    if word > U256::from(u64::MAX) {
+        return Err(RunnerError::InvalidHex(hex.to_string()));
+    }
+    Ok(word.as_u64())
+}
+
+pub fn hex_to_u128(hex: &str) -> Result<u128, RunnerError> {
+    let word = U256::from_big_endian(&hex_to_word(hex)?);
+    if word > U256::from(u128::MAX) {
+        return Err(RunnerError::InvalidHex(hex.to_string()));
+    }
+    Ok(word.as_u128())
+}
@@ -1,58 +1,67 @@
 use crate::{error::RunnerError, byte_operate::u64_to_u256_array};
 use crate::Execute;
 
-use super::super::storage::AccountState;
+use super::super::storage::KECCAK_EMPTY;
 
 /* -------------------------------------------------------------------------- */
 /*                              Account state operation of EVM                */
 /* -------------------------------------------------------------------------- */
+//不存在的账户余额为0
 pub fn get_balance(address: [u8; 20], execute: &mut Execute) -> Result<[u8; 32], RunnerError> {
+    execute.state.load_account(address)?;
     let balance = execute
         .state
         .accounts
         .get(&address)
         .map(|account| account.balance)
-        .ok_or(RunnerError::AccountNotFound);
-    Ok(balance?)
+        .unwrap_or([0u8; 32]);
+    Ok(balance)
 }
 
+//不存在的账户nonce为0
 pub fn get_nonce(address: [u8; 20], execute: &mut Execute) -> Result<[u8; 32], RunnerError> {
+    execute.state.load_account(address)?;
     let nonce = execute
         .state
         .accounts
         .get(&address)
         .map(|account| account.nonce)
-        .ok_or(RunnerError::AccountNotFound);
-    Ok(u64_to_u256_array(nonce?))
+        .unwrap_or(0);
+    Ok(u64_to_u256_array(nonce))
 }
 
 
+//创建空账户 合约账户的nonce由CREATE单独设置
 pub fn init_account(address: [u8; 20], execute: &mut Execute) -> Result<(), RunnerError> {
-    let account = execute.state.accounts.get_mut(&address);
-    match account {
-        Some(_) => Ok(()),
-        None => {
-            execute.state.accounts.insert(
-                address,
-                AccountState {
-                    nonce: 0,
-                    balance: [0; 32],
-                    storage: std::collections::HashMap::new(),
-                    code_hash: [0u8; 32],
-                },
-            );
-            increment_nonce(address, execute)
+    execute.state.load_account(address)?;
+    execute.state.init_account(address);
+    Ok(())
+}
+
+//EIP-1052: 不存在或为空的账户返回0 没有代码的账户返回keccak("")
+pub fn get_code_hash(address: [u8; 20], execute: &mut Execute) -> Result<[u8; 32], RunnerError> {
+    execute.state.load_account(address)?;
+    let code_hash = match execute.state.accounts.get(&address) {
+        Some(account) if !account.is_empty() => {
+            if !account.has_code() {
+                KECCAK_EMPTY
+            } else {
+                account.code_hash
+            }
         }
-    }
+        _ => [0u8; 32],
+    };
+    Ok(code_hash)
 }
 
 pub fn delete_account(address: [u8; 20], execute: &mut Execute) -> Result<(), RunnerError> {
-    execute.state.accounts.remove(&address);
+    execute.state.put_account(address, None, false);
     Ok(())
 }
 
 pub fn increment_nonce(address: [u8; 20], execute: &mut Execute) -> Result<(), RunnerError> {
-    let result = execute.state.accounts.get_mut(&address);
+    execute.state.load_account(address)?;
+    let result = execute.state.account_mut(address);
     let nonce = match result {
         Some(account) => account,
         None => {
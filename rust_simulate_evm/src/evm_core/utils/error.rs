@@ -16,17 +16,53 @@ pub enum RunnerError {
     EmptyByteCode,
     InsufficientBalance,
     OperationNotAllowed,
+    CreateCollision,
 
     // Flow errors
     StaticCallStateChanged,
     InvalidOpcode(u8),
     InvalidJumpDestination,
+    ReturnDataOutOfBounds,
 
     // Stack errors
     StackTooSmall,
     StackTooDeep,
     StackOverflow,
 
+    // Transaction errors
+    InvalidChainId,
+    NonceMismatch { expected: u64, got: u64 },
+    TxGasLimitExceedsBlock,
+    IntrinsicGasTooLow,
+    FeeCapTooLow,
+    TipAboveFeeCap,
+    SenderNotEoa,
+    TransactionTypeNotSupported(u8),
+    TransactionDecodeFailed(String),
+    InvalidSignature,
+    EmptyBlobs,
+    InvalidBlobVersionedHash,
+    TooManyBlobs,
+    BlobFeeCapTooLow,
+    EmptyAuthorizationList,
+    ReplacementUnderpriced,
+    DatabaseError(String),
+    ForkCacheMiss(String),
+    InvalidOverride(String),
+
+    // Block errors
+    InvalidBlock(String),
+    InvalidGenesis(String),
+    BlockGasLimitReached(usize),
+    BlockBlobGasLimitReached(usize),
+    InvalidBlockTransaction(usize, Box<RunnerError>),
+    SystemCallFailed([u8; 20], Box<RunnerError>),
+    InvalidHex(String),
+
+    // Trie errors
+    InvalidProof(String),
+    IncompleteState,
+
     // General execution errors
     Revert(Vec<u8>),
     RevertWithoutData,
@@ -50,6 +86,9 @@ impl fmt::Display for RunnerError {
             RunnerError::CodeNotFound => write!(f, "Trying to access non-existent account code"),
             RunnerError::RevertWithoutData => write!(f, "Execution revert without data"),
             RunnerError::InsufficientBalance => write!(f, "Insufficient balance to transfer"),
+            RunnerError::CreateCollision => {
+                write!(f, "Contract already exists at the create address")
+            }
             RunnerError::InvalidOpcode(op_code) => {
                 write!(f, "Invalid op code 0x{:X}", op_code)
             }
@@ -60,10 +99,74 @@ impl fmt::Display for RunnerError {
                 write!(f, "Op code 0x{:X} not implemented", op_code)
             }
             RunnerError::InvalidJumpDestination => write!(f, "Invalid jump destination"),
+            RunnerError::ReturnDataOutOfBounds => {
+                write!(f, "Attempted to read out of return data bounds")
+            }
             RunnerError::Revert(data) => {
                 let hex = super::debug::vec_to_hex_string(data.to_owned());
                 write!(f, "Execution revert with data: {}", hex)
             },
+            RunnerError::InvalidChainId => write!(f, "Transaction chain id does not match"),
+            RunnerError::NonceMismatch { expected, got } => {
+                write!(f, "Nonce mismatch: expected {}, got {}", expected, got)
+            }
+            RunnerError::TxGasLimitExceedsBlock => {
+                write!(f, "Transaction gas limit exceeds block gas limit")
+            }
+            RunnerError::IntrinsicGasTooLow => {
+                write!(f, "Gas limit is lower than intrinsic gas")
+            }
+            RunnerError::FeeCapTooLow => write!(f, "Max fee per gas is lower than base fee"),
+            RunnerError::TipAboveFeeCap => {
+                write!(f, "Max priority fee per gas is higher than max fee per gas")
+            }
+            RunnerError::SenderNotEoa => write!(f, "Transaction sender has deployed code"),
+            RunnerError::TransactionTypeNotSupported(tx_type) => {
+                write!(f, "Transaction type 0x{:X} not supported", tx_type)
+            }
+            RunnerError::TransactionDecodeFailed(reason) => {
+                write!(f, "Failed to decode transaction: {}", reason)
+            }
+            RunnerError::InvalidSignature => write!(f, "Invalid transaction signature"),
+            RunnerError::EmptyBlobs => write!(f, "Blob transaction carries no blobs"),
+            RunnerError::InvalidBlobVersionedHash => {
+                write!(f, "Blob versioned hash has an unsupported version")
+            }
+            RunnerError::TooManyBlobs => write!(f, "Blob transaction exceeds the blob gas limit"),
+            RunnerError::BlobFeeCapTooLow => {
+                write!(f, "Max fee per blob gas is lower than blob base fee")
+            }
+            RunnerError::BlockBlobGasLimitReached(index) => {
+                write!(f, "Transaction {} exceeds the remaining block blob gas", index)
+            }
+            RunnerError::EmptyAuthorizationList => {
+                write!(f, "Set-code transaction has an empty authorization list")
+            }
+            RunnerError::ReplacementUnderpriced => {
+                write!(f, "Replacement transaction does not raise the fees enough")
+            }
+            RunnerError::DatabaseError(reason) => write!(f, "Database error: {}", reason),
+            RunnerError::InvalidOverride(reason) => write!(f, "Invalid state override: {}", reason),
+            RunnerError::ForkCacheMiss(missing) => {
+                write!(f, "Fork cache has no {} and there is no remote to fetch it", missing)
+            }
+            RunnerError::InvalidBlock(reason) => write!(f, "Invalid block: {}", reason),
+            RunnerError::InvalidGenesis(reason) => write!(f, "Invalid genesis: {}", reason),
+            RunnerError::BlockGasLimitReached(index) => {
+                write!(f, "Transaction {} exceeds the remaining block gas", index)
+            }
+            RunnerError::InvalidBlockTransaction(index, error) => {
+                write!(f, "Invalid transaction {} in block: {}", index, error)
+            }
+            RunnerError::SystemCallFailed(address, error) => {
+                let address = super::debug::to_hex_address(*address);
+                write!(f, "System call to {} failed: {}", address, error)
+            }
+            RunnerError::InvalidHex(hex) => write!(f, "Invalid hex string {}", hex),
+            RunnerError::InvalidProof(reason) => write!(f, "Invalid merkle proof: {}", reason),
+            RunnerError::IncompleteState => {
+                write!(f, "Database cannot list its accounts, the state is only partly known")
+            }
             RunnerError::OutOfGas => write!(f, "OutOfGas to call function"),
             RunnerError::StorageRetrievalFailed => write!(f, "StorageRetrievalFailed"),
             RunnerError::EmptyCode => write!(f, " EmptyCode"),
@@ -84,11 +187,44 @@ impl PartialEq for RunnerError {
             | (CodeNotFound, CodeNotFound)
             | (EmptyByteCode, EmptyByteCode)
             | (InsufficientBalance, InsufficientBalance)
+            | (CreateCollision, CreateCollision)
             | (StaticCallStateChanged, StaticCallStateChanged)
             | (StackTooSmall, StackTooSmall)
             | (InvalidJumpDestination, InvalidJumpDestination)
+            | (ReturnDataOutOfBounds, ReturnDataOutOfBounds)
             | (StackTooDeep, StackTooDeep)
-            | (RevertWithoutData, RevertWithoutData) => true,
+            | (RevertWithoutData, RevertWithoutData)
+            | (InvalidChainId, InvalidChainId)
+            | (TxGasLimitExceedsBlock, TxGasLimitExceedsBlock)
+            | (IntrinsicGasTooLow, IntrinsicGasTooLow)
+            | (FeeCapTooLow, FeeCapTooLow)
+            | (TipAboveFeeCap, TipAboveFeeCap)
+            | (SenderNotEoa, SenderNotEoa)
+            | (InvalidSignature, InvalidSignature)
+            | (EmptyBlobs, EmptyBlobs)
+            | (InvalidBlobVersionedHash, InvalidBlobVersionedHash)
+            | (TooManyBlobs, TooManyBlobs)
+            | (BlobFeeCapTooLow, BlobFeeCapTooLow)
+            | (EmptyAuthorizationList, EmptyAuthorizationList)
+            | (ReplacementUnderpriced, ReplacementUnderpriced)
+            | (IncompleteState, IncompleteState) => true,
+            (
+                NonceMismatch { expected: a, got: b },
+                NonceMismatch { expected: c, got: d },
+            ) => a == c && b == d,
+            (TransactionTypeNotSupported(a), TransactionTypeNotSupported(b)) => a == b,
+            (TransactionDecodeFailed(a), TransactionDecodeFailed(b)) => a == b,
+            (DatabaseError(a), DatabaseError(b))
+            | (ForkCacheMiss(a), ForkCacheMiss(b))
+            | (InvalidOverride(a), InvalidOverride(b))
+            | (InvalidBlock(a), InvalidBlock(b))
+            | (InvalidGenesis(a), InvalidGenesis(b)) => a == b,
+            (BlockGasLimitReached(a), BlockGasLimitReached(b)) => a == b,
+            (BlockBlobGasLimitReached(a), BlockBlobGasLimitReached(b)) => a == b,
+            (InvalidBlockTransaction(a, c), InvalidBlockTransaction(b, d)) => a == b && c == d,
+            (InvalidHex(a), InvalidHex(b)) => a == b,
+            (SystemCallFailed(a, c), SystemCallFailed(b, d)) => a == b && c == d,
+            (InvalidProof(a), InvalidProof(b)) => a == b,
             (InvalidOpcode(a), InvalidOpcode(b)) => a == b,
             (NotImplemented(a), NotImplemented(b)) => a == b,
             (Revert(a), Revert(b)) => a == b,
@@ -7,3 +7,5 @@ pub mod assembly;
 pub mod enviroment;
 
 pub mod error;
+
+pub mod serde_hex;
@@ -0,0 +1,297 @@
+/*
+serde十六进制编码 配合#[serde(with = "serde_hex::...")]使用
+字节串 地址和哈希编码为0x开头的十六进制字符串 作为map的key时同样如此
+map按key排序输出 便于保存的状态做diff
+*/
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use alloy_primitives::B256;
+
+use super::byte_operate::{hex_to_address, hex_to_bytes, hex_to_u64, hex_to_word};
+use super::error::RunnerError;
+
+/// Values written as hex strings.
+pub trait Hex: Sized {
+    fn to_hex(&self) -> String;
+    fn from_hex(hex: &str) -> Result<Self, RunnerError>;
+}
+
+impl Hex for [u8; 20] {
+    fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self))
+    }
+
+    fn from_hex(hex: &str) -> Result<Self, RunnerError> {
+        hex_to_address(hex)
+    }
+}
+
+impl Hex for [u8; 32] {
+    fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self))
+    }
+
+    fn from_hex(hex: &str) -> Result<Self, RunnerError> {
+        hex_to_word(hex)
+    }
+}
+
+impl Hex for Vec<u8> {
+    fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self))
+    }
+
+    fn from_hex(hex: &str) -> Result<Self, RunnerError> {
+        hex_to_bytes(hex)
+    }
+}
+
+impl Hex for B256 {
+    fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.as_slice()))
+    }
+
+    fn from_hex(hex: &str) -> Result<Self, RunnerError> {
+        hex_to_word(hex).map(B256::from)
+    }
+}
+
+//数值按JSON-RPC规则去掉前导0
+impl Hex for u64 {
+    fn to_hex(&self) -> String {
+        format!("{:#x}", self)
+    }
+
+    fn from_hex(hex: &str) -> Result<Self, RunnerError> {
+        hex_to_u64(hex)
+    }
+}
+
+fn parse<T: Hex, E: serde::de::Error>(hex: &str) -> Result<T, E> {
+    T::from_hex(hex).map_err(E::custom)
+}
+
+/// A single [`Hex`] value.
+pub mod bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Hex;
+
+    pub fn serialize<T: Hex, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_hex())
+    }
+
+    pub fn deserialize<'de, T: Hex, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        super::parse(&String::deserialize(deserializer)?)
+    }
+}
+
+/// An optional [`Hex`] value, `null` when absent.
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Hex;
+
+    pub fn serialize<T: Hex, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.as_ref().map(Hex::to_hex).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: Hex, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| super::parse(&hex))
+            .transpose()
+    }
+}
+
+/// A list of [`Hex`] values.
+pub mod vec {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Hex;
+
+    pub fn serialize<T: Hex, S: Serializer>(values: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(Hex::to_hex))
+    }
+
+    pub fn deserialize<'de, T: Hex, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<T>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| super::parse(hex))
+            .collect()
+    }
+}
+
+/// A set of [`Hex`] values, written as a sorted list.
+pub mod set {
+    use std::collections::HashSet;
+    use std::hash::Hash;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Hex;
+
+    pub fn serialize<T: Hex, S: Serializer>(values: &HashSet<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut values: Vec<_> = values.iter().map(Hex::to_hex).collect();
+        values.sort();
+        serializer.collect_seq(values)
+    }
+
+    pub fn deserialize<'de, T: Hex + Eq + Hash, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashSet<T>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| super::parse(hex))
+            .collect()
+    }
+}
+
+/// A map from [`Hex`] keys to [`Hex`] values.
+pub mod map {
+    use super::*;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<K: Hex, V: Hex, S: Serializer>(
+        map: &HashMap<K, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let map: BTreeMap<_, _> = map.iter().map(|(key, value)| (key.to_hex(), value.to_hex())).collect();
+        serializer.collect_map(map)
+    }
+
+    pub fn deserialize<'de, K: Hex + Eq + Hash, V: Hex, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<K, V>, D::Error> {
+        HashMap::<String, String>::deserialize(deserializer)?
+            .iter()
+            .map(|(key, value)| Ok((parse(key)?, parse(value)?)))
+            .collect()
+    }
+}
+
+/// A map from [`Hex`] keys to values with their own serde implementation.
+pub mod key_map {
+    use super::*;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<K: Hex, V: Serialize, S: Serializer>(
+        map: &HashMap<K, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let map: BTreeMap<_, _> = map.iter().map(|(key, value)| (key.to_hex(), value)).collect();
+        serializer.collect_map(map)
+    }
+
+    pub fn deserialize<'de, K: Hex + Eq + Hash, V: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<K, V>, D::Error> {
+        HashMap::<String, V>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| Ok((parse(&key)?, value)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::evm_core::context::evm_context::EvmContext;
+    use crate::evm_core::context::spec::SpecId;
+    use crate::evm_core::db::memory::MemoryDb;
+    use crate::evm_core::log::Log;
+    use crate::evm_core::storage::{AccountState, EvmState};
+
+    const ALICE: [u8; 20] = [0xa1; 20];
+    const TOKEN: [u8; 20] = [0x70; 20];
+
+    fn value(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    fn word_hex(byte: u8) -> String {
+        format!("0x{}", hex::encode(value(byte)))
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let mut db = MemoryDb::new();
+        db.insert_account(
+            ALICE,
+            AccountState {
+                nonce: 2,
+                balance: value(9),
+                ..Default::default()
+            },
+        );
+        db.insert_account(TOKEN, AccountState::default());
+        let mut state = EvmState::with_database(db);
+        state.load_account(ALICE).unwrap();
+        state.sstore(ALICE, value(1), value(7)).unwrap();
+        state.put_code_at(TOKEN, vec![0x60, 0x00]).unwrap();
+        state.logs.push(Log::new(TOKEN, vec![value(3)], vec![0xab]));
+
+        let json = serde_json::to_value(&state).unwrap();
+        let alice = &json["accounts"]["0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1"];
+        assert_eq!(alice["nonce"], 2);
+        assert_eq!(alice["balance"], word_hex(9));
+        assert_eq!(alice["storage"][word_hex(1)], word_hex(7));
+        assert_eq!(json["logs"][0]["topics"], json!([word_hex(3)]));
+        assert_eq!(json["logs"][0]["data"], "0xab");
+
+        // 反序列化后不需要数据库即可读取
+        let mut restored: EvmState = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), json);
+        assert_eq!(restored.sload(ALICE, value(1)).unwrap(), value(7));
+        assert_eq!(restored.get_code_at(TOKEN), Some(&vec![0x60, 0x00]));
+        assert_eq!(restored.cached_state_root(), state.cached_state_root());
+    }
+
+    #[test]
+    fn context_round_trip() {
+        let mut context = EvmContext::new();
+        context.chain_id = Some(value(1));
+        context.coinbase = Some(ALICE);
+        context.prevrandao = Some(value(5).into());
+        context.excess_blob_gas = Some(10);
+        context.block_hashes.insert(99, value(4));
+        context.spec_id = SpecId::Cancun;
+
+        let json = serde_json::to_value(&context).unwrap();
+        assert_eq!(json["chainId"], word_hex(1));
+        assert_eq!(json["blockhash"], Value::Null);
+        assert_eq!(json["blockHashes"]["0x63"], word_hex(4));
+
+        let restored: EvmContext = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), json);
+        assert_eq!(restored.coinbase, Some(ALICE));
+        assert_eq!(restored.block_hashes[&99], value(4));
+    }
+
+    #[test]
+    fn invalid_hex_is_rejected() {
+        let account = |balance: &str| {
+            json!({ "nonce": 0, "balance": balance, "storage": {}, "codeHash": word_hex(0) })
+        };
+        assert!(serde_json::from_value::<AccountState>(account(&word_hex(1))).is_ok());
+        assert!(serde_json::from_value::<AccountState>(account("0xzz")).is_err());
+
+        let short_address = json!({
+            "address": "0xa1",
+            "topics": [],
+            "data": "0x",
+            "blockNumber": 0,
+            "transactionHash": word_hex(0),
+            "transactionIndex": 0,
+            "logIndex": 0,
+        });
+        assert!(serde_json::from_value::<Log>(short_address).is_err());
+    }
+}
@@ -5,12 +5,66 @@ pub use evm_core::memory::Memory;
 pub use evm_core::opcodes;
 pub use evm_core::execute::Execute;
 pub use evm_core::stack::Stack;
-pub use evm_core::storage::EvmState;
+pub use evm_core::log::Log;
+pub use evm_core::storage::{
+    delegated_address, delegation_designator, AccountState, EvmState, SnapshotId,
+    DELEGATION_PREFIX, KECCAK_EMPTY,
+};
+pub use evm_core::context::evm_context::EvmContext;
+pub use evm_core::context::spec::SpecId;
+pub use evm_core::gas::blob::{blob_base_fee, calc_excess_blob_gas, fake_exponential};
+
+/* ------------------------------- Transaction ------------------------------ */
+pub use evm_core::transaction::authorization::apply_authorizations;
+pub use evm_core::transaction::decode::{
+    decode_raw_transaction, decode_raw_transaction_hex, transact_raw,
+};
+pub use evm_core::transaction::transact::{intrinsic_gas, transact, TransactionResult};
+pub use evm_core::transaction::types::{
+    AccessListItem, Authorization, Transaction, TxEip1559, TxEip2930, TxEip4844, TxEip7702,
+    TxLegacy,
+};
+
+/* ---------------------------------- Block --------------------------------- */
+pub use evm_core::block::executor::{
+    execute_block, execute_block_with_context, BlockBuilder, BlockResult,
+};
+pub use evm_core::block::json::parse_transaction;
+pub use evm_core::block::receipt::{bloom_contains, logs_bloom, receipts_root, Bloom, Receipt};
+pub use evm_core::block::system::{
+    apply_beacon_root_call, apply_block_hash_call, apply_withdrawals, system_call,
+    BEACON_ROOTS_ADDRESS, HISTORY_STORAGE_ADDRESS, SYSTEM_ADDRESS,
+};
+pub use evm_core::block::types::{Block, BlockHeader, BlockTransaction, Withdrawal};
+
+/* ---------------------------------- Chain --------------------------------- */
+pub use evm_core::chain::local::Chain;
+pub use evm_core::chain::pool::TransactionPool;
+pub use evm_core::chain::types::{MinedBlock, MiningMode};
+
+/* ---------------------------------- State --------------------------------- */
+pub use evm_core::state::diff::{AccountDiff, AccountStatus, Change, StateDiff};
+pub use evm_core::state::overlay::{AccountOverride, BlockOverrides, OverlayDb, StateOverrides};
+
+/* -------------------------------- Database -------------------------------- */
+pub use evm_core::db::cache::{CacheDb, ForkCache};
+pub use evm_core::db::database::{AccountInfo, Database, StateKeys};
+pub use evm_core::db::file::FileDb;
+pub use evm_core::db::memory::MemoryDb;
+pub use evm_core::db::prefetch::{predict_accounts, predict_storage_keys};
+pub use evm_core::db::provider::ProviderDb;
+
+/* ---------------------------------- Trie ---------------------------------- */
+pub use evm_core::trie::encoding::EMPTY_ROOT;
+pub use evm_core::trie::merkle::{MerkleTrie, Node};
+pub use evm_core::trie::proof::{verify_proof, AccountProof, StorageProof};
+pub use evm_core::trie::root::{ordered_trie_root, trie_root};
 
 /* ---------------------------------- Utils --------------------------------- */
 pub use evm_core::utils::byte_operate;
 pub use evm_core::utils::debug;
 pub use evm_core::utils::enviroment;
 pub use evm_core::utils::error;
+pub use evm_core::utils::serde_hex;
 
 
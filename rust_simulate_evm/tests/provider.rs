@@ -0,0 +1,308 @@
+/*
+ProviderDb和CacheDb的集成测试
+本地启动一个最小的JSON-RPC节点 只实现ProviderDb用到的方法 并记录收到的请求
+固定区块0x64与最新区块的存储不同 用于检查请求是否固定在同一区块
+*/
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use ethers::types::U256;
+use ethers::utils::keccak256;
+use serde_json::{json, Value};
+
+use rust_simulate_evm::error::RunnerError;
+use rust_simulate_evm::{
+    transact, CacheDb, Database, EvmContext, EvmState, ProviderDb, Transaction, TxLegacy,
+};
+
+const ALICE: [u8; 20] = [0xa1; 20];
+const TOKEN: [u8; 20] = [0x70; 20];
+const NOBODY: [u8; 20] = [0x0b; 20];
+
+//PUSH1 1 SLOAD PUSH1 2 SSTORE STOP 把槽1复制到槽2
+const TOKEN_CODE: [u8; 7] = [0x60, 0x01, 0x54, 0x60, 0x02, 0x55, 0x00];
+
+const CHAIN_ID: u64 = 1;
+const BLOCK: u64 = 0x64;
+
+/* -------------------------------------------------------------------------- */
+/*                                  Mock node                                 */
+/* -------------------------------------------------------------------------- */
+/// JSON-RPC requests received, as `(method, params)`.
+type Requests = Arc<Mutex<Vec<(String, Value)>>>;
+
+struct MockNode {
+    url: String,
+    requests: Requests,
+}
+
+impl MockNode {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Requests::default();
+        let log = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let log = log.clone();
+                thread::spawn(move || serve(stream, log));
+            }
+        });
+        Self { url, requests }
+    }
+
+    fn count(&self, method: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| name == method)
+            .count()
+    }
+
+    fn total(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+
+    //状态查询的最后一个参数为区块
+    fn blocks(&self) -> Vec<Value> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| name != "eth_chainId" && name != "eth_blockNumber")
+            .filter(|(name, _)| name != "eth_getBlockByNumber")
+            .filter_map(|(_, params)| params.as_array().and_then(|params| params.last()).cloned())
+            .collect()
+    }
+}
+
+//一个连接上可以有多个keep-alive请求
+fn serve(stream: TcpStream, requests: Requests) {
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0u8; length];
+        reader.read_exact(&mut body).unwrap();
+
+        let request: Value = serde_json::from_slice(&body).unwrap();
+        let response = match request {
+            Value::Array(batch) => {
+                Value::Array(batch.iter().map(|request| reply(request, &requests)).collect())
+            }
+            request => reply(&request, &requests),
+        };
+        let response = response.to_string();
+        write!(
+            writer,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            response.len(),
+            response
+        )
+        .unwrap();
+    }
+}
+
+fn reply(request: &Value, requests: &Requests) -> Value {
+    let method = request["method"].as_str().unwrap().to_string();
+    let params = request["params"].clone();
+    requests.lock().unwrap().push((method.clone(), params.clone()));
+
+    let address = params[0].as_str().unwrap_or_default();
+    let pinned = params.as_array().and_then(|params| params.last()) == Some(&json!("0x64"));
+    let result = match method.as_str() {
+        "eth_chainId" => json!(format!("{:#x}", CHAIN_ID)),
+        "eth_blockNumber" => json!(format!("{:#x}", BLOCK)),
+        "eth_getBalance" if address == hex(&ALICE) => json!("0xde0b6b3a7640000"),
+        "eth_getBalance" => json!("0x0"),
+        "eth_getTransactionCount" if address == hex(&ALICE) => json!("0x3"),
+        "eth_getTransactionCount" => json!("0x0"),
+        "eth_getCode" if address == hex(&TOKEN) => json!(hex(&TOKEN_CODE)),
+        "eth_getCode" => json!("0x"),
+        // 最新区块上槽1已经改变
+        "eth_getStorageAt" if address == hex(&TOKEN) && quantity(&params[1]) == U256::one() => {
+            json!(hex(&slot(if pinned { 7 } else { 9 })))
+        }
+        "eth_getStorageAt" => json!(hex(&[0u8; 32])),
+        "eth_getBlockByNumber" => {
+            let number = params[0].as_str().unwrap();
+            json!({ "hash": hex(&keccak256(number)), "number": number })
+        }
+        _ => {
+            return json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": -32601, "message": "method not found" },
+            })
+        }
+    };
+    json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+}
+
+//槽可能以数值或32字节的形式发送
+fn quantity(value: &Value) -> U256 {
+    let text = value.as_str().unwrap_or_default();
+    U256::from_str_radix(text.trim_start_matches("0x"), 16).unwrap_or_default()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn slot(byte: u8) -> [u8; 32] {
+    let mut slot = [0u8; 32];
+    slot[31] = byte;
+    slot
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("minirevm-{}-{}.json", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+//ALICE调用TOKEN 把槽1复制到槽2
+fn copy_slot(state: &mut EvmState) -> bool {
+    let tx = Transaction::Legacy(TxLegacy {
+        from: ALICE,
+        nonce: 3,
+        gas_limit: 100_000,
+        to: Some(TOKEN),
+        ..Default::default()
+    });
+    transact(state, &EvmContext::new(), tx).unwrap().success
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[test]
+fn provider_reads_pinned_block() {
+    let node = MockNode::start();
+    let mut db = ProviderDb::pinned(&node.url, None).unwrap();
+    assert_eq!(db.block_number, Some(BLOCK));
+    assert_eq!(db.chain_id().unwrap(), CHAIN_ID);
+
+    let alice = db.basic(ALICE).unwrap().unwrap();
+    assert_eq!(alice.nonce, 3);
+    assert_eq!(alice.balance, {
+        let mut balance = [0u8; 32];
+        balance[24..].copy_from_slice(&0xde0b6b3a7640000u64.to_be_bytes());
+        balance
+    });
+    let token = db.basic(TOKEN).unwrap().unwrap();
+    assert_eq!(token.code_hash, keccak256(TOKEN_CODE));
+    assert_eq!(token.code, Some(TOKEN_CODE.to_vec()));
+    // 空账户按不存在处理
+    assert_eq!(db.basic(NOBODY).unwrap(), None);
+    assert_eq!(db.storage(TOKEN, slot(1)).unwrap(), slot(7));
+    assert_eq!(db.block_hash(0x63).unwrap(), keccak256("0x63"));
+
+    assert!(node.blocks().iter().all(|block| block == "0x64"));
+}
+
+#[test]
+fn provider_reads_latest_block() {
+    let node = MockNode::start();
+    let mut db = ProviderDb::new(&node.url).unwrap();
+    assert_eq!(db.storage(TOKEN, slot(1)).unwrap(), slot(9));
+    assert_eq!(node.count("eth_blockNumber"), 0);
+    assert_eq!(node.blocks(), vec![json!("latest")]);
+}
+
+#[test]
+fn provider_errors_are_reported() {
+    assert!(matches!(ProviderDb::new("not a url"), Err(RunnerError::DatabaseError(_))));
+
+    // 没有节点监听的端口
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut db = ProviderDb::new(&format!("http://127.0.0.1:{}", port)).unwrap();
+    assert!(matches!(db.basic(ALICE), Err(RunnerError::DatabaseError(_))));
+}
+
+#[test]
+fn prefetch_fetches_once() {
+    let node = MockNode::start();
+    let mut db = ProviderDb::pinned(&node.url, Some(BLOCK)).unwrap();
+    db.prefetch(&[ALICE, TOKEN], &[(TOKEN, slot(1))]);
+    let fetched = node.total();
+    assert_eq!(node.count("eth_getStorageAt"), 1);
+
+    assert_eq!(db.basic(ALICE).unwrap().unwrap().nonce, 3);
+    assert!(db.basic(TOKEN).unwrap().is_some());
+    assert_eq!(db.storage(TOKEN, slot(1)).unwrap(), slot(7));
+    assert_eq!(node.total(), fetched);
+}
+
+#[test]
+fn transact_on_fork() {
+    let node = MockNode::start();
+    let mut state = EvmState::fork(&node.url, Some(BLOCK)).unwrap();
+    assert!(copy_slot(&mut state));
+    assert_eq!(state.sload(TOKEN, slot(2)).unwrap(), slot(7));
+    assert_eq!(state.accounts[&ALICE].nonce, 4);
+
+    // 每个值只向节点查询一次
+    assert_eq!(state.sload(TOKEN, slot(1)).unwrap(), slot(7));
+    let requests = node.requests.lock().unwrap().clone();
+    for (i, request) in requests.iter().enumerate() {
+        assert!(!requests[..i].contains(request), "{:?} requested twice", request);
+    }
+    assert_eq!(node.count("eth_getStorageAt"), 2);
+}
+
+#[test]
+fn record_then_replay_fork() {
+    let node = MockNode::start();
+    let path = temp_path("fork");
+    let mut recorded = EvmState::fork_recorded(&node.url, None, &path).unwrap();
+    assert!(copy_slot(&mut recorded));
+    let hash = recorded.block_hash(0x63).unwrap();
+    // 丢弃状态时写入缓存文件
+    drop(recorded);
+
+    let requests = node.total();
+    let mut replayed = EvmState::fork_replayed(&path).unwrap();
+    assert!(copy_slot(&mut replayed));
+    assert_eq!(replayed.sload(TOKEN, slot(2)).unwrap(), slot(7));
+    assert_eq!(replayed.block_hash(0x63).unwrap(), hash);
+    assert_eq!(node.total(), requests);
+
+    // 记录中没有的值
+    assert!(matches!(replayed.block_hash(0x62), Err(RunnerError::ForkCacheMiss(_))));
+    assert!(matches!(replayed.load_account(NOBODY), Err(RunnerError::ForkCacheMiss(_))));
+
+    // 继续记录同一分叉只查询缺少的值
+    let provider = ProviderDb::pinned(&node.url, Some(BLOCK)).unwrap();
+    let mut db = CacheDb::record(provider, CHAIN_ID, BLOCK, &path).unwrap();
+    db.basic(ALICE).unwrap();
+    db.basic(NOBODY).unwrap();
+    assert_eq!(node.total(), requests + 3);
+    drop(db);
+    assert!(CacheDb::replay_file(&path).unwrap().basic(NOBODY).unwrap().is_none());
+
+    let other = CacheDb::record(ProviderDb::new(&node.url).unwrap(), CHAIN_ID, BLOCK + 1, &path);
+    assert!(matches!(other, Err(RunnerError::DatabaseError(_))));
+    let _ = std::fs::remove_file(&path);
+}